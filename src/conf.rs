@@ -1,4 +1,10 @@
-use crate::provider::{EcbConf, IexConf};
+use crate::{
+    provider::{EcbConf, IexConf},
+    service::{
+        asset::AssetConf, exchange_rate::RateCacheConf, recurring::RecurringConf,
+        snapshot::SnapshotConf, webhook::WebhookConf,
+    },
+};
 use anyhow::{ensure, Context, Result};
 use figment::{
     providers::{Format, Toml},
@@ -14,6 +20,11 @@ use std::{
 pub struct Conf {
     pub db_url: String,
     pub providers: ProvidersConf,
+    pub rate_cache: RateCacheConf,
+    pub assets: AssetConf,
+    pub webhooks: WebhookConf,
+    pub snapshots: SnapshotConf,
+    pub recurring: RecurringConf,
     pub migrations: Vec<Migration>,
 }
 
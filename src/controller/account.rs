@@ -0,0 +1,282 @@
+use crate::{
+    model::{Account, AccountType, ApiError, ApiResult, CostBasisMethod, Id, User},
+    service::AccountService,
+};
+use chrono::NaiveDate;
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    name: String,
+    #[serde(rename = "type")]
+    account_type: AccountType,
+    currency: String,
+    opened_at: NaiveDate,
+    closed_at: Option<NaiveDate>,
+    #[serde(default)]
+    cost_basis_method: CostBasisMethod,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/accounts")]
+pub async fn get(service: &State<AccountService>, user: User) -> ApiResult<Vec<Account>> {
+    match service.select_by_username(&user.username) {
+        Ok(accounts) => ApiResult::new(200, accounts),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/accounts/<id>")]
+pub async fn get_by_id(id: Id, service: &State<AccountService>, user: User) -> ApiResult<Account> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/accounts", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<AccountService>,
+    user: User,
+) -> ApiResult<Account> {
+    let account = Account {
+        id: Id::new(),
+        username: user.username.clone(),
+        name: input.name.clone(),
+        account_type: input.account_type,
+        currency: input.currency.clone(),
+        opened_at: input.opened_at,
+        closed_at: input.closed_at,
+        cost_basis_method: input.cost_basis_method,
+    };
+
+    if let Err(e) = service.validate(&account) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&account) {
+        return e.into();
+    }
+
+    ApiResult::new(201, account)
+}
+
+#[put("/accounts/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<AccountService>,
+    user: User,
+) -> ApiResult<Account> {
+    let account = match service.select_owned(&id, &user.username) {
+        Ok(Some(account)) => account,
+        res => return res.into(),
+    };
+
+    let account = Account {
+        name: input.name.clone(),
+        account_type: input.account_type,
+        currency: input.currency.clone(),
+        opened_at: input.opened_at,
+        closed_at: input.closed_at,
+        cost_basis_method: input.cost_basis_method,
+        ..account
+    };
+
+    if let Err(e) = service.validate(&account) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&account) {
+        return e.into();
+    }
+
+    ApiResult::new(200, account)
+}
+
+#[delete("/accounts/<id>")]
+pub async fn delete(id: Id, service: &State<AccountService>, user: User) -> ApiResult<Account> {
+    let account = match service.select_owned(&id, &user.username) {
+        Ok(Some(account)) => account,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&account.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, account)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::account::PostInput,
+        model::{Account, AccountType, CostBasisMethod, Goal, Id},
+        repository::{AccountRepository, GoalRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+    use rocket::http::Status;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let account = account("test");
+        repo.insert(&account)?;
+        repo.insert(&self::account("test2"))?;
+        let res = client.get("/accounts").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![account], res.into_json::<Vec<Account>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let account = account("test");
+        repo.insert(&account)?;
+        let res = client.get(format!("/accounts/{}", account.id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(account, res.into_json::<Account>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let account = account("test2");
+        repo.insert(&account)?;
+        let res = client.get(format!("/accounts/{}", account.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let res = client.post("/accounts").json(&input()).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let account = res.into_json::<Account>().unwrap();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        assert_eq!(Some(account.clone()), repo.select_by_id(&account.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_unknown_currency() {
+        let client = client();
+        let input = PostInput {
+            currency: "TST".into(),
+            ..input()
+        };
+        let res = client.post("/accounts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn post_closed_before_opened() {
+        let client = client();
+        let input = PostInput {
+            closed_at: Some(NaiveDate::from_ymd(2020, 1, 1)),
+            cost_basis_method: CostBasisMethod::Fifo,
+            ..input()
+        };
+        let res = client.post("/accounts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let account = account("test");
+        repo.insert(&account)?;
+        let input = PostInput {
+            name: "Savings".into(),
+            account_type: AccountType::Bank,
+            ..input()
+        };
+        let res = client
+            .put(format!("/accounts/{}", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let account = repo.select_by_id(&account.id)?.unwrap();
+        assert_eq!("Savings", account.name);
+        assert_eq!(AccountType::Bank, account.account_type);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let account = account("test");
+        repo.insert(&account)?;
+        let res = client
+            .delete(format!("/accounts/{}", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(repo.select_by_id(&account.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_goal_account() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        let goal_repo = client.rocket().state::<GoalRepository>().unwrap();
+        let account = account("test");
+        let kept = self::account("test");
+        repo.insert(&account)?;
+        repo.insert(&kept)?;
+        let goal = Goal {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Car".into(),
+            target: 2000.0,
+            currency: "EUR".into(),
+            deadline: None,
+            account_ids: vec![account.id.clone(), kept.id.clone()],
+        };
+        goal_repo.insert(&goal)?;
+        let res = client
+            .delete(format!("/accounts/{}", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![kept.id],
+            goal_repo.select_by_id(&goal.id)?.unwrap().account_ids
+        );
+        Ok(())
+    }
+
+    fn input() -> PostInput {
+        PostInput {
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        }
+    }
+
+    fn account(username: &str) -> Account {
+        Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    model::{Alert, AlertCondition, AlertEvent, ApiError, ApiResult, Id, User},
+    service::AlertService,
+};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    quote: String,
+    base: String,
+    condition: AlertCondition,
+    threshold: f64,
+    notifier: String,
+    target: String,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/alerts")]
+pub async fn get(service: &State<AlertService>, user: User) -> ApiResult<Vec<Alert>> {
+    match service.select_by_username(&user.username) {
+        Ok(alerts) => ApiResult::new(200, alerts),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/alerts/<id>")]
+pub async fn get_by_id(id: Id, service: &State<AlertService>, user: User) -> ApiResult<Alert> {
+    select_owned(service, &id, &user).into()
+}
+
+#[post("/alerts", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<AlertService>,
+    user: User,
+) -> ApiResult<Alert> {
+    let alert = Alert {
+        id: Id::new(),
+        username: user.username.clone(),
+        quote: input.quote.clone(),
+        base: input.base.clone(),
+        condition: input.condition,
+        threshold: input.threshold,
+        notifier: input.notifier.clone(),
+        target: input.target.clone(),
+        last_rate: None,
+    };
+
+    if let Err(e) = service.validate(&alert) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&alert) {
+        return e.into();
+    }
+
+    ApiResult::new(201, alert)
+}
+
+#[put("/alerts/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<AlertService>,
+    user: User,
+) -> ApiResult<Alert> {
+    let alert = match select_owned(service, &id, &user) {
+        Ok(Some(alert)) => alert,
+        res => return res.into(),
+    };
+
+    let alert = Alert {
+        quote: input.quote.clone(),
+        base: input.base.clone(),
+        condition: input.condition,
+        threshold: input.threshold,
+        notifier: input.notifier.clone(),
+        target: input.target.clone(),
+        last_rate: None,
+        ..alert
+    };
+
+    if let Err(e) = service.validate(&alert) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&alert) {
+        return e.into();
+    }
+
+    ApiResult::new(200, alert)
+}
+
+#[delete("/alerts/<id>")]
+pub async fn delete(id: Id, service: &State<AlertService>, user: User) -> ApiResult<Alert> {
+    let alert = match select_owned(service, &id, &user) {
+        Ok(Some(alert)) => alert,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&alert.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, alert)
+}
+
+#[get("/alerts/<id>/events")]
+pub async fn get_events(
+    id: Id,
+    service: &State<AlertService>,
+    user: User,
+) -> ApiResult<Vec<AlertEvent>> {
+    let alert = match select_owned(service, &id, &user) {
+        Ok(Some(alert)) => alert,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.select_events(&alert.id) {
+        Ok(events) => ApiResult::new(200, events),
+        Err(e) => e.into(),
+    }
+}
+
+/// Other users' alerts are treated as non-existent
+fn select_owned(service: &AlertService, id: &Id, user: &User) -> anyhow::Result<Option<Alert>> {
+    Ok(service
+        .select_by_id(id)?
+        .filter(|it| it.username == user.username))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::alert::PostInput,
+        model::{Alert, AlertCondition, AlertEvent, Id, Webhook},
+        repository::{AlertEventRepository, AlertRepository, WebhookRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::Utc;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let alert = alert("test");
+        repo.insert(&alert)?;
+        repo.insert(&self::alert("test2"))?;
+        let res = client.get("/alerts").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![alert], res.into_json::<Vec<Alert>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let alert = alert("test");
+        repo.insert(&alert)?;
+        let res = client.get(format!("/alerts/{}", alert.id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(alert, res.into_json::<Alert>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let alert = alert("test2");
+        repo.insert(&alert)?;
+        let res = client.get(format!("/alerts/{}", alert.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let input = input(&webhook(&client, "test")?);
+        let res = client.post("/alerts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let alert = res.into_json::<Alert>().unwrap();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        assert_eq!(Some(alert.clone()), repo.select_by_id(&alert.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_invalid_notifier() -> Result<()> {
+        let client = client();
+        let input = PostInput {
+            notifier: "carrier_pigeon".into(),
+            ..input(&webhook(&client, "test")?)
+        };
+        let res = client.post("/alerts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_invalid_target() {
+        let client = client();
+        let input = PostInput {
+            target: "https://example.com/hook".into(),
+            ..input(&Id::new())
+        };
+        let res = client.post("/alerts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn post_foreign_target() -> Result<()> {
+        let client = client();
+        let input = input(&webhook(&client, "test2")?);
+        let res = client.post("/alerts").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let alert = alert("test");
+        repo.insert(&alert)?;
+        let input = PostInput {
+            condition: AlertCondition::Below,
+            threshold: 40000.0,
+            ..input(&webhook(&client, "test")?)
+        };
+        let res = client
+            .put(format!("/alerts/{}", alert.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let alert = repo.select_by_id(&alert.id)?.unwrap();
+        assert_eq!(AlertCondition::Below, alert.condition);
+        assert_eq!(40000.0, alert.threshold);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let alert = alert("test");
+        repo.insert(&alert)?;
+        let res = client.delete(format!("/alerts/{}", alert.id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(repo.select_by_id(&alert.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn get_events() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<AlertRepository>().unwrap();
+        let event_repo = client.rocket().state::<AlertEventRepository>().unwrap();
+        let alert = alert("test");
+        repo.insert(&alert)?;
+        let event = AlertEvent {
+            id: Id::new(),
+            alert_id: alert.id.clone(),
+            rate: 51000.0,
+            fired_at: Utc::now(),
+            delivered: true,
+        };
+        event_repo.insert(&event)?;
+        let res = client
+            .get(format!("/alerts/{}/events", alert.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![event], res.into_json::<Vec<AlertEvent>>().unwrap());
+        Ok(())
+    }
+
+    fn webhook(client: &Client, username: &str) -> Result<Id> {
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = Webhook {
+            id: Id::new(),
+            username: username.into(),
+            url: "https://example.com/hook".into(),
+            secret: "secret".into(),
+            events: vec![],
+        };
+        repo.insert(&webhook)?;
+        Ok(webhook.id)
+    }
+
+    fn input(webhook_id: &Id) -> PostInput {
+        PostInput {
+            quote: "BTC".into(),
+            base: "EUR".into(),
+            condition: AlertCondition::Above,
+            threshold: 50000.0,
+            notifier: "webhook".into(),
+            target: webhook_id.to_string(),
+        }
+    }
+
+    fn alert(username: &str) -> Alert {
+        Alert {
+            id: Id::new(),
+            username: username.into(),
+            quote: "BTC".into(),
+            base: "EUR".into(),
+            condition: AlertCondition::Above,
+            threshold: 50000.0,
+            notifier: "webhook".into(),
+            target: "https://example.com/hook".into(),
+            last_rate: None,
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    model::{ApiError, ApiResult, Asset, AssetType, User},
+    service::AssetService,
+};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    code: String,
+    name: String,
+    #[serde(rename = "type")]
+    asset_type: AssetType,
+    precision: u8,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PutInput {
+    name: String,
+    #[serde(rename = "type")]
+    asset_type: AssetType,
+    precision: u8,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[get("/assets")]
+pub async fn get(service: &State<AssetService>, _user: User) -> ApiResult<Vec<Asset>> {
+    match service.select_all() {
+        Ok(assets) => ApiResult::new(200, assets),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/assets/<code>")]
+pub async fn get_by_code(
+    code: &str,
+    service: &State<AssetService>,
+    _user: User,
+) -> ApiResult<Asset> {
+    service.select_by_code(code).into()
+}
+
+#[post("/assets", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Asset> {
+    if !service.is_admin(&user.username) {
+        return ApiError::custom(403, "Only admins can change assets").into();
+    }
+
+    let input = input.into_inner();
+
+    let asset = Asset {
+        code: input.code,
+        name: input.name,
+        asset_type: input.asset_type,
+        precision: input.precision,
+        metadata: input.metadata,
+    };
+
+    if let Err(e) = service.validate(&asset) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    match service.exists(&asset.code) {
+        Ok(false) => {}
+        Ok(true) => return ApiError::custom(409, "Asset already exists").into(),
+        Err(e) => return e.into(),
+    }
+
+    if let Err(e) = service.insert(&asset) {
+        return e.into();
+    }
+
+    ApiResult::new(201, asset)
+}
+
+#[put("/assets/<code>", data = "<input>")]
+pub async fn put(
+    code: &str,
+    input: Json<PutInput>,
+    service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Asset> {
+    if !service.is_admin(&user.username) {
+        return ApiError::custom(403, "Only admins can change assets").into();
+    }
+
+    let asset = match service.select_by_code(code) {
+        Ok(Some(asset)) => asset,
+        res => return res.into(),
+    };
+
+    let input = input.into_inner();
+
+    let asset = Asset {
+        name: input.name,
+        asset_type: input.asset_type,
+        precision: input.precision,
+        metadata: input.metadata,
+        ..asset
+    };
+
+    if let Err(e) = service.validate(&asset) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&asset) {
+        return e.into();
+    }
+
+    ApiResult::new(200, asset)
+}
+
+#[delete("/assets/<code>")]
+pub async fn delete(code: &str, service: &State<AssetService>, user: User) -> ApiResult<Asset> {
+    if !service.is_admin(&user.username) {
+        return ApiError::custom(403, "Only admins can change assets").into();
+    }
+
+    let asset = match service.select_by_code(code) {
+        Ok(Some(asset)) => asset,
+        res => return res.into(),
+    };
+
+    match service.is_referenced(&asset.code) {
+        Ok(false) => {}
+        Ok(true) => return ApiError::custom(409, "Asset is still in use").into(),
+        Err(e) => return e.into(),
+    }
+
+    if let Err(e) = service.delete(&asset.code) {
+        return e.into();
+    }
+
+    ApiResult::new(200, asset)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::asset::{PostInput, PutInput},
+        model::{Asset, AssetType, Id, Watchlist},
+        repository::{AssetRepository, WatchlistRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use rocket::http::Status;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn get() {
+        let client = client();
+        let res = client.get("/assets").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let assets = res.into_json::<Vec<Asset>>().unwrap();
+        assert!(assets.iter().any(|it| it.code == "USD"));
+    }
+
+    #[test]
+    fn get_by_code() {
+        let client = client();
+        let res = client.get("/assets/EUR").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let asset = res.into_json::<Asset>().unwrap();
+        assert_eq!(AssetType::Fiat, asset.asset_type);
+        assert_eq!(2, asset.precision);
+    }
+
+    #[test]
+    fn get_by_code_not_found() {
+        let client = client();
+        let res = client.get("/assets/TST").dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let res = client.post("/assets").json(&input()).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let asset = res.into_json::<Asset>().unwrap();
+        let repo = client.rocket().state::<AssetRepository>().unwrap();
+        assert_eq!(Some(asset), repo.select_by_code("VWCE")?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_invalid_code() {
+        let client = client();
+        let input = PostInput {
+            code: "vwce".into(),
+            ..input()
+        };
+        let res = client.post("/assets").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn post_duplicate() {
+        let client = client();
+        let input = PostInput {
+            code: "USD".into(),
+            ..input()
+        };
+        let res = client.post("/assets").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Conflict);
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let input = PutInput {
+            name: "Euro".into(),
+            asset_type: AssetType::Fiat,
+            precision: 4,
+            metadata: BTreeMap::new(),
+        };
+        let res = client.put("/assets/EUR").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<AssetRepository>().unwrap();
+        assert_eq!(4, repo.select_by_code("EUR")?.unwrap().precision);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let res = client.delete("/assets/BTC").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<AssetRepository>().unwrap();
+        assert!(repo.select_by_code("BTC")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_referenced() -> Result<()> {
+        let client = client();
+        let watchlist_repo = client.rocket().state::<WatchlistRepository>().unwrap();
+        watchlist_repo.insert(&Watchlist {
+            id: Id::new(),
+            username: "test2".into(),
+            name: "Crypto".into(),
+            assets: vec!["BTC".into()],
+        })?;
+        let res = client.delete("/assets/BTC").dispatch();
+        assert_eq!(res.status(), Status::Conflict);
+        let repo = client.rocket().state::<AssetRepository>().unwrap();
+        assert!(repo.select_by_code("BTC")?.is_some());
+        Ok(())
+    }
+
+    fn input() -> PostInput {
+        PostInput {
+            code: "VWCE".into(),
+            name: "Vanguard FTSE All-World".into(),
+            asset_type: AssetType::Etf,
+            precision: 2,
+            metadata: vec![("isin".to_string(), "IE00BK5BQT80".to_string())]
+                .into_iter()
+                .collect(),
+        }
+    }
+}
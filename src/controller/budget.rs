@@ -0,0 +1,416 @@
+use crate::{
+    controller::parse_date,
+    model::{ApiError, ApiResult, Budget, BudgetPeriod, BudgetProgress, Id, Rollover, User},
+    service::BudgetService,
+};
+use chrono::{NaiveDate, Utc};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    category_id: Id,
+    period: BudgetPeriod,
+    amount: f64,
+    currency: String,
+    rollover: Rollover,
+    /// Defaults to the current period
+    start: Option<NaiveDate>,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/budgets")]
+pub async fn get(service: &State<BudgetService>, user: User) -> ApiResult<Vec<Budget>> {
+    match service.select_by_username(&user.username) {
+        Ok(budgets) => ApiResult::new(200, budgets),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/budgets/<id>")]
+pub async fn get_by_id(id: Id, service: &State<BudgetService>, user: User) -> ApiResult<Budget> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/budgets", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<BudgetService>,
+    user: User,
+) -> ApiResult<Budget> {
+    let budget = Budget {
+        id: Id::new(),
+        username: user.username.clone(),
+        category_id: input.category_id.clone(),
+        period: input.period,
+        amount: input.amount,
+        currency: input.currency.clone(),
+        rollover: input.rollover,
+        start: start(&input),
+    };
+
+    if let Err(e) = service.validate(&budget) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&budget) {
+        return e.into();
+    }
+
+    ApiResult::new(201, budget)
+}
+
+#[put("/budgets/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<BudgetService>,
+    user: User,
+) -> ApiResult<Budget> {
+    let budget = match service.select_owned(&id, &user.username) {
+        Ok(Some(budget)) => budget,
+        res => return res.into(),
+    };
+
+    let budget = Budget {
+        category_id: input.category_id.clone(),
+        period: input.period,
+        amount: input.amount,
+        currency: input.currency.clone(),
+        rollover: input.rollover,
+        start: input
+            .start
+            .map(|it| input.period.start(&it))
+            .unwrap_or_else(|| input.period.start(&budget.start)),
+        ..budget
+    };
+
+    if let Err(e) = service.validate(&budget) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&budget) {
+        return e.into();
+    }
+
+    ApiResult::new(200, budget)
+}
+
+#[delete("/budgets/<id>")]
+pub async fn delete(id: Id, service: &State<BudgetService>, user: User) -> ApiResult<Budget> {
+    let budget = match service.select_owned(&id, &user.username) {
+        Ok(Some(budget)) => budget,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&budget.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, budget)
+}
+
+/// Current period of every budget, or the one which contains a given day
+#[get("/budgets/progress?<date>")]
+pub async fn get_progress(
+    date: Option<&str>,
+    service: &State<BudgetService>,
+    user: User,
+) -> ApiResult<Vec<BudgetProgress>> {
+    let date = match parse_date(date) {
+        Ok(date) => date.unwrap_or_else(|| Utc::today().naive_utc()),
+        Err(e) => return e.into(),
+    };
+
+    let budgets = match service.select_by_username(&user.username) {
+        Ok(budgets) => budgets,
+        Err(e) => return e.into(),
+    };
+
+    let mut progress = vec![];
+
+    for budget in budgets {
+        match service.progress(&budget, &date) {
+            Ok(mut it) => {
+                it.periods = it.periods.split_off(it.periods.len().saturating_sub(1));
+                progress.push(it);
+            }
+            Err(e) => return e.into(),
+        }
+    }
+
+    ApiResult::new(200, progress)
+}
+
+/// Periods which end before `from` are left out, they still roll over into later ones
+#[get("/budgets/<id>/progress?<from>&<to>")]
+pub async fn get_progress_by_id(
+    id: Id,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<BudgetService>,
+    user: User,
+) -> ApiResult<BudgetProgress> {
+    let (from, to) = match (parse_date(from), parse_date(to)) {
+        (Ok(from), Ok(to)) => (from, to.unwrap_or_else(|| Utc::today().naive_utc())),
+        (Err(e), _) | (_, Err(e)) => return e.into(),
+    };
+
+    let budget = match service.select_owned(&id, &user.username) {
+        Ok(Some(budget)) => budget,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.progress(&budget, &to) {
+        Ok(mut progress) => {
+            if let Some(from) = from {
+                progress.periods.retain(|it| it.to >= from);
+            }
+
+            ApiResult::new(200, progress)
+        }
+        Err(e) => e.into(),
+    }
+}
+
+fn start(input: &PostInput) -> NaiveDate {
+    let start = input.start.unwrap_or_else(|| Utc::today().naive_utc());
+    input.period.start(&start)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::budget::PostInput,
+        model::{
+            Account, AccountType, Budget, BudgetPeriod, BudgetProgress, Category, CostBasisMethod,
+            ExchangeRate, Id, Rollover,
+        },
+        repository::{
+            AccountRepository, BudgetRepository, CategoryRepository, ExchangeRateRepository,
+        },
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<BudgetRepository>().unwrap();
+        let budget = budget("test", &Id::new());
+        repo.insert(&budget)?;
+        repo.insert(&self::budget("test2", &Id::new()))?;
+        let res = client.get("/budgets").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![budget], res.into_json::<Vec<Budget>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let category = category(&client, "test")?;
+        let input = PostInput {
+            start: Some(NaiveDate::from_ymd(2021, 8, 17)),
+            ..input(&category.id)
+        };
+        let res = client.post("/budgets").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let budget = res.into_json::<Budget>().unwrap();
+        assert_eq!(NaiveDate::from_ymd(2021, 8, 1), budget.start);
+        let repo = client.rocket().state::<BudgetRepository>().unwrap();
+        assert_eq!(Some(budget.clone()), repo.select_by_id(&budget.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign_category() -> Result<()> {
+        let client = client();
+        let category = category(&client, "test2")?;
+        let res = client
+            .post("/budgets")
+            .json(&input(&category.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_negative_amount() -> Result<()> {
+        let client = client();
+        let category = category(&client, "test")?;
+        let input = PostInput {
+            amount: -1.0,
+            ..input(&category.id)
+        };
+        let res = client.post("/budgets").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<BudgetRepository>().unwrap();
+        let budget = budget("test2", &Id::new());
+        repo.insert(&budget)?;
+        let res = client.delete(format!("/budgets/{}", budget.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    /// Spends 300 EUR in August and 50 USD at 0.8 in September out of 200 EUR a month,
+    /// and gets 20 EUR refunded in September
+    #[test]
+    fn get_progress_by_id() -> Result<()> {
+        let client = client();
+        let category = category(&client, "test")?;
+        let budget = Budget {
+            rollover: Rollover::All,
+            ..budget("test", &category.id)
+        };
+        let repo = client.rocket().state::<BudgetRepository>().unwrap();
+        repo.insert(&budget)?;
+        insert_spending(&client, &category)?;
+
+        let res = client
+            .get(format!("/budgets/{}/progress?to=2021-10-15", budget.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let progress = res.into_json::<BudgetProgress>().unwrap();
+        assert!(progress.unconverted.is_empty());
+        let periods: Vec<(&str, f64, f64, f64)> = progress
+            .periods
+            .iter()
+            .map(|it| (it.period.as_str(), it.carried, it.spent, it.remaining))
+            .collect();
+        assert_eq!(
+            vec![
+                ("2021-08", 0.0, 300.0, -100.0),
+                ("2021-09", -100.0, 20.0, 80.0),
+                ("2021-10", 80.0, 0.0, 280.0),
+            ],
+            periods
+        );
+        assert_eq!(Some(1.5), progress.periods[0].used);
+
+        repo.update(&Budget {
+            rollover: Rollover::Unspent,
+            ..budget.clone()
+        })?;
+        let res = client
+            .get(format!(
+                "/budgets/{}/progress?from=2021-10-01&to=2021-10-15",
+                budget.id
+            ))
+            .dispatch();
+        let progress = res.into_json::<BudgetProgress>().unwrap();
+        assert_eq!(1, progress.periods.len());
+        assert_eq!(180.0, progress.periods[0].carried);
+        Ok(())
+    }
+
+    #[test]
+    fn get_progress() -> Result<()> {
+        let client = client();
+        let category = category(&client, "test")?;
+        let repo = client.rocket().state::<BudgetRepository>().unwrap();
+        repo.insert(&budget("test", &category.id))?;
+        insert_spending(&client, &category)?;
+
+        let res = client.get("/budgets/progress?date=2021-08-31").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let progress = res.into_json::<Vec<BudgetProgress>>().unwrap();
+        assert_eq!(1, progress.len());
+        assert_eq!(1, progress[0].periods.len());
+        assert_eq!(300.0, progress[0].periods[0].spent);
+        Ok(())
+    }
+
+    fn insert_spending(client: &Client, category: &Category) -> Result<()> {
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Bank".into(),
+            account_type: AccountType::Bank,
+            currency: "EUR".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let account_repo = client.rocket().state::<AccountRepository>().unwrap();
+        account_repo.insert(&account)?;
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        rate_repo.insert_or_replace_history(
+            &ExchangeRate {
+                quote: "USD".into(),
+                base: "EUR".into(),
+                rate: 0.8,
+            },
+            &NaiveDate::from_ymd(2021, 9, 1),
+        )?;
+
+        let transactions = vec![
+            ("withdrawal", 300.0, "EUR", "2021-08-10", Some(&category.id)),
+            ("withdrawal", 30.0, "EUR", "2021-08-11", None),
+            ("withdrawal", 50.0, "USD", "2021-09-05", Some(&category.id)),
+            ("deposit", 20.0, "EUR", "2021-09-06", Some(&category.id)),
+        ];
+        for (transaction_type, quantity, currency, date, category_id) in transactions {
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&json!({
+                    "type": transaction_type,
+                    "quantity": quantity,
+                    "currency": currency,
+                    "time": format!("{}T12:00:00Z", date),
+                    "category_id": category_id,
+                }))
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+
+        Ok(())
+    }
+
+    fn category(client: &Client, username: &str) -> Result<Category> {
+        let category = Category {
+            id: Id::new(),
+            username: username.into(),
+            name: "Food".into(),
+            parent_id: None,
+        };
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        repo.insert(&category)?;
+        Ok(category)
+    }
+
+    fn input(category_id: &Id) -> PostInput {
+        PostInput {
+            category_id: category_id.clone(),
+            period: BudgetPeriod::Month,
+            amount: 200.0,
+            currency: "EUR".into(),
+            rollover: Rollover::None,
+            start: None,
+        }
+    }
+
+    fn budget(username: &str, category_id: &Id) -> Budget {
+        Budget {
+            id: Id::new(),
+            username: username.into(),
+            category_id: category_id.clone(),
+            period: BudgetPeriod::Month,
+            amount: 200.0,
+            currency: "EUR".into(),
+            rollover: Rollover::None,
+            start: NaiveDate::from_ymd(2021, 8, 1),
+        }
+    }
+}
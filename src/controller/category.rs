@@ -0,0 +1,392 @@
+use crate::{
+    model::{ApiError, ApiResult, Category, CategoryRule, Id, User},
+    service::CategoryService,
+};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    name: String,
+    parent_id: Option<Id>,
+}
+
+pub type PutInput = PostInput;
+
+#[derive(Serialize, Deserialize)]
+pub struct RulePostInput {
+    pattern: String,
+    category_id: Id,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+pub type RulePutInput = RulePostInput;
+
+/// Sorted by name, subcategories refer to their parent
+#[get("/categories")]
+pub async fn get(service: &State<CategoryService>, user: User) -> ApiResult<Vec<Category>> {
+    match service.select_by_username(&user.username) {
+        Ok(categories) => ApiResult::new(200, categories),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/categories/<id>")]
+pub async fn get_by_id(
+    id: Id,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Category> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/categories", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Category> {
+    let category = Category {
+        id: Id::new(),
+        username: user.username.clone(),
+        name: input.name.clone(),
+        parent_id: input.parent_id.clone(),
+    };
+
+    if let Err(e) = service.validate(&category) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&category) {
+        return e.into();
+    }
+
+    ApiResult::new(201, category)
+}
+
+#[put("/categories/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Category> {
+    let category = match service.select_owned(&id, &user.username) {
+        Ok(Some(category)) => category,
+        res => return res.into(),
+    };
+
+    let category = Category {
+        name: input.name.clone(),
+        parent_id: input.parent_id.clone(),
+        ..category
+    };
+
+    if let Err(e) = service.validate(&category) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&category) {
+        return e.into();
+    }
+
+    ApiResult::new(200, category)
+}
+
+#[delete("/categories/<id>")]
+pub async fn delete(id: Id, service: &State<CategoryService>, user: User) -> ApiResult<Category> {
+    let category = match service.select_owned(&id, &user.username) {
+        Ok(Some(category)) => category,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&category) {
+        return e.into();
+    }
+
+    ApiResult::new(200, category)
+}
+
+/// In the order they're tried
+#[get("/categories/rules")]
+pub async fn get_rules(
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Vec<CategoryRule>> {
+    match service.select_rules(&user.username) {
+        Ok(rules) => ApiResult::new(200, rules),
+        Err(e) => e.into(),
+    }
+}
+
+#[post("/categories/rules", data = "<input>")]
+pub async fn post_rule(
+    input: Json<RulePostInput>,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<CategoryRule> {
+    let rule = CategoryRule {
+        id: Id::new(),
+        username: user.username.clone(),
+        pattern: input.pattern.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+    };
+
+    if let Err(e) = service.validate_rule(&rule) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert_rule(&rule) {
+        return e.into();
+    }
+
+    ApiResult::new(201, rule)
+}
+
+#[put("/categories/rules/<id>", data = "<input>")]
+pub async fn put_rule(
+    id: Id,
+    input: Json<RulePutInput>,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<CategoryRule> {
+    let rule = match service.select_owned_rule(&id, &user.username) {
+        Ok(Some(rule)) => rule,
+        res => return res.into(),
+    };
+
+    let rule = CategoryRule {
+        pattern: input.pattern.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+        ..rule
+    };
+
+    if let Err(e) = service.validate_rule(&rule) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update_rule(&rule) {
+        return e.into();
+    }
+
+    ApiResult::new(200, rule)
+}
+
+#[delete("/categories/rules/<id>")]
+pub async fn delete_rule(
+    id: Id,
+    service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<CategoryRule> {
+    let rule = match service.select_owned_rule(&id, &user.username) {
+        Ok(Some(rule)) => rule,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete_rule(&rule.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, rule)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::category::{PostInput, RulePostInput},
+        model::{Category, CategoryRule, Id},
+        repository::{CategoryRepository, CategoryRuleRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use rocket::http::Status;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let category = category("test", None);
+        repo.insert(&category)?;
+        repo.insert(&self::category("test2", None))?;
+        let res = client.get("/categories").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![category], res.into_json::<Vec<Category>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let category = category("test2", None);
+        repo.insert(&category)?;
+        let res = client
+            .get(format!("/categories/{}", category.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let parent = category("test", None);
+        repo.insert(&parent)?;
+        let input = PostInput {
+            name: "Groceries".into(),
+            parent_id: Some(parent.id.clone()),
+        };
+        let res = client.post("/categories").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let category = res.into_json::<Category>().unwrap();
+        assert_eq!(Some(category.clone()), repo.select_by_id(&category.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign_parent() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let parent = category("test2", None);
+        repo.insert(&parent)?;
+        let input = PostInput {
+            name: "Groceries".into(),
+            parent_id: Some(parent.id),
+        };
+        let res = client.post("/categories").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn put_cycle() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let parent = category("test", None);
+        let child = category("test", Some(&parent.id));
+        repo.insert(&parent)?;
+        repo.insert(&child)?;
+        let input = PostInput {
+            name: "Food".into(),
+            parent_id: Some(child.id),
+        };
+        let res = client
+            .put(format!("/categories/{}", parent.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let rule_repo = client.rocket().state::<CategoryRuleRepository>().unwrap();
+        let parent = category("test", None);
+        let category = category("test", Some(&parent.id));
+        let child = self::category("test", Some(&category.id));
+        repo.insert(&parent)?;
+        repo.insert(&category)?;
+        repo.insert(&child)?;
+        rule_repo.insert(&rule(&category.id))?;
+
+        let res = client
+            .delete(format!("/categories/{}", category.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(repo.select_by_id(&category.id)?.is_none());
+        assert_eq!(
+            Some(parent.id),
+            repo.select_by_id(&child.id)?.unwrap().parent_id
+        );
+        assert!(rule_repo.select_by_username("test")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn get_rules() -> Result<()> {
+        let client = client();
+        let rule_repo = client.rocket().state::<CategoryRuleRepository>().unwrap();
+        let rule = rule(&Id::new());
+        rule_repo.insert(&rule)?;
+        let res = client.get("/categories/rules").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![rule], res.into_json::<Vec<CategoryRule>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn post_rule() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let category = category("test", None);
+        repo.insert(&category)?;
+        let input = RulePostInput {
+            pattern: "aldi|lidl".into(),
+            category_id: category.id,
+            tags: vec!["groceries".into()],
+        };
+        let res = client.post("/categories/rules").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let rule = res.into_json::<CategoryRule>().unwrap();
+        let rule_repo = client.rocket().state::<CategoryRuleRepository>().unwrap();
+        assert_eq!(Some(rule.clone()), rule_repo.select_by_id(&rule.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_rule_invalid_pattern() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let category = category("test", None);
+        repo.insert(&category)?;
+        let input = RulePostInput {
+            pattern: "[aldi".into(),
+            category_id: category.id,
+            tags: vec![],
+        };
+        let res = client.post("/categories/rules").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_rule_foreign() -> Result<()> {
+        let client = client();
+        let rule_repo = client.rocket().state::<CategoryRuleRepository>().unwrap();
+        let rule = CategoryRule {
+            username: "test2".into(),
+            ..rule(&Id::new())
+        };
+        rule_repo.insert(&rule)?;
+        let res = client
+            .delete(format!("/categories/rules/{}", rule.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    fn category(username: &str, parent_id: Option<&Id>) -> Category {
+        Category {
+            id: Id::new(),
+            username: username.into(),
+            name: "Food".into(),
+            parent_id: parent_id.cloned(),
+        }
+    }
+
+    fn rule(category_id: &Id) -> CategoryRule {
+        CategoryRule {
+            id: Id::new(),
+            username: "test".into(),
+            pattern: "^aldi".into(),
+            category_id: category_id.clone(),
+            tags: vec![],
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    model::{ApiResult, ExchangeRate, User},
-    service::ExchangeRateService,
+    model::{ApiError, ApiResult, ExchangeRate, User},
+    service::{exchange_rate::RateCacheStats, AssetService, ExchangeRateService},
 };
 use rocket::{get, State};
 
@@ -9,14 +9,41 @@ pub async fn get(
     quote: &str,
     base: &str,
     service: &State<ExchangeRateService>,
+    asset_service: &State<AssetService>,
     _user: User,
 ) -> ApiResult<ExchangeRate> {
-    service.get_by_quote_and_base(quote, base).into()
+    match service.get_by_quote_and_base(quote, base) {
+        Ok(Some(rate)) => return ApiResult::new(200, rate),
+        Ok(None) => {}
+        Err(e) => return e.into(),
+    }
+
+    // Assets are only looked up to explain a missing rate, found ones come from the cache
+    for code in [quote, base].iter() {
+        match asset_service.exists(code) {
+            Ok(true) => {}
+            Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", code)).into(),
+            Err(e) => return e.into(),
+        }
+    }
+
+    ApiError::new(404).into()
+}
+
+#[get("/exchange_rates/cache")]
+pub async fn get_cache(
+    service: &State<ExchangeRateService>,
+    _user: User,
+) -> ApiResult<RateCacheStats> {
+    ApiResult::new(200, service.cache_stats())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{model::ExchangeRate, test::client, ExchangeRateRepository};
+    use crate::{
+        model::ExchangeRate, service::exchange_rate::RateCacheStats, test::client,
+        ExchangeRateRepository,
+    };
     use anyhow::Result;
     use r2d2::Pool;
     use r2d2_sqlite::SqliteConnectionManager;
@@ -114,6 +141,41 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn get_cached() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+
+        let mut rate = ExchangeRate {
+            quote: "EUR".into(),
+            base: "USD".into(),
+            rate: 1.25,
+        };
+
+        repo.insert_or_replace(&rate)?;
+
+        for _ in 0..2 {
+            let res = client.get("/exchange_rates?quote=EUR&base=USD").dispatch();
+            assert_eq!(rate, res.into_json::<ExchangeRate>().unwrap());
+        }
+
+        rate.rate = 1.5;
+        repo.insert_or_replace(&rate)?;
+
+        let res = client.get("/exchange_rates?quote=EUR&base=USD").dispatch();
+        assert_eq!(rate, res.into_json::<ExchangeRate>().unwrap());
+
+        let res = client.get("/exchange_rates/cache").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let stats = RateCacheStats {
+            hits: 1,
+            misses: 2,
+            entries: 1,
+        };
+        assert_eq!(stats, res.into_json::<RateCacheStats>().unwrap());
+        Ok(())
+    }
+
     #[test]
     fn get_unauthorized() {
         let client = client();
@@ -133,6 +195,13 @@ mod test {
         assert_eq!(res.status(), Status::NotFound);
     }
 
+    #[test]
+    fn get_unknown_asset() {
+        let client = client();
+        let res = client.get("/exchange_rates?quote=EUR&base=TST").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
     #[test]
     fn get_sql_query_failed() {
         let client = client();
@@ -0,0 +1,174 @@
+use crate::{
+    model::{ApiError, ExportFormat, User},
+    service::ExportService,
+};
+use rocket::{
+    get,
+    http::{ContentType, Header},
+    Responder, State,
+};
+
+#[derive(Responder)]
+pub struct Attachment {
+    body: Vec<u8>,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+impl Attachment {
+    pub fn new(body: Vec<u8>, content_type: ContentType, filename: &str) -> Attachment {
+        Attachment {
+            body,
+            content_type,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        }
+    }
+}
+
+/// Exports JSON unless a format is given
+#[get("/export?<format>")]
+pub async fn get(
+    format: Option<&str>,
+    service: &State<ExportService>,
+    user: User,
+) -> Result<Attachment, ApiError> {
+    let format = match format
+        .map(|it| it.parse())
+        .unwrap_or(Ok(ExportFormat::Json))
+    {
+        Ok(format) => format,
+        Err(e) => return Err(ApiError::custom(400, &e)),
+    };
+
+    let (extension, content_type) = match format {
+        ExportFormat::Csv => ("zip", ContentType::ZIP),
+        ExportFormat::Json => ("json", ContentType::JSON),
+        ExportFormat::Beancount => ("beancount", ContentType::Plain),
+        ExportFormat::Ledger => ("ledger", ContentType::Plain),
+    };
+
+    let body = service
+        .export(&user.username)
+        .and_then(|it| ExportService::render(&it, format))
+        .map_err(|_| ApiError::new(500))?;
+
+    Ok(Attachment::new(
+        body,
+        content_type,
+        &format!("pfd.{}", extension),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, CostBasisMethod, ExchangeRate, Export, Id, Transaction,
+            TransactionType,
+        },
+        repository::{AccountRepository, ExchangeRateRepository, TransactionRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{
+        http::{ContentType, Status},
+        local::blocking::Client,
+    };
+
+    #[test]
+    fn get_json() -> Result<()> {
+        let client = client();
+        let account = insert(&client, "test")?;
+        insert(&client, "test2")?;
+        let res = client.get("/export?format=json").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.content_type(), Some(ContentType::JSON));
+        let export = res.into_json::<Export>().unwrap();
+        assert_eq!(1, export.accounts.len());
+        assert_eq!(account.id, export.accounts[0].id);
+        assert_eq!(1, export.transactions.len());
+        assert_eq!(1, export.rates.len());
+        assert_eq!("BTC", export.rates[0].quote);
+        Ok(())
+    }
+
+    #[test]
+    fn get_beancount() -> Result<()> {
+        let client = client();
+        insert(&client, "test")?;
+        let res = client.get("/export?format=beancount").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let disposition = res.headers().get_one("Content-Disposition").unwrap();
+        assert_eq!("attachment; filename=\"pfd.beancount\"", disposition);
+        let text = res.into_string().unwrap();
+        assert!(text.contains("2021-01-01 open Assets:Brokerage:Broker\n"));
+        assert!(text.contains("price BTC 40000 USD\n"));
+        assert!(text.contains("Assets:Brokerage:Broker  0.5 BTC {40000 USD}\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn get_unknown_format() {
+        let client = client();
+        let res = client.get("/export?format=xml").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    fn insert(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let transaction = Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type: TransactionType::Buy,
+            asset: Some("BTC".into()),
+            quantity: 0.5,
+            price: Some(40000.0),
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        };
+        client
+            .rocket()
+            .state::<AccountRepository>()
+            .unwrap()
+            .insert(&account)?;
+        client
+            .rocket()
+            .state::<TransactionRepository>()
+            .unwrap()
+            .insert(&transaction)?;
+        client
+            .rocket()
+            .state::<ExchangeRateRepository>()
+            .unwrap()
+            .insert_or_replace_history(
+                &ExchangeRate {
+                    quote: "BTC".into(),
+                    base: "USD".into(),
+                    rate: 40000.0,
+                },
+                &NaiveDate::from_ymd(2021, 8, 1),
+            )?;
+        Ok(account)
+    }
+}
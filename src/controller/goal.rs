@@ -0,0 +1,338 @@
+use crate::{
+    controller::parse_date,
+    model::{ApiError, ApiResult, Goal, GoalProgress, Id, User},
+    service::GoalService,
+};
+use chrono::{NaiveDate, Utc};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    name: String,
+    target: f64,
+    currency: String,
+    deadline: Option<NaiveDate>,
+    account_ids: Vec<Id>,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/goals")]
+pub async fn get(service: &State<GoalService>, user: User) -> ApiResult<Vec<Goal>> {
+    match service.select_by_username(&user.username) {
+        Ok(goals) => ApiResult::new(200, goals),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/goals/<id>")]
+pub async fn get_by_id(id: Id, service: &State<GoalService>, user: User) -> ApiResult<Goal> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/goals", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<GoalService>,
+    user: User,
+) -> ApiResult<Goal> {
+    let goal = Goal {
+        id: Id::new(),
+        username: user.username.clone(),
+        name: input.name.clone(),
+        target: input.target,
+        currency: input.currency.clone(),
+        deadline: input.deadline,
+        account_ids: input.account_ids.clone(),
+    };
+
+    if let Err(e) = service.validate(&goal) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&goal) {
+        return e.into();
+    }
+
+    ApiResult::new(201, goal)
+}
+
+#[put("/goals/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<GoalService>,
+    user: User,
+) -> ApiResult<Goal> {
+    let goal = match service.select_owned(&id, &user.username) {
+        Ok(Some(goal)) => goal,
+        res => return res.into(),
+    };
+
+    let goal = Goal {
+        name: input.name.clone(),
+        target: input.target,
+        currency: input.currency.clone(),
+        deadline: input.deadline,
+        account_ids: input.account_ids.clone(),
+        ..goal
+    };
+
+    if let Err(e) = service.validate(&goal) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&goal) {
+        return e.into();
+    }
+
+    ApiResult::new(200, goal)
+}
+
+#[delete("/goals/<id>")]
+pub async fn delete(id: Id, service: &State<GoalService>, user: User) -> ApiResult<Goal> {
+    let goal = match service.select_owned(&id, &user.username) {
+        Ok(Some(goal)) => goal,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&goal.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, goal)
+}
+
+/// Progress of every goal as of today or a given day
+#[get("/goals/progress?<date>")]
+pub async fn get_progress(
+    date: Option<&str>,
+    service: &State<GoalService>,
+    user: User,
+) -> ApiResult<Vec<GoalProgress>> {
+    let date = match parse_date(date) {
+        Ok(date) => date.unwrap_or_else(|| Utc::today().naive_utc()),
+        Err(e) => return e.into(),
+    };
+
+    let goals = match service.select_by_username(&user.username) {
+        Ok(goals) => goals,
+        Err(e) => return e.into(),
+    };
+
+    let mut progress = vec![];
+
+    for goal in goals {
+        match service.progress(&goal, &date) {
+            Ok(it) => progress.push(it),
+            Err(e) => return e.into(),
+        }
+    }
+
+    ApiResult::new(200, progress)
+}
+
+#[get("/goals/<id>/progress?<date>")]
+pub async fn get_progress_by_id(
+    id: Id,
+    date: Option<&str>,
+    service: &State<GoalService>,
+    user: User,
+) -> ApiResult<GoalProgress> {
+    let date = match parse_date(date) {
+        Ok(date) => date.unwrap_or_else(|| Utc::today().naive_utc()),
+        Err(e) => return e.into(),
+    };
+
+    let goal = match service.select_owned(&id, &user.username) {
+        Ok(Some(goal)) => goal,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.progress(&goal, &date) {
+        Ok(progress) => ApiResult::new(200, progress),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::goal::PostInput,
+        model::{Account, AccountType, CostBasisMethod, Goal, GoalProgress, Id},
+        repository::{AccountRepository, GoalRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<GoalRepository>().unwrap();
+        let goal = goal("test", &Id::new());
+        repo.insert(&goal)?;
+        repo.insert(&self::goal("test2", &Id::new()))?;
+        let res = client.get("/goals").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![goal], res.into_json::<Vec<Goal>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client.post("/goals").json(&input(&account.id)).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let goal = res.into_json::<Goal>().unwrap();
+        let repo = client.rocket().state::<GoalRepository>().unwrap();
+        assert_eq!(Some(goal.clone()), repo.select_by_id(&goal.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign_account() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let res = client.post("/goals").json(&input(&account.id)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_invalid_target() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            target: 0.0,
+            ..input(&account.id)
+        };
+        let res = client.post("/goals").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<GoalRepository>().unwrap();
+        let goal = goal("test2", &Id::new());
+        repo.insert(&goal)?;
+        let res = client.delete(format!("/goals/{}", goal.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    /// Saves 100 EUR a month in 2021 towards 2000 EUR, the remaining 800 EUR take
+    /// another 8 months at that pace
+    #[test]
+    fn get_progress_by_id() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        for month in 1..=12 {
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&json!({
+                    "type": "deposit",
+                    "quantity": 100.0,
+                    "currency": "EUR",
+                    "time": format!("2021-{:02}-01T12:00:00Z", month),
+                }))
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+        let repo = client.rocket().state::<GoalRepository>().unwrap();
+        let goal = goal("test", &account.id);
+        repo.insert(&goal)?;
+
+        let res = client
+            .get(format!("/goals/{}/progress?date=2021-12-31", goal.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let progress = res.into_json::<GoalProgress>().unwrap();
+        assert_eq!(1200.0, progress.value);
+        assert!((progress.progress - 0.6).abs() < 1e-9);
+        assert!((progress.monthly_contribution.unwrap() - 100.0).abs() < 1e-9);
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2022, 8, 31)),
+            progress.projected_completion
+        );
+        assert!((progress.required_contribution.unwrap() - 800.0 / 6.0).abs() < 1e-6);
+        assert_eq!(Some(false), progress.on_track);
+
+        let res = client.get("/goals/progress?date=2021-12-31").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(1, res.into_json::<Vec<GoalProgress>>().unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn get_progress_short_history() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&json!({
+                "type": "deposit",
+                "quantity": 1000.0,
+                "currency": "EUR",
+                "time": "2021-12-31T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let repo = client.rocket().state::<GoalRepository>().unwrap();
+        let goal = goal("test", &account.id);
+        repo.insert(&goal)?;
+
+        let res = client
+            .get(format!("/goals/{}/progress?date=2021-12-31", goal.id))
+            .dispatch();
+        let progress = res.into_json::<GoalProgress>().unwrap();
+        assert_eq!(None, progress.monthly_contribution);
+        assert_eq!(None, progress.projected_completion);
+        Ok(())
+    }
+
+    fn account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Savings".into(),
+            account_type: AccountType::Bank,
+            currency: "EUR".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+
+    fn input(account_id: &Id) -> PostInput {
+        PostInput {
+            name: "Car".into(),
+            target: 2000.0,
+            currency: "EUR".into(),
+            deadline: Some(NaiveDate::from_ymd(2022, 6, 30)),
+            account_ids: vec![account_id.clone()],
+        }
+    }
+
+    fn goal(username: &str, account_id: &Id) -> Goal {
+        Goal {
+            id: Id::new(),
+            username: username.into(),
+            name: "Car".into(),
+            target: 2000.0,
+            currency: "EUR".into(),
+            deadline: Some(NaiveDate::from_ymd(2022, 6, 30)),
+            account_ids: vec![account_id.clone()],
+        }
+    }
+}
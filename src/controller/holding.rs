@@ -0,0 +1,198 @@
+use crate::{
+    controller::parse_date,
+    model::{ApiError, ApiResult, Holding, Id, User},
+    service::{AccountService, HoldingService},
+};
+use rocket::{get, State};
+
+#[get("/accounts/<account_id>/holdings?<as_of>")]
+pub async fn get_by_account_id(
+    account_id: Id,
+    as_of: Option<&str>,
+    account_service: &State<AccountService>,
+    service: &State<HoldingService>,
+    user: User,
+) -> ApiResult<Vec<Holding>> {
+    let as_of = match parse_date(as_of) {
+        Ok(as_of) => as_of,
+        Err(e) => return e.into(),
+    };
+
+    let account = match account_service.select_owned(&account_id, &user.username) {
+        Ok(Some(account)) => account,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_account_id(&account.id, as_of) {
+        Ok(holdings) => ApiResult::new(200, holdings),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/holdings?<as_of>")]
+pub async fn get(
+    as_of: Option<&str>,
+    service: &State<HoldingService>,
+    user: User,
+) -> ApiResult<Vec<Holding>> {
+    let as_of = match parse_date(as_of) {
+        Ok(as_of) => as_of,
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_username(&user.username, as_of) {
+        Ok(holdings) => ApiResult::new(200, holdings),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Account, AccountType, CostBasisMethod, Holding, Id, Transaction, TransactionType},
+        repository::{AccountRepository, TransactionRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+
+    #[test]
+    fn get_by_account_id() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        insert_transactions(&client, &account.id)?;
+        let res = client
+            .get(format!("/accounts/{}/holdings", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![holding("BTC", 0.5), holding("USD", 5000.0)],
+            res.into_json::<Vec<Holding>>().unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_account_id_as_of() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        insert_transactions(&client, &account.id)?;
+        let res = client
+            .get(format!(
+                "/accounts/{}/holdings?as_of=2021-08-01",
+                account.id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![holding("USD", 25000.0)],
+            res.into_json::<Vec<Holding>>().unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_account_id_invalid_date() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client
+            .get(format!("/accounts/{}/holdings?as_of=yesterday", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_account_id_foreign() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let res = client
+            .get(format!("/accounts/{}/holdings", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let account_1 = account(&client, "test")?;
+        let account_2 = account(&client, "test")?;
+        let foreign_account = account(&client, "test2")?;
+        insert_transactions(&client, &account_1.id)?;
+        insert_transactions(&client, &account_2.id)?;
+        insert_transactions(&client, &foreign_account.id)?;
+        let res = client.get("/holdings").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![holding("BTC", 1.0), holding("USD", 10000.0)],
+            res.into_json::<Vec<Holding>>().unwrap()
+        );
+        Ok(())
+    }
+
+    fn account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+
+    fn insert_transactions(client: &Client, account_id: &Id) -> Result<()> {
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        repo.insert(&Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 25000.0,
+            price: None,
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        })?;
+        repo.insert(&Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Buy,
+            asset: Some("BTC".into()),
+            quantity: 0.5,
+            price: Some(40000.0),
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        })
+    }
+
+    fn holding(asset: &str, quantity: f64) -> Holding {
+        Holding {
+            asset: asset.into(),
+            quantity,
+        }
+    }
+}
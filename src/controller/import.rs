@@ -0,0 +1,282 @@
+use crate::{
+    model::{ApiError, ApiResult, CsvMapping, Id, ImportFormat, ImportReport, User},
+    service::{AccountService, ImportService},
+};
+use rocket::{get, post, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    #[serde(default)]
+    format: ImportFormat,
+    /// Contents of the imported file
+    #[serde(alias = "csv")]
+    data: String,
+    /// Name of a built-in CSV mapping, takes precedence over `mapping`
+    preset: Option<String>,
+    mapping: Option<CsvMapping>,
+    /// Chrono format of QIF dates
+    date_format: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[get("/imports/presets")]
+pub async fn get_presets(_user: User) -> ApiResult<BTreeMap<String, CsvMapping>> {
+    ApiResult::new(200, ImportService::presets())
+}
+
+#[post("/accounts/<account_id>/import", data = "<input>")]
+pub async fn post(
+    account_id: Id,
+    input: Json<PostInput>,
+    account_service: &State<AccountService>,
+    service: &State<ImportService>,
+    user: User,
+) -> ApiResult<ImportReport> {
+    let account = match account_service.select_owned(&account_id, &user.username) {
+        Ok(Some(account)) => account,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    let rows = match input.format {
+        ImportFormat::Csv => {
+            let mapping = match (&input.preset, &input.mapping) {
+                (Some(preset), _) => match ImportService::presets().remove(preset) {
+                    Some(mapping) => mapping,
+                    None => {
+                        return ApiError::custom(400, &format!("Unknown preset: {}", preset)).into()
+                    }
+                },
+                (None, Some(mapping)) => mapping.clone(),
+                (None, None) => {
+                    return ApiError::custom(400, "Either preset or mapping is required").into()
+                }
+            };
+
+            service.parse_csv(&input.data, &mapping, &account)
+        }
+        ImportFormat::Ofx => service.parse_ofx(&input.data, &account),
+        ImportFormat::Qif => service.parse_qif(&input.data, input.date_format.as_deref(), &account),
+    };
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => return ApiError::custom(400, &e.to_string()).into(),
+    };
+
+    match service.import(&account, rows, input.dry_run) {
+        Ok(report) if report.dry_run => ApiResult::new(200, report),
+        Ok(report) => ApiResult::new(201, report),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::import::PostInput,
+        model::{
+            Account, AccountType, CostBasisMethod, CsvMapping, Id, ImportFormat, ImportReport,
+            ImportStatus, Transaction, TransactionType,
+        },
+        repository::{AccountRepository, TransactionRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+    use std::collections::BTreeMap;
+
+    const CSV: &str = "time,type,asset,quantity,price,currency,fee,external_id\n\
+        2021-08-01T12:00:00Z,deposit,,1000,,USD,0,1\n\
+        2021-08-02T12:00:00Z,buy,BTC,0.01,40000,USD,1,2\n\
+        2021-08-03T12:00:00Z,buy,TST,1,1,USD,0,3\n\
+        2021-08-04T12:00:00Z,buy,BTC,abc,40000,USD,0,4\n";
+
+    #[test]
+    fn get_presets() {
+        let client = client();
+        let res = client.get("/imports/presets").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let presets = res.into_json::<BTreeMap<String, CsvMapping>>().unwrap();
+        assert!(presets.contains_key("coinbase"));
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input(false))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let report = res.into_json::<ImportReport>().unwrap();
+        assert_eq!(2, report.imported);
+        assert_eq!(2, report.errors);
+        assert_eq!(ImportStatus::Error, report.rows[2].status);
+        assert_eq!(Some("Unknown asset: TST".into()), report.rows[2].error);
+        assert_eq!(Some("Invalid number: abc".into()), report.rows[3].error);
+        assert_eq!(5, report.rows[3].line);
+
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transactions = repo.select_by_account_id(&account.id)?;
+        assert_eq!(2, transactions.len());
+        assert_eq!(TransactionType::Deposit, transactions[0].transaction_type);
+        assert_eq!(Some("2".into()), transactions[1].external_id);
+        Ok(())
+    }
+
+    #[test]
+    fn post_duplicates() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input(false))
+            .dispatch();
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input(false))
+            .dispatch();
+        let report = res.into_json::<ImportReport>().unwrap();
+        assert_eq!(0, report.imported);
+        assert_eq!(2, report.duplicates);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert_eq!(2, repo.select_by_account_id(&account.id)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn post_dry_run() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input(true))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<ImportReport>().unwrap();
+        assert_eq!(2, report.imported);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert!(repo.select_by_account_id(&account.id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn post_unknown_preset() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            preset: Some("unknown".into()),
+            ..input(false)
+        };
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input(false))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post_ofx() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let manual = Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type: TransactionType::Withdrawal,
+            asset: None,
+            quantity: 42.5,
+            price: None,
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 2).and_hms(9, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        };
+        repo.insert(&manual)?;
+        let ofx = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>\n\
+            <CURDEF>USD\n<BANKTRANLIST>\n\
+            <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20210801<TRNAMT>1000.00<FITID>A1</STMTTRN>\n\
+            <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20210802<TRNAMT>-42.50<FITID>A2</STMTTRN>\n\
+            </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let input = PostInput {
+            format: ImportFormat::Ofx,
+            data: ofx.into(),
+            preset: None,
+            ..input(false)
+        };
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let report = res.into_json::<ImportReport>().unwrap();
+        assert_eq!(1, report.imported);
+        assert_eq!(1, report.reconciled);
+
+        let transactions = repo.select_by_account_id(&account.id)?;
+        assert_eq!(2, transactions.len());
+        assert_eq!(Some("A1".into()), transactions[0].external_id);
+        assert_eq!(manual.id, transactions[1].id);
+        assert_eq!(Some("A2".into()), transactions[1].external_id);
+
+        let res = client
+            .post(format!("/accounts/{}/import", account.id))
+            .json(&input)
+            .dispatch();
+        let report = res.into_json::<ImportReport>().unwrap();
+        assert_eq!(2, report.duplicates);
+        Ok(())
+    }
+
+    fn input(dry_run: bool) -> PostInput {
+        PostInput {
+            format: ImportFormat::Csv,
+            data: CSV.into(),
+            preset: Some("pfd".into()),
+            mapping: None,
+            date_format: None,
+            dry_run,
+        }
+    }
+
+    fn account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+}
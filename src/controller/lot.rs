@@ -0,0 +1,187 @@
+use crate::{
+    model::{ApiError, ApiResult, Id, Lot, User},
+    service::{AccountService, LotService},
+};
+use rocket::{get, State};
+
+#[get("/accounts/<account_id>/lots")]
+pub async fn get(
+    account_id: Id,
+    account_service: &State<AccountService>,
+    service: &State<LotService>,
+    user: User,
+) -> ApiResult<Vec<Lot>> {
+    let account = match account_service.select_owned(&account_id, &user.username) {
+        Ok(Some(account)) => account,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_account_id(&account.id) {
+        Ok(lots) => ApiResult::new(200, lots),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Account, AccountType, CostBasisMethod, Id, Lot, Transaction},
+        repository::{AccountRepository, LotMatchRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test", CostBasisMethod::Lifo)?;
+        let first = post(&client, &account, json!({ "type": "buy", "price": 100.0 }));
+        let second = post(&client, &account, json!({ "type": "buy", "price": 200.0 }));
+        post(&client, &account, json!({ "type": "sell", "price": 300.0 }));
+
+        let res = client
+            .get(format!("/accounts/{}/lots", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let lots = res.into_json::<Vec<Lot>>().unwrap();
+        assert_eq!(
+            vec![first.id, second.id.clone()],
+            lots.iter().map(|it| it.id.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1.0, 0.0],
+            lots.iter().map(|it| it.remaining).collect::<Vec<_>>()
+        );
+
+        let match_repo = client.rocket().state::<LotMatchRepository>().unwrap();
+        let matches = match_repo.select_by_account_id(&account.id)?;
+        assert_eq!(second.id, matches[0].lot_id);
+        Ok(())
+    }
+
+    #[test]
+    fn get_specific_transferred() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", CostBasisMethod::Fifo)?;
+        let to = account(&client, "test", CostBasisMethod::Specific)?;
+        post(&client, &from, json!({ "type": "buy", "price": 100.0 }));
+        post(&client, &to, json!({ "type": "buy", "price": 200.0 }));
+        let res = client
+            .post("/transfers")
+            .json(&json!({
+                "from_account_id": from.id,
+                "to_account_id": to.id,
+                "asset": "BTC",
+                "quantity": 1.0,
+                "currency": "USD",
+                "fee": 0.0,
+                "time": "2021-08-02T12:00:00Z"
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+
+        let res = client.get(format!("/accounts/{}/lots", to.id)).dispatch();
+        let lots = res.into_json::<Vec<Lot>>().unwrap();
+        let moved = lots.iter().find(|it| it.cost == 100.0).unwrap();
+        post(
+            &client,
+            &to,
+            json!({
+                "type": "sell",
+                "price": 300.0,
+                "lot_id": moved.id,
+                "time": "2021-08-03T12:00:00Z"
+            }),
+        );
+
+        let match_repo = client.rocket().state::<LotMatchRepository>().unwrap();
+        let matches = match_repo.select_by_account_id(&to.id)?;
+        assert_eq!(moved.id, matches[0].lot_id);
+        Ok(())
+    }
+
+    #[test]
+    fn get_rebuilt_after_edit() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test", CostBasisMethod::Fifo)?;
+        let buy = post(&client, &account, json!({ "type": "buy", "price": 100.0 }));
+        post(&client, &account, json!({ "type": "sell", "price": 300.0 }));
+
+        let res = client
+            .put(format!("/accounts/{}/transactions/{}", account.id, buy.id))
+            .json(&input(
+                json!({ "type": "buy", "price": 150.0, "quantity": 2.0 }),
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client
+            .get(format!("/accounts/{}/lots", account.id))
+            .dispatch();
+        let lots = res.into_json::<Vec<Lot>>().unwrap();
+        assert_eq!(1.0, lots[0].remaining);
+        assert_eq!(300.0, lots[0].cost);
+
+        let match_repo = client.rocket().state::<LotMatchRepository>().unwrap();
+        let matches = match_repo.select_by_account_id(&account.id)?;
+        assert_eq!(1, matches.len());
+        assert_eq!(150.0, matches[0].cost);
+        Ok(())
+    }
+
+    #[test]
+    fn get_foreign() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2", CostBasisMethod::Fifo)?;
+        let res = client
+            .get(format!("/accounts/{}/lots", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    fn post(client: &Client, account: &Account, input: serde_json::Value) -> Transaction {
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&self::input(input))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        res.into_json::<Transaction>().unwrap()
+    }
+
+    /// Trade of one BTC for USD without fees, fields can be overridden
+    fn input(overrides: serde_json::Value) -> serde_json::Value {
+        let mut input = json!({
+            "asset": "BTC",
+            "quantity": 1.0,
+            "currency": "USD",
+            "time": "2021-08-01T12:00:00Z",
+        });
+
+        for (key, value) in overrides.as_object().unwrap() {
+            input[key] = value.clone();
+        }
+
+        input
+    }
+
+    fn account(client: &Client, username: &str, method: CostBasisMethod) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: method,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+}
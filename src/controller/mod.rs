@@ -1,3 +1,34 @@
+pub mod account;
+pub mod alert;
+pub mod asset;
 pub mod auth_token;
+pub mod budget;
+pub mod category;
 pub mod exchange_rate;
+pub mod export;
+pub mod goal;
+pub mod holding;
+pub mod import;
+pub mod lot;
+pub mod portfolio;
+pub mod recurring;
+pub mod report;
+pub mod transaction;
+pub mod transfer;
 pub mod user;
+pub mod watchlist;
+pub mod webhook;
+
+use crate::model::ApiError;
+use chrono::NaiveDate;
+
+/// Expects ISO 8601 dates such as 2021-08-01
+pub fn parse_date(date: Option<&str>) -> Result<Option<NaiveDate>, ApiError> {
+    match date {
+        Some(date) => date
+            .parse()
+            .map(Some)
+            .map_err(|_| ApiError::custom(400, &format!("Invalid date: {}", date))),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,422 @@
+use crate::{
+    controller::parse_date,
+    model::{
+        ApiError, ApiResult, AssetType, PortfolioSnapshot, PortfolioValue, Rebalance, Target, User,
+    },
+    service::{AssetService, SnapshotService, TargetService, ValuationService},
+};
+use rocket::{get, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct TargetInput {
+    asset: Option<String>,
+    #[serde(rename = "type")]
+    asset_type: Option<AssetType>,
+    weight: f64,
+}
+
+pub type PutTargetsInput = Vec<TargetInput>;
+
+#[get("/portfolio/value?<currency>&<date>")]
+pub async fn get_value(
+    currency: &str,
+    date: Option<&str>,
+    service: &State<ValuationService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<PortfolioValue> {
+    let date = match parse_date(date) {
+        Ok(date) => date,
+        Err(e) => return e.into(),
+    };
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", currency)).into(),
+        Err(e) => return e.into(),
+    }
+
+    match service.value_by_username(&user.username, currency, date) {
+        Ok(value) => ApiResult::new(200, value),
+        Err(e) => e.into(),
+    }
+}
+
+/// Daily values kept by the snapshots job, the current day shows up after its next run
+#[get("/portfolio/history?<currency>&<from>&<to>")]
+pub async fn get_history(
+    currency: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<SnapshotService>,
+    user: User,
+) -> ApiResult<Vec<PortfolioSnapshot>> {
+    let (from, to) = match (parse_date(from), parse_date(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return e.into(),
+    };
+
+    if !service.is_tracked(currency) {
+        return ApiError::custom(400, &format!("Snapshots aren't taken in {}", currency)).into();
+    }
+
+    match service.select_history(&user.username, currency, from, to) {
+        Ok(history) => ApiResult::new(200, history),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/portfolio/targets")]
+pub async fn get_targets(service: &State<TargetService>, user: User) -> ApiResult<Vec<Target>> {
+    match service.select_by_username(&user.username) {
+        Ok(targets) => ApiResult::new(200, targets),
+        Err(e) => e.into(),
+    }
+}
+
+/// Replaces all the targets, an empty list removes them
+#[put("/portfolio/targets", data = "<input>")]
+pub async fn put_targets(
+    input: Json<PutTargetsInput>,
+    service: &State<TargetService>,
+    user: User,
+) -> ApiResult<Vec<Target>> {
+    let targets: Vec<Target> = input
+        .iter()
+        .map(|it| Target {
+            username: user.username.clone(),
+            asset: it.asset.clone(),
+            asset_type: it.asset_type,
+            weight: it.weight,
+        })
+        .collect();
+
+    if let Err(e) = service.validate(&targets) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.replace_by_username(&user.username, &targets) {
+        return e.into();
+    }
+
+    ApiResult::new(200, targets)
+}
+
+/// Tolerance is the largest acceptable difference between weights, 0.05 by default
+#[get("/portfolio/rebalance?<currency>&<cash>&<tolerance>")]
+pub async fn get_rebalance(
+    currency: &str,
+    cash: Option<f64>,
+    tolerance: Option<f64>,
+    service: &State<TargetService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Rebalance> {
+    let tolerance = tolerance.unwrap_or(0.05);
+
+    if !(0.0..=1.0).contains(&tolerance) {
+        return ApiError::custom(400, "Tolerance must be between 0 and 1").into();
+    }
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", currency)).into(),
+        Err(e) => return e.into(),
+    }
+
+    match service.select_by_username(&user.username) {
+        Ok(targets) if targets.is_empty() => {
+            return ApiError::custom(400, "No targets are set").into()
+        }
+        Ok(_) => {}
+        Err(e) => return e.into(),
+    }
+
+    match service.rebalance(&user.username, currency, cash.unwrap_or(0.0), tolerance) {
+        Ok(rebalance) => ApiResult::new(200, rebalance),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, AssetType, CostBasisMethod, ExchangeRate, Id, PortfolioSnapshot,
+            PortfolioValue, Rebalance, Target, Transaction, TransactionType,
+        },
+        repository::{
+            AccountRepository, ExchangeRateRepository, PortfolioSnapshotRepository,
+            TransactionRepository,
+        },
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn get_value() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        rate_repo.insert_or_replace(&ExchangeRate {
+            quote: "BTC".into(),
+            base: "USD".into(),
+            rate: 50000.0,
+        })?;
+        let res = client.get("/portfolio/value?currency=USD").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let value = res.into_json::<PortfolioValue>().unwrap();
+        assert_eq!(5000.0 + 25000.0, value.total);
+        assert_eq!(2, value.holdings.len());
+        assert_eq!(1, value.rates.len());
+        Ok(())
+    }
+
+    #[test]
+    fn get_value_as_of() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let res = client
+            .get("/portfolio/value?currency=USD&date=2021-08-01")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let value = res.into_json::<PortfolioValue>().unwrap();
+        assert_eq!(25000.0, value.total);
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 8, 1)), value.date);
+        Ok(())
+    }
+
+    #[test]
+    fn get_value_unknown_currency() {
+        let client = client();
+        let res = client.get("/portfolio/value?currency=TST").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn get_history() -> Result<()> {
+        let client = client();
+        let repo = client
+            .rocket()
+            .state::<PortfolioSnapshotRepository>()
+            .unwrap();
+        for day in 1..=3 {
+            repo.insert_or_replace(&snapshot("test", day))?;
+        }
+        repo.insert_or_replace(&snapshot("test2", 2))?;
+        let res = client
+            .get("/portfolio/history?currency=USD&from=2021-08-02")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let history = res.into_json::<Vec<PortfolioSnapshot>>().unwrap();
+        assert_eq!(vec![snapshot("test", 2), snapshot("test", 3)], history);
+        Ok(())
+    }
+
+    #[test]
+    fn get_history_untracked_currency() {
+        let client = client();
+        let res = client.get("/portfolio/history?currency=GBP").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn get_history_after_backdated_transaction() -> Result<()> {
+        let client = client();
+        let account = insert_portfolio(&client)?;
+        let repo = client
+            .rocket()
+            .state::<PortfolioSnapshotRepository>()
+            .unwrap();
+        for day in 1..=3 {
+            repo.insert_or_replace(&snapshot("test", day))?;
+        }
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&json!({
+                "type": "deposit",
+                "quantity": 100.0,
+                "currency": "USD",
+                "time": "2021-08-02T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let res = client.get("/portfolio/history?currency=USD").dispatch();
+        let history = res.into_json::<Vec<PortfolioSnapshot>>().unwrap();
+        assert_eq!(vec![snapshot("test", 1)], history);
+        Ok(())
+    }
+
+    fn snapshot(username: &str, day: u32) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            username: username.into(),
+            currency: "USD".into(),
+            date: NaiveDate::from_ymd(2021, 8, day),
+            value: 1000.0,
+        }
+    }
+
+    #[test]
+    fn put_targets() {
+        let client = client();
+        let res = client
+            .put("/portfolio/targets")
+            .json(&json!([
+                { "asset": "BTC", "weight": 0.4 },
+                { "type": "fiat", "weight": 0.6 }
+            ]))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.get("/portfolio/targets").dispatch();
+        let targets = res.into_json::<Vec<Target>>().unwrap();
+        assert_eq!(2, targets.len());
+        assert_eq!(Some(AssetType::Fiat), targets[1].asset_type);
+    }
+
+    #[test]
+    fn put_targets_invalid() {
+        let client = client();
+        let inputs = vec![
+            json!([{ "asset": "BTC", "weight": 0.4 }]),
+            json!([{ "asset": "TST", "weight": 1.0 }]),
+            json!([{ "asset": "BTC", "type": "crypto", "weight": 1.0 }]),
+            json!([{ "asset": "BTC", "weight": 0.5 }, { "asset": "BTC", "weight": 0.5 }]),
+        ];
+
+        for input in inputs {
+            let res = client.put("/portfolio/targets").json(&input).dispatch();
+            assert_eq!(res.status(), Status::BadRequest);
+        }
+    }
+
+    #[test]
+    fn get_rebalance() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        rate_repo.insert_or_replace(&ExchangeRate {
+            quote: "BTC".into(),
+            base: "USD".into(),
+            rate: 50000.0,
+        })?;
+        client
+            .put("/portfolio/targets")
+            .json(&json!([
+                { "asset": "BTC", "weight": 0.5 },
+                { "type": "fiat", "weight": 0.5 }
+            ]))
+            .dispatch();
+
+        let res = client
+            .get("/portfolio/rebalance?currency=USD&cash=10000")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let rebalance = res.into_json::<Rebalance>().unwrap();
+        assert_eq!(40000.0, rebalance.total);
+        let btc = &rebalance.positions[0];
+        assert_eq!(0.625, btc.weight);
+        assert_eq!(-5000.0, btc.amount);
+        assert_eq!(-0.1, btc.trades[0].quantity);
+        assert_eq!(15000.0, rebalance.positions[1].amount);
+
+        let res = client
+            .get("/portfolio/rebalance?currency=USD&cash=10000&tolerance=0.2")
+            .dispatch();
+        let rebalance = res.into_json::<Rebalance>().unwrap();
+        assert_eq!(0.0, rebalance.positions[0].amount);
+        assert!(rebalance.positions[0].trades.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn get_rebalance_cash_holding() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        rate_repo.insert_or_replace(&ExchangeRate {
+            quote: "BTC".into(),
+            base: "USD".into(),
+            rate: 50000.0,
+        })?;
+        client
+            .put("/portfolio/targets")
+            .json(&json!([{ "asset": "BTC", "weight": 1.0 }]))
+            .dispatch();
+
+        let res = client
+            .get("/portfolio/rebalance?currency=USD&cash=10000")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let rebalance = res.into_json::<Rebalance>().unwrap();
+        assert_eq!(40000.0, rebalance.total);
+        assert_eq!(15000.0, rebalance.cash);
+        assert_eq!(1, rebalance.positions.len());
+        assert_eq!(15000.0, rebalance.positions[0].amount);
+        assert_eq!(0.3, rebalance.positions[0].trades[0].quantity);
+        Ok(())
+    }
+
+    #[test]
+    fn get_rebalance_without_targets() {
+        let client = client();
+        let res = client.get("/portfolio/rebalance?currency=USD").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    fn insert_portfolio(client: &Client) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let account_repo = client.rocket().state::<AccountRepository>().unwrap();
+        account_repo.insert(&account)?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        repo.insert(&Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 25000.0,
+            price: None,
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        })?;
+        repo.insert(&Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type: TransactionType::Buy,
+            asset: Some("BTC".into()),
+            quantity: 0.5,
+            price: Some(40000.0),
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        })?;
+        Ok(account)
+    }
+}
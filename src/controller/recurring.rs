@@ -0,0 +1,515 @@
+use crate::{
+    controller::parse_date,
+    model::{
+        ApiError, ApiResult, Id, Occurrence, RecurringException, RecurringTransaction,
+        TransactionType, User,
+    },
+    service::{AccountService, CategoryService, RecurringService},
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    account_id: Id,
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    asset: Option<String>,
+    quantity: f64,
+    price: Option<f64>,
+    currency: String,
+    #[serde(default)]
+    fee: f64,
+    description: Option<String>,
+    category_id: Option<Id>,
+    #[serde(default)]
+    tags: Vec<String>,
+    schedule: String,
+    start: DateTime<Utc>,
+    end: Option<NaiveDate>,
+}
+
+pub type PutInput = PostInput;
+
+#[derive(Serialize, Deserialize)]
+pub struct OccurrenceInput {
+    #[serde(default)]
+    skip: bool,
+    quantity: Option<f64>,
+    price: Option<f64>,
+    time: Option<DateTime<Utc>>,
+}
+
+#[get("/recurring")]
+pub async fn get(
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<Vec<RecurringTransaction>> {
+    match service.select_by_username(&user.username) {
+        Ok(recurring) => ApiResult::new(200, recurring),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/recurring/<id>")]
+pub async fn get_by_id(
+    id: Id,
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<RecurringTransaction> {
+    service.select_owned(&id, &user.username).into()
+}
+
+/// Past occurrences become transactions right away
+#[post("/recurring", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<RecurringService>,
+    account_service: &State<AccountService>,
+    category_service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<RecurringTransaction> {
+    if let Err(e) = check_input(account_service, category_service, &input, &user) {
+        return e.into();
+    }
+
+    let mut recurring = RecurringTransaction {
+        id: Id::new(),
+        username: user.username.clone(),
+        account_id: input.account_id.clone(),
+        transaction_type: input.transaction_type,
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        price: input.price,
+        currency: input.currency.clone(),
+        fee: input.fee,
+        description: input.description.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+        schedule: input.schedule.trim().into(),
+        start: input.start,
+        end: input.end,
+        materialized_until: None,
+    };
+
+    if let Err(e) = service.validate(&recurring) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&recurring) {
+        return e.into();
+    }
+
+    if let Err(e) = service.materialize(&mut recurring, &Utc::today().naive_utc()) {
+        return e.into();
+    }
+
+    ApiResult::new(201, recurring)
+}
+
+/// Occurrences which are already transactions stay as they are
+#[put("/recurring/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<RecurringService>,
+    account_service: &State<AccountService>,
+    category_service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<RecurringTransaction> {
+    let recurring = match service.select_owned(&id, &user.username) {
+        Ok(Some(recurring)) => recurring,
+        res => return res.into(),
+    };
+
+    if let Err(e) = check_input(account_service, category_service, &input, &user) {
+        return e.into();
+    }
+
+    let mut recurring = RecurringTransaction {
+        account_id: input.account_id.clone(),
+        transaction_type: input.transaction_type,
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        price: input.price,
+        currency: input.currency.clone(),
+        fee: input.fee,
+        description: input.description.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+        schedule: input.schedule.trim().into(),
+        start: input.start,
+        end: input.end,
+        ..recurring
+    };
+
+    if let Err(e) = service.validate(&recurring) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&recurring) {
+        return e.into();
+    }
+
+    if let Err(e) = service.materialize(&mut recurring, &Utc::today().naive_utc()) {
+        return e.into();
+    }
+
+    ApiResult::new(200, recurring)
+}
+
+#[delete("/recurring/<id>")]
+pub async fn delete(
+    id: Id,
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<RecurringTransaction> {
+    let recurring = match service.select_owned(&id, &user.username) {
+        Ok(Some(recurring)) => recurring,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&recurring.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, recurring)
+}
+
+/// Defaults to the occurrences of the coming year
+#[get("/recurring/<id>/occurrences?<from>&<to>")]
+pub async fn get_occurrences(
+    id: Id,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<Vec<Occurrence>> {
+    let (from, to) = match (parse_date(from), parse_date(to)) {
+        (Ok(from), Ok(to)) => {
+            let from = from.unwrap_or_else(|| Utc::today().naive_utc());
+            (from, to.unwrap_or(from + Duration::days(365)))
+        }
+        (Err(e), _) | (_, Err(e)) => return e.into(),
+    };
+
+    let recurring = match service.select_owned(&id, &user.username) {
+        Ok(Some(recurring)) => recurring,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.occurrences(&recurring, &from, &to) {
+        Ok(occurrences) => ApiResult::new(200, occurrences),
+        Err(e) => e.into(),
+    }
+}
+
+/// Skips a single occurrence or changes its quantity and price
+#[put("/recurring/<id>/occurrences/<date>", data = "<input>")]
+pub async fn put_occurrence(
+    id: Id,
+    date: &str,
+    input: Json<OccurrenceInput>,
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<RecurringException> {
+    let date = match parse_date(Some(date)) {
+        Ok(date) => date.unwrap(),
+        Err(e) => return e.into(),
+    };
+
+    let recurring = match service.select_owned(&id, &user.username) {
+        Ok(Some(recurring)) => recurring,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    let exception = RecurringException {
+        recurring_id: recurring.id.clone(),
+        date,
+        skip: input.skip,
+        quantity: input.quantity,
+        price: input.price,
+        time: input.time,
+    };
+
+    if let Err(e) = service.validate_exception(&recurring, &exception) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert_or_replace_exception(&exception) {
+        return e.into();
+    }
+
+    ApiResult::new(200, exception)
+}
+
+/// Restores a single occurrence to the template
+#[delete("/recurring/<id>/occurrences/<date>")]
+pub async fn delete_occurrence(
+    id: Id,
+    date: &str,
+    service: &State<RecurringService>,
+    user: User,
+) -> ApiResult<RecurringTransaction> {
+    let date = match parse_date(Some(date)) {
+        Ok(date) => date.unwrap(),
+        Err(e) => return e.into(),
+    };
+
+    let recurring = match service.select_owned(&id, &user.username) {
+        Ok(Some(recurring)) => recurring,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete_exception(&recurring.id, &date) {
+        return e.into();
+    }
+
+    ApiResult::new(200, recurring)
+}
+
+fn check_input(
+    account_service: &AccountService,
+    category_service: &CategoryService,
+    input: &PostInput,
+    user: &User,
+) -> Result<(), ApiError> {
+    match account_service.select_owned(&input.account_id, &user.username) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let message = format!("Unknown account: {}", input.account_id);
+            return Err(ApiError::custom(400, &message));
+        }
+        Err(_) => return Err(ApiError::new(500)),
+    }
+
+    let category_id = match &input.category_id {
+        Some(category_id) => category_id,
+        None => return Ok(()),
+    };
+
+    match category_service.select_owned(category_id, &user.username) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(ApiError::custom(
+            400,
+            &format!("Unknown category: {}", category_id),
+        )),
+        Err(_) => Err(ApiError::new(500)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::recurring::PostInput,
+        model::{
+            Account, AccountType, CostBasisMethod, Id, Occurrence, RecurringTransaction,
+            TransactionType,
+        },
+        repository::{AccountRepository, RecurringTransactionRepository, TransactionRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client
+            .rocket()
+            .state::<RecurringTransactionRepository>()
+            .unwrap();
+        let recurring = recurring("test", &account.id);
+        repo.insert(&recurring)?;
+        repo.insert(&self::recurring("test2", &account.id))?;
+        let res = client.get("/recurring").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![recurring],
+            res.into_json::<Vec<RecurringTransaction>>().unwrap()
+        );
+        Ok(())
+    }
+
+    /// Past occurrences become transactions right away
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            start: Utc.ymd(2021, 8, 1).and_hms(0, 0, 0),
+            end: Some(NaiveDate::from_ymd(2021, 10, 31)),
+            ..input(&account.id)
+        };
+        let res = client.post("/recurring").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let recurring = res.into_json::<RecurringTransaction>().unwrap();
+        assert!(recurring.materialized_until.is_some());
+        let repo = client
+            .rocket()
+            .state::<RecurringTransactionRepository>()
+            .unwrap();
+        assert_eq!(
+            Some(recurring),
+            repo.select_by_id(&repo.select_all()?[0].id)?
+        );
+        let transaction_repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert_eq!(3, transaction_repo.select_by_account_id(&account.id)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign_account() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let res = client
+            .post("/recurring")
+            .json(&input(&account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_invalid_schedule() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            schedule: "FREQ=MONTHLY;BYSETPOS=-1".into(),
+            ..input(&account.id)
+        };
+        let res = client.post("/recurring").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_foreign() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let repo = client
+            .rocket()
+            .state::<RecurringTransactionRepository>()
+            .unwrap();
+        let recurring = recurring("test2", &account.id);
+        repo.insert(&recurring)?;
+        let res = client
+            .delete(format!("/recurring/{}", recurring.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn put_occurrence() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client
+            .rocket()
+            .state::<RecurringTransactionRepository>()
+            .unwrap();
+        let recurring = recurring("test", &account.id);
+        repo.insert(&recurring)?;
+        let url = format!("/recurring/{}/occurrences", recurring.id);
+
+        let res = client
+            .put(format!("{}/2100-02-25", url))
+            .json(&json!({ "skip": true }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client
+            .put(format!("{}/2100-03-25", url))
+            .json(&json!({ "quantity": 2100.0 }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client
+            .put(format!("{}/2100-03-24", url))
+            .json(&json!({ "skip": true }))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+
+        let res = client
+            .get(format!("{}?from=2100-01-01&to=2100-03-31", url))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let occurrences = res.into_json::<Vec<Occurrence>>().unwrap();
+        let occurrences: Vec<(bool, f64)> = occurrences
+            .iter()
+            .map(|it| (it.skipped, it.quantity))
+            .collect();
+        assert_eq!(
+            vec![(false, 2000.0), (true, 2000.0), (false, 2100.0)],
+            occurrences
+        );
+
+        let res = client.delete(format!("{}/2100-02-25", url)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client
+            .get(format!("{}?from=2100-02-01&to=2100-02-28", url))
+            .dispatch();
+        let occurrences = res.into_json::<Vec<Occurrence>>().unwrap();
+        assert!(!occurrences[0].skipped);
+        Ok(())
+    }
+
+    fn account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Bank".into(),
+            account_type: AccountType::Bank,
+            currency: "EUR".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+
+    fn input(account_id: &Id) -> PostInput {
+        PostInput {
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 2000.0,
+            price: None,
+            currency: "EUR".into(),
+            fee: 0.0,
+            description: Some("Salary".into()),
+            category_id: None,
+            tags: vec![],
+            schedule: "0 0 9 25 * * *".into(),
+            start: Utc.ymd(2100, 1, 1).and_hms(0, 0, 0),
+            end: None,
+        }
+    }
+
+    fn recurring(username: &str, account_id: &Id) -> RecurringTransaction {
+        RecurringTransaction {
+            id: Id::new(),
+            username: username.into(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 2000.0,
+            price: None,
+            currency: "EUR".into(),
+            fee: 0.0,
+            description: Some("Salary".into()),
+            category_id: None,
+            tags: vec![],
+            schedule: "0 0 9 25 * * *".into(),
+            start: Utc.ymd(2100, 1, 1).and_hms(0, 0, 0),
+            end: None,
+            materialized_until: None,
+        }
+    }
+}
@@ -0,0 +1,819 @@
+use crate::{
+    controller::{export::Attachment, parse_date},
+    model::{
+        Allocation, AllocationBy, ApiError, ApiResult, Cashflow, CashflowGroup, CostBasisMethod,
+        GainsReport, Id, Performance, Period, TaxReport, TaxReportFormat, User,
+    },
+    service::{
+        AccountService, AllocationService, AssetService, CashflowService, GainsService,
+        PerformanceService, TaxService,
+    },
+};
+use chrono::{NaiveDate, Utc};
+use rocket::{get, http::ContentType, Responder, State};
+
+#[derive(Responder)]
+pub enum TaxResponse {
+    Json(ApiResult<TaxReport>),
+    Zip(Attachment),
+}
+
+#[get("/reports/gains?<currency>&<from>&<to>")]
+pub async fn get_gains(
+    currency: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<GainsService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<GainsReport> {
+    let (from, to) = match (parse_date(from), parse_date(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return e.into(),
+    };
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", currency)).into(),
+        Err(e) => return e.into(),
+    }
+
+    match service.report(&user.username, currency, from, to) {
+        Ok(report) => ApiResult::new(200, report),
+        Err(e) => e.into(),
+    }
+}
+
+/// Capital gains schedule and dividend income of a calendar year. Each account uses its
+/// own cost basis method unless one is given.
+#[get("/reports/tax?<currency>&<year>&<method>&<format>")]
+pub async fn get_tax(
+    currency: &str,
+    year: i32,
+    method: Option<&str>,
+    format: Option<&str>,
+    service: &State<TaxService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> TaxResponse {
+    let method = match method.map(|it| it.parse::<CostBasisMethod>()).transpose() {
+        Ok(method) => method,
+        Err(e) => return TaxResponse::Json(ApiError::custom(400, &e).into()),
+    };
+
+    let format = match format.map(|it| it.parse::<TaxReportFormat>()).transpose() {
+        Ok(format) => format.unwrap_or(TaxReportFormat::Json),
+        Err(e) => return TaxResponse::Json(ApiError::custom(400, &e).into()),
+    };
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => {
+            let message = format!("Unknown asset: {}", currency);
+            return TaxResponse::Json(ApiError::custom(400, &message).into());
+        }
+        Err(e) => return TaxResponse::Json(e.into()),
+    }
+
+    let report = match service.report(&user.username, currency, year, method) {
+        Ok(report) => report,
+        Err(e) => return TaxResponse::Json(e.into()),
+    };
+
+    match format {
+        TaxReportFormat::Json => TaxResponse::Json(ApiResult::new(200, report)),
+        TaxReportFormat::Zip => match TaxService::to_zip(&report) {
+            Ok(body) => TaxResponse::Zip(Attachment::new(
+                body,
+                ContentType::ZIP,
+                &format!("tax-{}.zip", year),
+            )),
+            Err(e) => TaxResponse::Json(e.into()),
+        },
+    }
+}
+
+/// Grouping defaults to asset, the latest rates are used when there is no date
+#[get("/reports/allocation?<currency>&<by>&<date>")]
+pub async fn get_allocation(
+    currency: &str,
+    by: Option<&str>,
+    date: Option<&str>,
+    service: &State<AllocationService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Allocation> {
+    let by = match by.map(|it| it.parse::<AllocationBy>()).transpose() {
+        Ok(by) => by.unwrap_or(AllocationBy::Asset),
+        Err(e) => return ApiError::custom(400, &e).into(),
+    };
+
+    let date = match parse_date(date) {
+        Ok(date) => date,
+        Err(e) => return e.into(),
+    };
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", currency)).into(),
+        Err(e) => return e.into(),
+    }
+
+    match service.report(&user.username, currency, by, date) {
+        Ok(allocation) => ApiResult::new(200, allocation),
+        Err(e) => e.into(),
+    }
+}
+
+/// Grouping defaults to month, only transactions with the given tag are counted if there
+/// is one
+#[get("/reports/cashflow?<currency>&<group>&<from>&<to>&<tag>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_cashflow(
+    currency: &str,
+    group: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    tag: Option<&str>,
+    service: &State<CashflowService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Cashflow> {
+    let group = match group.map(|it| it.parse::<CashflowGroup>()).transpose() {
+        Ok(group) => group.unwrap_or(CashflowGroup::Month),
+        Err(e) => return ApiError::custom(400, &e).into(),
+    };
+
+    let (from, to) = match (parse_date(from), parse_date(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return e.into(),
+    };
+
+    match asset_service.exists(currency) {
+        Ok(true) => {}
+        Ok(false) => return ApiError::custom(400, &format!("Unknown asset: {}", currency)).into(),
+        Err(e) => return e.into(),
+    }
+
+    match service.report(&user.username, currency, group, from, to, tag) {
+        Ok(cashflow) => ApiResult::new(200, cashflow),
+        Err(e) => e.into(),
+    }
+}
+
+/// Period defaults to since inception, explicit dates take precedence over it
+#[get("/reports/performance?<currency>&<period>&<from>&<to>")]
+pub async fn get_performance(
+    currency: &str,
+    period: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<PerformanceService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Performance> {
+    let (from, to) = match performance_range(currency, period, from, to, asset_service) {
+        Ok(range) => range,
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_username(&user.username, currency, from, to) {
+        Ok(performance) => ApiResult::new(200, performance),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/accounts/<account_id>/performance?<currency>&<period>&<from>&<to>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_account_performance(
+    account_id: Id,
+    currency: &str,
+    period: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    service: &State<PerformanceService>,
+    account_service: &State<AccountService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Performance> {
+    match account_service.select_owned(&account_id, &user.username) {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    }
+
+    let (from, to) = match performance_range(currency, period, from, to, asset_service) {
+        Ok(range) => range,
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_account_id(&account_id, currency, from, to) {
+        Ok(performance) => ApiResult::new(200, performance),
+        Err(e) => e.into(),
+    }
+}
+
+fn performance_range(
+    currency: &str,
+    period: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    asset_service: &AssetService,
+) -> Result<(Option<NaiveDate>, NaiveDate), ApiError> {
+    let period = match period.map(|it| it.parse::<Period>()).transpose() {
+        Ok(period) => period.unwrap_or(Period::SinceInception),
+        Err(e) => return Err(ApiError::custom(400, &e)),
+    };
+
+    let to = parse_date(to)?.unwrap_or_else(|| Utc::today().naive_utc());
+    let from = parse_date(from)?.or_else(|| period.start(&to));
+
+    if let Some(from) = from {
+        if from > to {
+            return Err(ApiError::custom(400, "Period can't end before it starts"));
+        }
+    }
+
+    match asset_service.exists(currency) {
+        Ok(true) => Ok((from, to)),
+        Ok(false) => Err(ApiError::custom(
+            400,
+            &format!("Unknown asset: {}", currency),
+        )),
+        Err(_) => Err(ApiError::new(500)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, Allocation, Asset, AssetType, Cashflow, Category,
+            CostBasisMethod, ExchangeRate, GainsReport, HoldingPeriod, Id, Performance, TaxReport,
+        },
+        repository::{
+            AccountRepository, AssetRepository, CategoryRepository, ExchangeRateRepository,
+        },
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{
+        http::{ContentType, Status},
+        local::blocking::Client,
+    };
+    use serde_json::json;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn get_gains() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+
+        let res = client
+            .get("/reports/gains?currency=EUR&to=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<GainsReport>().unwrap();
+
+        assert_eq!(1, report.realized.len());
+        let realized = &report.realized[0];
+        assert_eq!(50.0, realized.cost);
+        assert_eq!(112.5, realized.proceeds);
+        assert_eq!(37.5, realized.price_gain);
+        assert_eq!(25.0, realized.fx_gain);
+        assert_eq!(62.5, report.total_realized);
+
+        assert_eq!(1, report.unrealized.len());
+        let unrealized = &report.unrealized[0];
+        assert_eq!(60.0, unrealized.cost);
+        assert_eq!(120.0, unrealized.value);
+        assert_eq!(30.0, unrealized.price_gain);
+        assert_eq!(30.0, unrealized.fx_gain);
+        assert_eq!(60.0, report.total_unrealized);
+        Ok(())
+    }
+
+    #[test]
+    fn get_gains_period() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+
+        let res = client
+            .get("/reports/gains?currency=EUR&from=2021-08-04&to=2021-08-04")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<GainsReport>().unwrap();
+        assert!(report.realized.is_empty());
+        assert_eq!(1, report.unrealized.len());
+        Ok(())
+    }
+
+    #[test]
+    fn get_gains_unknown_currency() {
+        let client = client();
+        let res = client.get("/reports/gains?currency=TST").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn get_tax() -> Result<()> {
+        let client = client();
+        let account = insert_account(&client, "test")?;
+        insert_portfolio_to(&client, &account)?;
+        let income = vec![("dividend", 10.0), ("tax", 1.5)];
+        for (transaction_type, quantity) in income {
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&json!({
+                    "type": transaction_type,
+                    "asset": "BTC",
+                    "quantity": quantity,
+                    "currency": "USD",
+                    "time": "2021-08-03T12:00:00Z",
+                }))
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&json!({
+                "type": "tax",
+                "quantity": 2.0,
+                "currency": "USD",
+                "time": "2021-08-03T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+
+        let res = client.get("/reports/tax?currency=EUR&year=2021").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<TaxReport>().unwrap();
+        assert_eq!(1, report.disposals.len());
+        let disposal = &report.disposals[0];
+        assert_eq!(50.0, disposal.cost);
+        assert_eq!(112.5, disposal.proceeds);
+        assert_eq!(62.5, disposal.gain);
+        assert_eq!(HoldingPeriod::Short, disposal.holding_period);
+        assert_eq!(62.5, report.short_term_gain);
+        assert_eq!(0.0, report.long_term_gain);
+        assert_eq!(7.5, report.total_dividends);
+        assert_eq!(1.125, report.total_withheld);
+        assert_eq!(1.5, report.unattributed_taxes);
+        assert_eq!(6.375, report.dividends[0].net);
+
+        let res = client
+            .get("/reports/tax?currency=EUR&year=2021&method=lifo")
+            .dispatch();
+        let report = res.into_json::<TaxReport>().unwrap();
+        assert_eq!(60.0, report.disposals[0].cost);
+        assert_eq!(52.5, report.total_gain);
+
+        let res = client.get("/reports/tax?currency=EUR&year=2020").dispatch();
+        let report = res.into_json::<TaxReport>().unwrap();
+        assert!(report.disposals.is_empty());
+        assert!(report.dividends.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn get_tax_transfer() -> Result<()> {
+        let client = client();
+        let from = insert_account(&client, "test")?;
+        let to = insert_account(&client, "test")?;
+        let res = client
+            .post(format!("/accounts/{}/transactions", from.id))
+            .json(&json!({
+                "type": "buy",
+                "asset": "BTC",
+                "quantity": 1.0,
+                "price": 100.0,
+                "currency": "USD",
+                "time": "2020-01-02T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let res = client
+            .post("/transfers")
+            .json(&json!({
+                "from_account_id": from.id,
+                "to_account_id": to.id,
+                "asset": "BTC",
+                "quantity": 1.0,
+                "currency": "USD",
+                "time": "2021-03-01T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let res = client
+            .post(format!("/accounts/{}/transactions", to.id))
+            .json(&json!({
+                "type": "sell",
+                "asset": "BTC",
+                "quantity": 1.0,
+                "price": 150.0,
+                "currency": "USD",
+                "time": "2021-08-03T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+
+        let res = client.get("/reports/tax?currency=USD&year=2021").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<TaxReport>().unwrap();
+        assert_eq!(1, report.disposals.len());
+        let disposal = &report.disposals[0];
+        assert_eq!(to.id, disposal.account_id);
+        assert_eq!(Utc.ymd(2020, 1, 2).and_hms(12, 0, 0), disposal.acquired_at);
+        assert_eq!(100.0, disposal.cost);
+        assert_eq!(50.0, disposal.gain);
+        assert_eq!(HoldingPeriod::Long, disposal.holding_period);
+        assert_eq!(50.0, report.long_term_gain);
+
+        let res = client.get("/reports/gains?currency=USD").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let report = res.into_json::<GainsReport>().unwrap();
+        assert_eq!(1, report.realized.len());
+        assert_eq!(50.0, report.total_realized);
+        Ok(())
+    }
+
+    #[test]
+    fn get_tax_zip() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let res = client
+            .get("/reports/tax?currency=EUR&year=2021&format=zip")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.content_type(), Some(ContentType::ZIP));
+        let zip = res.into_bytes().unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(zip))?;
+        let mut disposals = String::new();
+        zip.by_name("disposals.csv")?
+            .read_to_string(&mut disposals)?;
+        assert_eq!(2, disposals.lines().count());
+        assert!(disposals.lines().nth(1).unwrap().starts_with("BTC,1,"));
+
+        let res = client
+            .get("/reports/tax?currency=EUR&year=2021&format=csv")
+            .dispatch();
+        assert_eq!(res.content_type(), Some(ContentType::ZIP));
+        Ok(())
+    }
+
+    #[test]
+    fn get_tax_unknown_method() {
+        let client = client();
+        let res = client
+            .get("/reports/tax?currency=EUR&year=2021&method=hifo")
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    /// Buys BTC for 100 and 120 USD, sells the first one for 150 USD. USD/EUR moves from 0.5
+    /// to 0.75 and BTC/USD ends at 160.
+    fn insert_portfolio(client: &Client) -> Result<()> {
+        let account = insert_account(client, "test")?;
+        insert_portfolio_to(client, &account)
+    }
+
+    fn insert_portfolio_to(client: &Client, account: &Account) -> Result<()> {
+        insert_rates(
+            client,
+            vec![
+                ("USD", "EUR", 0.5, 1),
+                ("USD", "EUR", 0.75, 3),
+                ("BTC", "USD", 160.0, 3),
+            ],
+        )?;
+
+        let transactions = vec![("buy", 100.0, 1), ("buy", 120.0, 2), ("sell", 150.0, 3)];
+        for (transaction_type, price, day) in transactions {
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&json!({
+                    "type": transaction_type,
+                    "asset": "BTC",
+                    "quantity": 1.0,
+                    "price": price,
+                    "currency": "USD",
+                    "time": format!("2021-08-0{}T12:00:00Z", day),
+                }))
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_performance() -> Result<()> {
+        let client = client();
+        insert_flows(&client)?;
+
+        let res = client
+            .get("/reports/performance?currency=USD&to=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let performance = res.into_json::<Performance>().unwrap();
+        assert_eq!(NaiveDate::from_ymd(2021, 8, 1), performance.from);
+        assert_eq!(0.0, performance.start_value);
+        assert_eq!(2310.0, performance.end_value);
+        assert_eq!(2100.0, performance.net_flows);
+        assert!((performance.twr.unwrap() - 0.1).abs() < 1e-9);
+        assert!(performance.xirr.unwrap() > 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn get_performance_ytd() -> Result<()> {
+        let client = client();
+        insert_flows(&client)?;
+
+        let res = client
+            .get("/reports/performance?currency=USD&period=ytd&to=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let performance = res.into_json::<Performance>().unwrap();
+        assert_eq!(NaiveDate::from_ymd(2021, 1, 1), performance.from);
+        Ok(())
+    }
+
+    #[test]
+    fn get_performance_unknown_period() {
+        let client = client();
+        let res = client
+            .get("/reports/performance?currency=USD&period=5y")
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn get_account_performance() -> Result<()> {
+        let client = client();
+        let account = insert_flows(&client)?;
+
+        let res = client
+            .get(format!(
+                "/accounts/{}/performance?currency=USD&from=2021-08-03&to=2021-08-03",
+                account.id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let performance = res.into_json::<Performance>().unwrap();
+        assert_eq!(2200.0, performance.start_value);
+        assert_eq!(0.0, performance.net_flows);
+        assert!((performance.twr.unwrap() - 0.05).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn get_account_performance_foreign() -> Result<()> {
+        let client = client();
+        let account = insert_account(&client, "test2")?;
+        let res = client
+            .get(format!("/accounts/{}/performance?currency=USD", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn get_allocation() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+
+        let res = client
+            .get("/reports/allocation?currency=EUR&date=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let allocation = res.into_json::<Allocation>().unwrap();
+        assert_eq!(112.5 - 52.5, allocation.total);
+        assert_eq!(2, allocation.groups.len());
+        assert_eq!("BTC", allocation.groups[0].key);
+        assert_eq!(112.5, allocation.groups[0].value);
+        assert_eq!("USD", allocation.groups[1].key);
+        assert_eq!(-52.5, allocation.groups[1].value);
+        assert!((allocation.groups[0].weight - 112.5 / 60.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn get_allocation_by_currency() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+        let account = insert_account(&client, "test")?;
+        let asset_repo = client.rocket().state::<AssetRepository>().unwrap();
+        asset_repo.insert(&Asset {
+            code: "VWCE".into(),
+            name: "Vanguard FTSE All-World".into(),
+            asset_type: AssetType::Etf,
+            precision: 2,
+            metadata: vec![("currency".to_string(), "USD".to_string())]
+                .into_iter()
+                .collect(),
+        })?;
+        insert_rates(&client, vec![("VWCE", "EUR", 100.0, 3)])?;
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&json!({
+                "type": "buy",
+                "asset": "VWCE",
+                "quantity": 1.0,
+                "price": 100.0,
+                "currency": "EUR",
+                "time": "2021-08-03T12:00:00Z",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+
+        let res = client
+            .get("/reports/allocation?currency=EUR&by=currency&date=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let allocation = res.into_json::<Allocation>().unwrap();
+        let values: Vec<(&str, f64)> = allocation
+            .groups
+            .iter()
+            .map(|it| (it.key.as_str(), it.value))
+            .collect();
+        assert_eq!(vec![("BTC", 112.5), ("USD", 47.5), ("EUR", -100.0)], values);
+        Ok(())
+    }
+
+    #[test]
+    fn get_allocation_by_account() -> Result<()> {
+        let client = client();
+        insert_portfolio(&client)?;
+
+        let res = client
+            .get("/reports/allocation?currency=EUR&by=account&date=2021-08-03")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let allocation = res.into_json::<Allocation>().unwrap();
+        assert_eq!(1, allocation.groups.len());
+        assert_eq!("Broker", allocation.groups[0].name);
+        assert_eq!(60.0, allocation.groups[0].value);
+        assert_eq!(1.0, allocation.groups[0].weight);
+        Ok(())
+    }
+
+    #[test]
+    fn get_allocation_unknown_grouping() {
+        let client = client();
+        let res = client
+            .get("/reports/allocation?currency=EUR&by=sector")
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn get_cashflow() -> Result<()> {
+        let client = client();
+        let account = insert_account(&client, "test")?;
+        insert_rates(
+            &client,
+            vec![("USD", "EUR", 0.5, 1), ("USD", "EUR", 0.8, 3)],
+        )?;
+        let repo = client.rocket().state::<CategoryRepository>().unwrap();
+        let food = Category {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Food".into(),
+            parent_id: None,
+        };
+        let groceries = Category {
+            id: Id::new(),
+            name: "Groceries".into(),
+            parent_id: Some(food.id.clone()),
+            ..food.clone()
+        };
+        repo.insert(&food)?;
+        repo.insert(&groceries)?;
+
+        let transactions = vec![
+            json!({ "type": "deposit", "quantity": 1000.0, "time": "2021-07-31T12:00:00Z" }),
+            json!({ "type": "withdrawal", "quantity": 100.0, "time": "2021-08-01T12:00:00Z",
+                "category_id": groceries.id, "tags": ["holiday"] }),
+            json!({ "type": "withdrawal", "quantity": 50.0, "time": "2021-08-03T12:00:00Z",
+                "category_id": groceries.id }),
+            json!({ "type": "interest", "quantity": 10.0, "time": "2021-08-03T12:00:00Z" }),
+            json!({ "type": "buy", "asset": "BTC", "quantity": 1.0, "price": 100.0,
+                "time": "2021-08-03T12:00:00Z" }),
+        ];
+        for mut transaction in transactions {
+            transaction["currency"] = json!("USD");
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&transaction)
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+
+        let res = client
+            .get("/reports/cashflow?currency=EUR&from=2021-08-01")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let cashflow = res.into_json::<Cashflow>().unwrap();
+        assert!(cashflow.unconverted.is_empty());
+        assert_eq!(1, cashflow.periods.len());
+        let period = &cashflow.periods[0];
+        assert_eq!("2021-08", period.period);
+        assert_eq!(8.0, period.income);
+        assert_eq!(50.0 + 40.0, period.expense);
+        assert_eq!(8.0 - 90.0, period.net);
+        assert_eq!(2, period.categories.len());
+        assert_eq!("Food / Groceries", period.categories[0].name);
+        assert_eq!(90.0, period.categories[0].expense);
+        assert_eq!(None, period.categories[1].category_id);
+
+        let res = client
+            .get("/reports/cashflow?currency=EUR&group=year&tag=holiday")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let cashflow = res.into_json::<Cashflow>().unwrap();
+        assert_eq!("2021", cashflow.periods[0].period);
+        assert_eq!(50.0, cashflow.expense);
+        assert_eq!(0.0, cashflow.income);
+        Ok(())
+    }
+
+    #[test]
+    fn get_cashflow_unknown_grouping() {
+        let client = client();
+        let res = client
+            .get("/reports/cashflow?currency=EUR&group=week")
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    /// Deposits 1000 USD and buys 10 BTC for it, BTC/USD goes up by 10% a day and another
+    /// 1100 USD are deposited on the second day
+    fn insert_flows(client: &Client) -> Result<Account> {
+        let account = insert_account(client, "test")?;
+        insert_rates(
+            client,
+            vec![
+                ("BTC", "USD", 100.0, 1),
+                ("BTC", "USD", 110.0, 2),
+                ("BTC", "USD", 121.0, 3),
+            ],
+        )?;
+
+        let transactions = vec![
+            json!({ "type": "deposit", "quantity": 1000.0, "day": 1 }),
+            json!({ "type": "buy", "asset": "BTC", "quantity": 10.0, "price": 100.0, "day": 1 }),
+            json!({ "type": "deposit", "quantity": 1100.0, "day": 2 }),
+        ];
+        for transaction in transactions {
+            let res = client
+                .post(format!("/accounts/{}/transactions", account.id))
+                .json(&json!({
+                    "type": transaction["type"],
+                    "asset": transaction["asset"],
+                    "quantity": transaction["quantity"],
+                    "price": transaction["price"],
+                    "currency": "USD",
+                    "time": format!("2021-08-0{}T12:00:00Z", transaction["day"]),
+                }))
+                .dispatch();
+            assert_eq!(res.status(), Status::Created);
+        }
+
+        Ok(account)
+    }
+
+    fn insert_account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let account_repo = client.rocket().state::<AccountRepository>().unwrap();
+        account_repo.insert(&account)?;
+        Ok(account)
+    }
+
+    fn insert_rates(client: &Client, rates: Vec<(&str, &str, f64, u32)>) -> Result<()> {
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        for (quote, base, rate, day) in rates {
+            rate_repo.insert_or_replace_history(
+                &ExchangeRate {
+                    quote: quote.into(),
+                    base: base.into(),
+                    rate,
+                },
+                &NaiveDate::from_ymd(2021, 8, day),
+            )?;
+        }
+
+        Ok(())
+    }
+}
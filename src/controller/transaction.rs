@@ -0,0 +1,538 @@
+use crate::{
+    model::{Account, ApiError, ApiResult, Id, Transaction, TransactionType, User},
+    service::{AccountService, CategoryService, TransactionService},
+};
+use chrono::{DateTime, Utc};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    asset: Option<String>,
+    quantity: f64,
+    price: Option<f64>,
+    currency: String,
+    #[serde(default)]
+    fee: f64,
+    time: DateTime<Utc>,
+    lot_id: Option<Id>,
+    new_asset: Option<String>,
+    description: Option<String>,
+    category_id: Option<Id>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/accounts/<account_id>/transactions")]
+pub async fn get(
+    account_id: Id,
+    account_service: &State<AccountService>,
+    service: &State<TransactionService>,
+    user: User,
+) -> ApiResult<Vec<Transaction>> {
+    let account = match select_account(account_service, &account_id, &user) {
+        Ok(Some(account)) => account,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.select_by_account_id(&account.id) {
+        Ok(transactions) => ApiResult::new(200, transactions),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/accounts/<account_id>/transactions/<id>")]
+pub async fn get_by_id(
+    account_id: Id,
+    id: Id,
+    account_service: &State<AccountService>,
+    service: &State<TransactionService>,
+    user: User,
+) -> ApiResult<Transaction> {
+    select_owned(account_service, service, &account_id, &id, &user).into()
+}
+
+#[post("/accounts/<account_id>/transactions", data = "<input>")]
+pub async fn post(
+    account_id: Id,
+    input: Json<PostInput>,
+    account_service: &State<AccountService>,
+    service: &State<TransactionService>,
+    category_service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Transaction> {
+    let account = match select_account(account_service, &account_id, &user) {
+        Ok(Some(account)) => account,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    if let Err(e) = check_category(category_service, &input, &user) {
+        return e.into();
+    }
+
+    let transaction = Transaction {
+        id: Id::new(),
+        account_id: account.id,
+        transaction_type: input.transaction_type,
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        price: input.price,
+        currency: input.currency.clone(),
+        fee: input.fee,
+        time: input.time,
+        lot_id: input.lot_id.clone(),
+        external_id: None,
+        new_asset: input.new_asset.clone(),
+        transfer_id: None,
+        description: input.description.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+    };
+
+    if let Err(e) = service.validate(&transaction) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&transaction) {
+        return e.into();
+    }
+
+    ApiResult::new(201, transaction)
+}
+
+#[put("/accounts/<account_id>/transactions/<id>", data = "<input>")]
+pub async fn put(
+    account_id: Id,
+    id: Id,
+    input: Json<PutInput>,
+    account_service: &State<AccountService>,
+    service: &State<TransactionService>,
+    category_service: &State<CategoryService>,
+    user: User,
+) -> ApiResult<Transaction> {
+    let transaction = match select_owned(account_service, service, &account_id, &id, &user) {
+        Ok(Some(transaction)) => transaction,
+        res => return res.into(),
+    };
+
+    if let Some(transfer_id) = &transaction.transfer_id {
+        let message = format!("Transfers are changed through /transfers/{}", transfer_id);
+        return ApiError::custom(400, &message).into();
+    }
+
+    if let Err(e) = check_category(category_service, &input, &user) {
+        return e.into();
+    }
+
+    let transaction = Transaction {
+        transaction_type: input.transaction_type,
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        price: input.price,
+        currency: input.currency.clone(),
+        fee: input.fee,
+        time: input.time,
+        lot_id: input.lot_id.clone(),
+        new_asset: input.new_asset.clone(),
+        description: input.description.clone(),
+        category_id: input.category_id.clone(),
+        tags: input.tags.clone(),
+        ..transaction
+    };
+
+    if let Err(e) = service.validate(&transaction) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&transaction) {
+        return e.into();
+    }
+
+    ApiResult::new(200, transaction)
+}
+
+#[delete("/accounts/<account_id>/transactions/<id>")]
+pub async fn delete(
+    account_id: Id,
+    id: Id,
+    account_service: &State<AccountService>,
+    service: &State<TransactionService>,
+    user: User,
+) -> ApiResult<Transaction> {
+    let transaction = match select_owned(account_service, service, &account_id, &id, &user) {
+        Ok(Some(transaction)) => transaction,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&transaction.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, transaction)
+}
+
+/// Other users' categories are treated as unknown
+fn check_category(
+    category_service: &CategoryService,
+    input: &PostInput,
+    user: &User,
+) -> Result<(), ApiError> {
+    let category_id = match &input.category_id {
+        Some(category_id) => category_id,
+        None => return Ok(()),
+    };
+
+    match category_service.select_owned(category_id, &user.username) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(ApiError::custom(
+            400,
+            &format!("Unknown category: {}", category_id),
+        )),
+        Err(_) => Err(ApiError::new(500)),
+    }
+}
+
+fn select_account(
+    account_service: &AccountService,
+    account_id: &Id,
+    user: &User,
+) -> anyhow::Result<Option<Account>> {
+    account_service.select_owned(account_id, &user.username)
+}
+
+/// Transactions of other users' accounts are treated as non-existent
+fn select_owned(
+    account_service: &AccountService,
+    service: &TransactionService,
+    account_id: &Id,
+    id: &Id,
+    user: &User,
+) -> anyhow::Result<Option<Transaction>> {
+    match select_account(account_service, account_id, user)? {
+        Some(account) => service.select_by_account_id_and_id(&account.id, id),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::transaction::PostInput,
+        model::{
+            Account, AccountType, Asset, AssetType, Category, CostBasisMethod, Id, Transaction,
+            TransactionType,
+        },
+        repository::{
+            AccountRepository, AssetRepository, CategoryRepository, TransactionRepository,
+        },
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transaction = transaction(&account.id);
+        repo.insert(&transaction)?;
+        repo.insert(&self::transaction(&Id::new()))?;
+        let res = client
+            .get(format!("/accounts/{}/transactions", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(
+            vec![transaction],
+            res.into_json::<Vec<Transaction>>().unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_foreign_account() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test2")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        repo.insert(&transaction(&account.id))?;
+        let res = client
+            .get(format!("/accounts/{}/transactions", account.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transaction = transaction(&account.id);
+        repo.insert(&transaction)?;
+        let res = client
+            .get(format!(
+                "/accounts/{}/transactions/{}",
+                account.id, transaction.id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(transaction, res.into_json::<Transaction>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_other_account() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let other_account = self::account(&client, "test2")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transaction = transaction(&other_account.id);
+        repo.insert(&transaction)?;
+        let res = client
+            .get(format!(
+                "/accounts/{}/transactions/{}",
+                account.id, transaction.id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&input())
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let transaction = res.into_json::<Transaction>().unwrap();
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert_eq!(
+            Some(transaction.clone()),
+            repo.select_by_id(&transaction.id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign_category() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let category = Category {
+            id: Id::new(),
+            username: "test2".into(),
+            name: "Food".into(),
+            parent_id: None,
+        };
+        let category_repo = client.rocket().state::<CategoryRepository>().unwrap();
+        category_repo.insert(&category)?;
+        let input = PostInput {
+            category_id: Some(category.id),
+            ..input()
+        };
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_buy_without_price() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            price: None,
+            ..input()
+        };
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_transfer() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            transaction_type: TransactionType::Transfer,
+            price: None,
+            ..input()
+        };
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_ticker_change() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        client
+            .rocket()
+            .state::<AssetRepository>()
+            .unwrap()
+            .insert(&Asset {
+                code: "WBTC".into(),
+                name: "Wrapped Bitcoin".into(),
+                asset_type: AssetType::Crypto,
+                precision: 8,
+                metadata: BTreeMap::new(),
+            })?;
+        let ticker_change = |new_asset: Option<&str>| PostInput {
+            transaction_type: TransactionType::TickerChange,
+            quantity: 1.0,
+            price: None,
+            fee: 0.0,
+            new_asset: new_asset.map(|it| it.into()),
+            ..input()
+        };
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&ticker_change(None))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&ticker_change(Some("WBTC")))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let transaction = res.into_json::<Transaction>().unwrap();
+        assert_eq!(Some("WBTC".into()), transaction.new_asset);
+        Ok(())
+    }
+
+    #[test]
+    fn post_unknown_asset() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let input = PostInput {
+            asset: Some("TST".into()),
+            ..input()
+        };
+        let res = client
+            .post(format!("/accounts/{}/transactions", account.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transaction = transaction(&account.id);
+        repo.insert(&transaction)?;
+        let input = PostInput {
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 1000.0,
+            price: None,
+            ..input()
+        };
+        let res = client
+            .put(format!(
+                "/accounts/{}/transactions/{}",
+                account.id, transaction.id
+            ))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let transaction = repo.select_by_id(&transaction.id)?.unwrap();
+        assert_eq!(TransactionType::Deposit, transaction.transaction_type);
+        assert_eq!(1000.0, transaction.quantity);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let account = account(&client, "test")?;
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let transaction = transaction(&account.id);
+        repo.insert(&transaction)?;
+        let res = client
+            .delete(format!(
+                "/accounts/{}/transactions/{}",
+                account.id, transaction.id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(repo.select_by_id(&transaction.id)?.is_none());
+        Ok(())
+    }
+
+    fn input() -> PostInput {
+        PostInput {
+            transaction_type: TransactionType::Buy,
+            asset: Some("BTC".into()),
+            quantity: 0.5,
+            price: Some(40000.0),
+            currency: "USD".into(),
+            fee: 10.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            new_asset: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+
+    fn account(client: &Client, username: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let repo = client.rocket().state::<AccountRepository>().unwrap();
+        repo.insert(&account)?;
+        Ok(account)
+    }
+
+    fn transaction(account_id: &Id) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Buy,
+            asset: Some("BTC".into()),
+            quantity: 0.5,
+            price: Some(40000.0),
+            currency: "USD".into(),
+            fee: 10.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+}
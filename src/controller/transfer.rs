@@ -0,0 +1,508 @@
+use crate::{
+    model::{ApiError, ApiResult, Id, Transfer, User},
+    service::{AccountService, TransferService},
+};
+use chrono::{DateTime, Utc};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    from_account_id: Id,
+    to_account_id: Id,
+    asset: Option<String>,
+    quantity: f64,
+    currency: String,
+    /// Same as `currency` if it's missing
+    to_currency: Option<String>,
+    /// Latest exchange rate is used if it's missing
+    rate: Option<f64>,
+    #[serde(default)]
+    fee: f64,
+    time: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PutInput {
+    asset: Option<String>,
+    quantity: f64,
+    currency: String,
+    to_currency: Option<String>,
+    rate: Option<f64>,
+    #[serde(default)]
+    fee: f64,
+    time: DateTime<Utc>,
+}
+
+#[get("/transfers")]
+pub async fn get(service: &State<TransferService>, user: User) -> ApiResult<Vec<Transfer>> {
+    match service.select_by_username(&user.username) {
+        Ok(transfers) => ApiResult::new(200, transfers),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/transfers/<id>")]
+pub async fn get_by_id(
+    id: Id,
+    service: &State<TransferService>,
+    user: User,
+) -> ApiResult<Transfer> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/transfers", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    account_service: &State<AccountService>,
+    service: &State<TransferService>,
+    user: User,
+) -> ApiResult<Transfer> {
+    for account_id in &[&input.from_account_id, &input.to_account_id] {
+        match account_service.select_owned(account_id, &user.username) {
+            Ok(Some(_)) => {}
+            Ok(None) => return ApiError::new(404).into(),
+            Err(e) => return e.into(),
+        }
+    }
+
+    let to_currency = input.to_currency.as_ref().unwrap_or(&input.currency);
+
+    let rate = match resolve_rate(service, &input.currency, to_currency, input.rate) {
+        Ok(rate) => rate,
+        Err(e) => return e.into(),
+    };
+
+    let transfer = Transfer {
+        id: Id::new(),
+        from_account_id: input.from_account_id.clone(),
+        to_account_id: input.to_account_id.clone(),
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        from_currency: input.currency.clone(),
+        to_currency: to_currency.clone(),
+        rate,
+        fee: input.fee,
+        time: input.time,
+        from_transaction_id: Id::new(),
+        to_transaction_id: Id::new(),
+    };
+
+    if let Err(e) = service.validate(&transfer) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&transfer) {
+        return e.into();
+    }
+
+    ApiResult::new(201, transfer)
+}
+
+#[put("/transfers/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<TransferService>,
+    user: User,
+) -> ApiResult<Transfer> {
+    let transfer = match service.select_owned(&id, &user.username) {
+        Ok(Some(transfer)) => transfer,
+        res => return res.into(),
+    };
+
+    let to_currency = input.to_currency.as_ref().unwrap_or(&input.currency);
+
+    let rate = match resolve_rate(service, &input.currency, to_currency, input.rate) {
+        Ok(rate) => rate,
+        Err(e) => return e.into(),
+    };
+
+    let transfer = Transfer {
+        asset: input.asset.clone(),
+        quantity: input.quantity,
+        from_currency: input.currency.clone(),
+        to_currency: to_currency.clone(),
+        rate,
+        fee: input.fee,
+        time: input.time,
+        ..transfer
+    };
+
+    if let Err(e) = service.validate(&transfer) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&transfer) {
+        return e.into();
+    }
+
+    ApiResult::new(200, transfer)
+}
+
+#[delete("/transfers/<id>")]
+pub async fn delete(id: Id, service: &State<TransferService>, user: User) -> ApiResult<Transfer> {
+    let transfer = match service.select_owned(&id, &user.username) {
+        Ok(Some(transfer)) => transfer,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&transfer) {
+        return e.into();
+    }
+
+    ApiResult::new(200, transfer)
+}
+
+/// Explicit rate takes precedence over the latest known one
+fn resolve_rate(
+    service: &TransferService,
+    from: &str,
+    to: &str,
+    rate: Option<f64>,
+) -> Result<f64, ApiError> {
+    if let Some(rate) = rate {
+        return Ok(rate);
+    }
+
+    match service.rate(from, to) {
+        Ok(Some(rate)) => Ok(rate),
+        Ok(None) => Err(ApiError::custom(
+            400,
+            &format!("Unknown exchange rate: {}/{}", from, to),
+        )),
+        Err(_) => Err(ApiError::new(500)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::transfer::{PostInput, PutInput},
+        model::{
+            Account, AccountType, CostBasisMethod, ExchangeRate, Id, Performance, Transaction,
+            TransactionType, Transfer,
+        },
+        repository::{
+            AccountRepository, ExchangeRateRepository, LotRepository, TransactionRepository,
+        },
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rocket::{http::Status, local::blocking::Client};
+    use serde_json::json;
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "EUR")?;
+        client
+            .rocket()
+            .state::<ExchangeRateRepository>()
+            .unwrap()
+            .insert_or_replace(&ExchangeRate {
+                quote: "USD".into(),
+                base: "EUR".into(),
+                rate: 0.8,
+            })?;
+        let res = client
+            .post("/transfers")
+            .json(&input(&from, &to, Some("EUR"), None))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let transfer = res.into_json::<Transfer>().unwrap();
+        assert_eq!(0.8, transfer.rate);
+
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let outgoing = &repo.select_by_account_id(&from.id)?[0];
+        assert_eq!(-100.0, outgoing.quantity);
+        assert_eq!(1.0, outgoing.fee);
+        let incoming = &repo.select_by_account_id(&to.id)?[0];
+        assert_eq!(80.0, incoming.quantity);
+        assert_eq!("EUR", incoming.currency);
+        assert_eq!(Some(transfer.id), incoming.transfer_id);
+        Ok(())
+    }
+
+    #[test]
+    fn post_unknown_rate() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "EUR")?;
+        let res = client
+            .post("/transfers")
+            .json(&input(&from, &to, Some("EUR"), None))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn post_foreign() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test2", "USD")?;
+        let res = client
+            .post("/transfers")
+            .json(&input(&from, &to, None, None))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        let transfer = insert(&client, &from, &to)?;
+        let res = client.get("/transfers").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![transfer], res.into_json::<Vec<Transfer>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_foreign() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test2", "USD")?;
+        let to = account(&client, "test2", "USD")?;
+        let transfer = Transfer {
+            id: Id::new(),
+            from_account_id: from.id.clone(),
+            to_account_id: to.id.clone(),
+            asset: None,
+            quantity: 100.0,
+            from_currency: "USD".into(),
+            to_currency: "USD".into(),
+            rate: 1.0,
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            from_transaction_id: Id::new(),
+            to_transaction_id: Id::new(),
+        };
+        let (outgoing, incoming) = transfer.legs();
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        repo.insert(&outgoing)?;
+        repo.insert(&incoming)?;
+        let res = client.get(format!("/transfers/{}", transfer.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "EUR")?;
+        let transfer = insert(&client, &from, &to)?;
+        let input = PutInput {
+            asset: None,
+            quantity: 50.0,
+            currency: "USD".into(),
+            to_currency: Some("EUR".into()),
+            rate: Some(0.9),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+        };
+        let res = client
+            .put(format!("/transfers/{}", transfer.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        let incoming = &repo.select_by_account_id(&to.id)?[0];
+        assert_eq!(transfer.to_transaction_id, incoming.id);
+        assert_eq!(45.0, incoming.quantity);
+        Ok(())
+    }
+
+    #[test]
+    fn put_leg() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        let transfer = insert(&client, &from, &to)?;
+        let res = client
+            .put(format!(
+                "/accounts/{}/transactions/{}",
+                from.id, transfer.from_transaction_id
+            ))
+            .json(&json!({
+                "type": "transfer",
+                "quantity": -50.0,
+                "currency": "USD",
+                "time": "2021-08-01T12:00:00Z"
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        let transfer = insert(&client, &from, &to)?;
+        let res = client
+            .delete(format!("/transfers/{}", transfer.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert!(repo.select_by_transfer_id(&transfer.id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_leg() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        let transfer = insert(&client, &from, &to)?;
+        let res = client
+            .delete(format!(
+                "/accounts/{}/transactions/{}",
+                to.id, transfer.to_transaction_id
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert!(repo.select_by_account_id(&from.id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_account() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        let res = client
+            .post(format!("/accounts/{}/transactions", from.id))
+            .json(&json!({
+                "type": "buy",
+                "asset": "BTC",
+                "quantity": 1.0,
+                "price": 100.0,
+                "currency": "USD",
+                "time": "2021-07-01T12:00:00Z"
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let res = client
+            .post("/transfers")
+            .json(&PostInput {
+                asset: Some("BTC".into()),
+                quantity: 1.0,
+                fee: 0.0,
+                ..input(&from, &to, None, None)
+            })
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let lot_repo = client.rocket().state::<LotRepository>().unwrap();
+        assert_eq!(1, lot_repo.select_by_account_id(&to.id)?.len());
+
+        let res = client.delete(format!("/accounts/{}", from.id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let repo = client.rocket().state::<TransactionRepository>().unwrap();
+        assert!(repo.select_by_account_id(&to.id)?.is_empty());
+        assert!(lot_repo.select_by_account_id(&to.id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn performance() -> Result<()> {
+        let client = client();
+        let from = account(&client, "test", "USD")?;
+        let to = account(&client, "test", "USD")?;
+        client
+            .rocket()
+            .state::<TransactionRepository>()
+            .unwrap()
+            .insert(&Transaction {
+                id: Id::new(),
+                account_id: from.id.clone(),
+                transaction_type: TransactionType::Deposit,
+                asset: None,
+                quantity: 1000.0,
+                price: None,
+                currency: "USD".into(),
+                fee: 0.0,
+                time: Utc.ymd(2021, 7, 1).and_hms(12, 0, 0),
+                lot_id: None,
+                external_id: None,
+                new_asset: None,
+                transfer_id: None,
+                description: None,
+                category_id: None,
+                tags: vec![],
+            })?;
+        insert(&client, &from, &to)?;
+
+        let res = client
+            .get("/reports/performance?currency=USD&to=2021-08-03")
+            .dispatch();
+        let performance = res.into_json::<Performance>().unwrap();
+        assert_eq!(1000.0, performance.net_flows);
+        assert_eq!(999.0, performance.end_value);
+
+        let res = client
+            .get(format!(
+                "/accounts/{}/performance?currency=USD&to=2021-08-03",
+                to.id
+            ))
+            .dispatch();
+        let performance = res.into_json::<Performance>().unwrap();
+        assert_eq!(100.0, performance.net_flows);
+        Ok(())
+    }
+
+    fn insert(client: &Client, from: &Account, to: &Account) -> Result<Transfer> {
+        let res = client
+            .post("/transfers")
+            .json(&input(from, to, Some(&to.currency), Some(1.0)))
+            .dispatch();
+        assert_eq!(res.status(), Status::Created);
+        Ok(res.into_json::<Transfer>().unwrap())
+    }
+
+    fn input(
+        from: &Account,
+        to: &Account,
+        to_currency: Option<&str>,
+        rate: Option<f64>,
+    ) -> PostInput {
+        PostInput {
+            from_account_id: from.id.clone(),
+            to_account_id: to.id.clone(),
+            asset: None,
+            quantity: 100.0,
+            currency: "USD".into(),
+            to_currency: to_currency.map(|it| it.into()),
+            rate,
+            fee: 1.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+        }
+    }
+
+    fn account(client: &Client, username: &str, currency: &str) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: username.into(),
+            name: "Bank".into(),
+            account_type: AccountType::Bank,
+            currency: currency.into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        client
+            .rocket()
+            .state::<AccountRepository>()
+            .unwrap()
+            .insert(&account)?;
+        Ok(account)
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    model::{ApiError, ApiResult, Id, User, Watchlist, WatchlistQuotes},
+    service::{AssetService, WatchlistService},
+};
+use chrono::Utc;
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    name: String,
+    assets: Vec<String>,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/watchlists")]
+pub async fn get(service: &State<WatchlistService>, user: User) -> ApiResult<Vec<Watchlist>> {
+    match service.select_by_username(&user.username) {
+        Ok(watchlists) => ApiResult::new(200, watchlists),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/watchlists/<id>")]
+pub async fn get_by_id(
+    id: Id,
+    service: &State<WatchlistService>,
+    user: User,
+) -> ApiResult<Watchlist> {
+    service.select_owned(&id, &user.username).into()
+}
+
+#[post("/watchlists", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<WatchlistService>,
+    user: User,
+) -> ApiResult<Watchlist> {
+    let watchlist = Watchlist {
+        id: Id::new(),
+        username: user.username.clone(),
+        name: input.name.clone(),
+        assets: input.assets.clone(),
+    };
+
+    if let Err(e) = service.validate(&watchlist) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&watchlist) {
+        return e.into();
+    }
+
+    ApiResult::new(201, watchlist)
+}
+
+#[put("/watchlists/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<WatchlistService>,
+    user: User,
+) -> ApiResult<Watchlist> {
+    let watchlist = match service.select_owned(&id, &user.username) {
+        Ok(Some(watchlist)) => watchlist,
+        res => return res.into(),
+    };
+
+    let watchlist = Watchlist {
+        name: input.name.clone(),
+        assets: input.assets.clone(),
+        ..watchlist
+    };
+
+    if let Err(e) = service.validate(&watchlist) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&watchlist) {
+        return e.into();
+    }
+
+    ApiResult::new(200, watchlist)
+}
+
+#[delete("/watchlists/<id>")]
+pub async fn delete(id: Id, service: &State<WatchlistService>, user: User) -> ApiResult<Watchlist> {
+    let watchlist = match service.select_owned(&id, &user.username) {
+        Ok(Some(watchlist)) => watchlist,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&watchlist.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, watchlist)
+}
+
+/// Quotes of every watchlist
+#[get("/watchlists/quotes?<currency>")]
+pub async fn get_quotes(
+    currency: &str,
+    service: &State<WatchlistService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<Vec<WatchlistQuotes>> {
+    if let Err(e) = check_currency(asset_service, currency) {
+        return e.into();
+    }
+
+    let watchlists = match service.select_by_username(&user.username) {
+        Ok(watchlists) => watchlists,
+        Err(e) => return e.into(),
+    };
+
+    let today = Utc::today().naive_utc();
+    let mut quotes = vec![];
+
+    for watchlist in watchlists {
+        match service.quotes(&watchlist, currency, &today) {
+            Ok(it) => quotes.push(it),
+            Err(e) => return e.into(),
+        }
+    }
+
+    ApiResult::new(200, quotes)
+}
+
+#[get("/watchlists/<id>/quotes?<currency>")]
+pub async fn get_quotes_by_id(
+    id: Id,
+    currency: &str,
+    service: &State<WatchlistService>,
+    asset_service: &State<AssetService>,
+    user: User,
+) -> ApiResult<WatchlistQuotes> {
+    if let Err(e) = check_currency(asset_service, currency) {
+        return e.into();
+    }
+
+    let watchlist = match service.select_owned(&id, &user.username) {
+        Ok(Some(watchlist)) => watchlist,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.quotes(&watchlist, currency, &Utc::today().naive_utc()) {
+        Ok(quotes) => ApiResult::new(200, quotes),
+        Err(e) => e.into(),
+    }
+}
+
+fn check_currency(asset_service: &AssetService, currency: &str) -> Result<(), ApiError> {
+    match asset_service.exists(currency) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::custom(
+            400,
+            &format!("Unknown asset: {}", currency),
+        )),
+        Err(_) => Err(ApiError::new(500)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::watchlist::PostInput,
+        model::{ExchangeRate, Id, Watchlist, WatchlistQuotes},
+        repository::{ExchangeRateRepository, WatchlistRepository},
+        test::client,
+    };
+    use anyhow::Result;
+    use chrono::Utc;
+    use rocket::http::Status;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WatchlistRepository>().unwrap();
+        let watchlist = watchlist("test");
+        repo.insert(&watchlist)?;
+        repo.insert(&self::watchlist("test2"))?;
+        let res = client.get("/watchlists").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![watchlist], res.into_json::<Vec<Watchlist>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let input = PostInput {
+            name: "Crypto".into(),
+            assets: vec!["BTC".into(), "USD".into()],
+        };
+        let res = client.post("/watchlists").json(&input).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let watchlist = res.into_json::<Watchlist>().unwrap();
+        let repo = client.rocket().state::<WatchlistRepository>().unwrap();
+        assert_eq!(Some(watchlist.clone()), repo.select_by_id(&watchlist.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_unknown_asset() {
+        let client = client();
+        let input = PostInput {
+            name: "Crypto".into(),
+            assets: vec!["BTC".into(), "NOPE".into()],
+        };
+        let res = client.post("/watchlists").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn delete_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WatchlistRepository>().unwrap();
+        let watchlist = watchlist("test2");
+        repo.insert(&watchlist)?;
+        let res = client
+            .delete(format!("/watchlists/{}", watchlist.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    /// BTC rose from 40000 to 50000 EUR since yesterday, USD has no rates
+    #[test]
+    fn get_quotes_by_id() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WatchlistRepository>().unwrap();
+        let watchlist = Watchlist {
+            assets: vec!["BTC".into(), "USD".into()],
+            ..watchlist("test")
+        };
+        repo.insert(&watchlist)?;
+        let rate_repo = client.rocket().state::<ExchangeRateRepository>().unwrap();
+        rate_repo.insert_or_replace(&rate("BTC", 50000.0))?;
+        rate_repo
+            .insert_or_replace_history(&rate("BTC", 40000.0), &Utc::today().naive_utc().pred())?;
+
+        let res = client
+            .get(format!("/watchlists/{}/quotes?currency=EUR", watchlist.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let quotes = res.into_json::<WatchlistQuotes>().unwrap();
+        assert_eq!(2, quotes.quotes.len());
+        assert_eq!(Some(50000.0), quotes.quotes[0].price);
+        assert_eq!(Some(10000.0), quotes.quotes[0].change);
+        assert_eq!(Some(0.25), quotes.quotes[0].change_percent);
+        assert_eq!(None, quotes.quotes[1].price);
+
+        let res = client
+            .get(format!("/watchlists/{}/quotes?currency=NOPE", watchlist.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    fn rate(quote: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            quote: quote.into(),
+            base: "EUR".into(),
+            rate,
+        }
+    }
+
+    fn watchlist(username: &str) -> Watchlist {
+        Watchlist {
+            id: Id::new(),
+            username: username.into(),
+            name: "Crypto".into(),
+            assets: vec!["BTC".into()],
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{
+    model::{ApiError, ApiResult, Id, User, Webhook, WebhookDelivery, WebhookEvent},
+    service::WebhookService,
+};
+use rocket::{delete, get, post, put, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct PostInput {
+    url: String,
+    events: Vec<WebhookEvent>,
+}
+
+pub type PutInput = PostInput;
+
+#[get("/webhooks")]
+pub async fn get(service: &State<WebhookService>, user: User) -> ApiResult<Vec<Webhook>> {
+    match service.select_by_username(&user.username) {
+        Ok(webhooks) => ApiResult::new(200, webhooks),
+        Err(e) => e.into(),
+    }
+}
+
+#[get("/webhooks/<id>")]
+pub async fn get_by_id(id: Id, service: &State<WebhookService>, user: User) -> ApiResult<Webhook> {
+    select_owned(service, &id, &user).into()
+}
+
+#[post("/webhooks", data = "<input>")]
+pub async fn post(
+    input: Json<PostInput>,
+    service: &State<WebhookService>,
+    user: User,
+) -> ApiResult<Webhook> {
+    let webhook = Webhook {
+        id: Id::new(),
+        username: user.username.clone(),
+        url: input.url.clone(),
+        secret: WebhookService::generate_secret(),
+        events: input.events.clone(),
+    };
+
+    if let Err(e) = service.validate(&webhook) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.insert(&webhook) {
+        return e.into();
+    }
+
+    ApiResult::new(201, webhook)
+}
+
+#[put("/webhooks/<id>", data = "<input>")]
+pub async fn put(
+    id: Id,
+    input: Json<PutInput>,
+    service: &State<WebhookService>,
+    user: User,
+) -> ApiResult<Webhook> {
+    let webhook = match select_owned(service, &id, &user) {
+        Ok(Some(webhook)) => webhook,
+        res => return res.into(),
+    };
+
+    let webhook = Webhook {
+        url: input.url.clone(),
+        events: input.events.clone(),
+        ..webhook
+    };
+
+    if let Err(e) = service.validate(&webhook) {
+        return ApiError::custom(400, &e.to_string()).into();
+    }
+
+    if let Err(e) = service.update(&webhook) {
+        return e.into();
+    }
+
+    ApiResult::new(200, webhook)
+}
+
+#[delete("/webhooks/<id>")]
+pub async fn delete(id: Id, service: &State<WebhookService>, user: User) -> ApiResult<Webhook> {
+    let webhook = match select_owned(service, &id, &user) {
+        Ok(Some(webhook)) => webhook,
+        res => return res.into(),
+    };
+
+    if let Err(e) = service.delete(&webhook.id) {
+        return e.into();
+    }
+
+    ApiResult::new(200, webhook)
+}
+
+#[get("/webhooks/<id>/deliveries")]
+pub async fn get_deliveries(
+    id: Id,
+    service: &State<WebhookService>,
+    user: User,
+) -> ApiResult<Vec<WebhookDelivery>> {
+    let webhook = match select_owned(service, &id, &user) {
+        Ok(Some(webhook)) => webhook,
+        Ok(None) => return ApiError::new(404).into(),
+        Err(e) => return e.into(),
+    };
+
+    match service.select_deliveries(&webhook.id) {
+        Ok(deliveries) => ApiResult::new(200, deliveries),
+        Err(e) => e.into(),
+    }
+}
+
+/// Other users' webhooks are treated as non-existent
+fn select_owned(service: &WebhookService, id: &Id, user: &User) -> anyhow::Result<Option<Webhook>> {
+    Ok(service
+        .select_by_id(id)?
+        .filter(|it| it.username == user.username))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        controller::webhook::PostInput,
+        model::{Id, Webhook, WebhookDelivery, WebhookEvent},
+        repository::WebhookRepository,
+        service::WebhookService,
+        test::client,
+    };
+    use anyhow::Result;
+    use rocket::http::Status;
+    use serde_json::json;
+
+    #[test]
+    fn get() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = webhook("test");
+        repo.insert(&webhook)?;
+        repo.insert(&self::webhook("test2"))?;
+        let res = client.get("/webhooks").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(vec![webhook], res.into_json::<Vec<Webhook>>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = webhook("test");
+        repo.insert(&webhook)?;
+        let res = client.get(format!("/webhooks/{}", webhook.id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(webhook, res.into_json::<Webhook>().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_id_foreign() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = webhook("test2");
+        repo.insert(&webhook)?;
+        let res = client.get(format!("/webhooks/{}", webhook.id)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn post() -> Result<()> {
+        let client = client();
+        let res = client.post("/webhooks").json(&input()).dispatch();
+        assert_eq!(res.status(), Status::Created);
+        let webhook = res.into_json::<Webhook>().unwrap();
+        assert_eq!(64, webhook.secret.len());
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        assert_eq!(Some(webhook.clone()), repo.select_by_id(&webhook.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn post_no_events() {
+        let client = client();
+        let input = PostInput {
+            events: vec![],
+            ..input()
+        };
+        let res = client.post("/webhooks").json(&input).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn put() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = webhook("test");
+        repo.insert(&webhook)?;
+        let input = PostInput {
+            url: "https://example.com/hook2".into(),
+            events: vec![WebhookEvent::SyncFailed],
+        };
+        let res = client
+            .put(format!("/webhooks/{}", webhook.id))
+            .json(&input)
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let updated = repo.select_by_id(&webhook.id)?.unwrap();
+        assert_eq!(input.url, updated.url);
+        assert_eq!(input.events, updated.events);
+        assert_eq!(webhook.secret, updated.secret);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let webhook = webhook("test");
+        repo.insert(&webhook)?;
+        let res = client
+            .delete(format!("/webhooks/{}", webhook.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(repo.select_by_id(&webhook.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn get_deliveries() -> Result<()> {
+        let client = client();
+        let repo = client.rocket().state::<WebhookRepository>().unwrap();
+        let service = client.rocket().state::<WebhookService>().unwrap();
+        let webhook = webhook("test");
+        repo.insert(&webhook)?;
+        service.enqueue(Some("test"), WebhookEvent::AlertFired, &json!({}))?;
+        let res = client
+            .get(format!("/webhooks/{}/deliveries", webhook.id))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let deliveries = res.into_json::<Vec<WebhookDelivery>>().unwrap();
+        assert_eq!(1, deliveries.len());
+        assert_eq!(WebhookEvent::AlertFired, deliveries[0].event);
+        Ok(())
+    }
+
+    fn input() -> PostInput {
+        PostInput {
+            url: "https://example.com/hook".into(),
+            events: vec![WebhookEvent::RateUpdated, WebhookEvent::AlertFired],
+        }
+    }
+
+    fn webhook(username: &str) -> Webhook {
+        Webhook {
+            id: Id::new(),
+            username: username.into(),
+            url: "https://example.com/hook".into(),
+            secret: WebhookService::generate_secret(),
+            events: vec![WebhookEvent::RateUpdated, WebhookEvent::AlertFired],
+        }
+    }
+}
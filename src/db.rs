@@ -1,14 +1,25 @@
 use crate::{
     conf::{Conf, Migration},
-    provider::{Ecb, Iex, Provider},
-    repository::ExchangeRateRepository,
+    notifier::WebhookNotifier,
+    provider::{Ecb, Iex, Provider, SyncListener},
+    repository::{
+        AccountRepository, AlertEventRepository, AlertRepository, AssetRepository,
+        ExchangeRateRepository, LotMatchRepository, LotRepository, PortfolioSnapshotRepository,
+        RecurringExceptionRepository, RecurringTransactionRepository, TransactionRepository,
+        WebhookDeliveryRepository, WebhookRepository,
+    },
+    service::{
+        AlertService, AssetService, ExchangeRateService, LotService, RecurringService,
+        SnapshotService, TransactionService, ValuationService, WebhookService,
+    },
 };
 use anyhow::{Context, Error, Result};
-use futures::future::join_all;
+use chrono::Utc;
+use futures::{future::join_all, join};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
-use std::{fs::remove_file, path::Path};
+use std::{fs::remove_file, path::Path, sync::Arc};
 use tracing::{info, warn};
 
 #[derive(Debug)]
@@ -116,8 +127,52 @@ async fn cli_sync(args: &[String]) -> Result<()> {
     let conf = Conf::new()?;
     let pool = new_pool()?;
 
-    let ecb = Ecb::new(conf.providers.ecb, ExchangeRateRepository::new(&pool));
-    let iex = Iex::new(conf.providers.iex, ExchangeRateRepository::new(&pool));
+    let rate_repo = ExchangeRateRepository::new(&pool);
+    let rate_service = ExchangeRateService::new(&rate_repo, &conf.rate_cache);
+    let account_repo = AccountRepository::new(&pool);
+    let transaction_repo = TransactionRepository::new(&pool);
+    let snapshot_repo = PortfolioSnapshotRepository::new(&pool);
+    let snapshot_service = SnapshotService::new(
+        &snapshot_repo,
+        &account_repo,
+        &transaction_repo,
+        &ValuationService::new(&transaction_repo, &rate_service),
+        &conf.snapshots,
+    );
+    let recurring_service = RecurringService::new(
+        &RecurringTransactionRepository::new(&pool),
+        &RecurringExceptionRepository::new(&pool),
+        &TransactionService::new(
+            &transaction_repo,
+            &snapshot_repo,
+            &AssetService::new(&AssetRepository::new(&pool), &conf.assets),
+            &LotService::new(
+                &LotRepository::new(&pool),
+                &LotMatchRepository::new(&pool),
+                &account_repo,
+                &transaction_repo,
+            ),
+        ),
+        &conf.recurring,
+    );
+    let webhook_service = WebhookService::new(
+        &WebhookRepository::new(&pool),
+        &WebhookDeliveryRepository::new(&pool),
+        &conf.webhooks,
+    );
+    let alert_service = AlertService::new(
+        &AlertRepository::new(&pool),
+        &AlertEventRepository::new(&pool),
+        &rate_service,
+        &webhook_service,
+        vec![Arc::new(WebhookNotifier::new(&webhook_service))],
+    );
+    // Alerts go first so the webhook listener can dispatch fired alerts right away
+    let listeners: Vec<Arc<dyn SyncListener>> =
+        vec![Arc::new(alert_service), Arc::new(webhook_service.clone())];
+
+    let ecb = Ecb::new(conf.providers.ecb, rate_repo.clone(), listeners.clone());
+    let iex = Iex::new(conf.providers.iex, rate_repo, listeners);
 
     match args.len() {
         0 => {
@@ -127,9 +182,24 @@ async fn cli_sync(args: &[String]) -> Result<()> {
         }
         1 => match args.first().unwrap().as_str() {
             "schedule" => {
-                for res in join_all(vec![ecb.schedule(), iex.schedule()]).await {
+                let providers = join_all(vec![ecb.schedule(), iex.schedule()]);
+                let (results, res, snapshots_res, recurring_res) = join!(
+                    providers,
+                    webhook_service.schedule(),
+                    snapshot_service.schedule(),
+                    recurring_service.schedule()
+                );
+                for res in results {
                     res?;
                 }
+                res?;
+                snapshots_res?;
+                recurring_res?;
+            }
+            "snapshots" => snapshot_service.take_all(&Utc::today().naive_utc())?,
+            "recurring" => recurring_service.materialize_all(&Utc::today().naive_utc())?,
+            "history" => {
+                ecb.sync_history().await?;
             }
             _ => return Err(Error::msg("Unknown arguments")),
         },
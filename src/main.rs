@@ -2,6 +2,7 @@ mod conf;
 mod controller;
 mod db;
 mod model;
+mod notifier;
 mod provider;
 mod repository;
 mod service;
@@ -11,8 +12,22 @@ mod test;
 use crate::{
     conf::Conf,
     model::ApiError,
-    repository::{AuthTokenRepository, ExchangeRateRepository, UserRepository},
-    service::{AuthTokenService, ExchangeRateService, UserService},
+    notifier::WebhookNotifier,
+    repository::{
+        AccountRepository, AlertEventRepository, AlertRepository, AssetRepository,
+        AuthTokenRepository, BudgetRepository, CategoryRepository, CategoryRuleRepository,
+        ExchangeRateRepository, GoalRepository, LotMatchRepository, LotRepository,
+        PortfolioSnapshotRepository, RecurringExceptionRepository, RecurringTransactionRepository,
+        TargetRepository, TransactionRepository, UserRepository, WatchlistRepository,
+        WebhookDeliveryRepository, WebhookRepository,
+    },
+    service::{
+        AccountService, AlertService, AllocationService, AssetService, AuthTokenService,
+        BudgetService, CashflowService, CategoryService, ExchangeRateService, ExportService,
+        GainsService, GoalService, HoldingService, ImportService, LotService, PerformanceService,
+        RecurringService, SnapshotService, TargetService, TaxService, TransactionService,
+        TransferService, UserService, ValuationService, WatchlistService, WebhookService,
+    },
 };
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
@@ -21,6 +36,7 @@ use std::{
     env::{self, VarError},
     path::Path,
     process::exit,
+    sync::Arc,
 };
 use tracing::{error, warn};
 use tracing_subscriber::EnvFilter;
@@ -82,7 +98,123 @@ fn attach_payload(rocket: Rocket<Build>, conf: Conf) -> Rocket<Build> {
     let token_repo = AuthTokenRepository::new(&pool);
     let token_service = AuthTokenService::new(&token_repo);
     let rate_repo = ExchangeRateRepository::new(&pool);
-    let rate_service = ExchangeRateService::new(&rate_repo);
+    let rate_service = ExchangeRateService::new(&rate_repo, &conf.rate_cache);
+    let asset_repo = AssetRepository::new(&pool);
+    let asset_service = AssetService::new(&asset_repo, &conf.assets);
+    let account_repo = AccountRepository::new(&pool);
+    let transaction_repo = TransactionRepository::new(&pool);
+    let lot_repo = LotRepository::new(&pool);
+    let lot_match_repo = LotMatchRepository::new(&pool);
+    let lot_service = LotService::new(&lot_repo, &lot_match_repo, &account_repo, &transaction_repo);
+    let snapshot_repo = PortfolioSnapshotRepository::new(&pool);
+    let transaction_service = TransactionService::new(
+        &transaction_repo,
+        &snapshot_repo,
+        &asset_service,
+        &lot_service,
+    );
+    let recurring_repo = RecurringTransactionRepository::new(&pool);
+    let recurring_exception_repo = RecurringExceptionRepository::new(&pool);
+    let recurring_service = RecurringService::new(
+        &recurring_repo,
+        &recurring_exception_repo,
+        &transaction_service,
+        &conf.recurring,
+    );
+    let category_repo = CategoryRepository::new(&pool);
+    let category_rule_repo = CategoryRuleRepository::new(&pool);
+    let budget_repo = BudgetRepository::new(&pool);
+    let category_service = CategoryService::new(
+        &category_repo,
+        &category_rule_repo,
+        &transaction_repo,
+        &budget_repo,
+        &recurring_repo,
+    );
+    let import_service =
+        ImportService::new(&transaction_repo, &transaction_service, &category_service);
+    let target_repo = TargetRepository::new(&pool);
+    let transfer_service = TransferService::new(
+        &transaction_repo,
+        &account_repo,
+        &transaction_service,
+        &rate_service,
+    );
+    let export_service = ExportService::new(&account_repo, &transaction_repo, &rate_repo);
+    let holding_service = HoldingService::new(&transaction_repo);
+    let valuation_service = ValuationService::new(&transaction_repo, &rate_service);
+    let gains_service = GainsService::new(
+        &account_repo,
+        &transaction_repo,
+        &lot_match_repo,
+        &valuation_service,
+    );
+    let tax_service = TaxService::new(
+        &account_repo,
+        &transaction_repo,
+        &gains_service,
+        &valuation_service,
+    );
+    let performance_service = PerformanceService::new(&transaction_repo, &valuation_service);
+    let goal_repo = GoalRepository::new(&pool);
+    let goal_service = GoalService::new(
+        &goal_repo,
+        &account_repo,
+        &transaction_repo,
+        &asset_service,
+        &performance_service,
+    );
+    let cashflow_service = CashflowService::new(&transaction_repo, &category_repo, &rate_service);
+    let budget_service = BudgetService::new(
+        &budget_repo,
+        &category_repo,
+        &transaction_repo,
+        &asset_service,
+        &rate_service,
+    );
+    let allocation_service = AllocationService::new(
+        &account_repo,
+        &transaction_repo,
+        &asset_service,
+        &valuation_service,
+    );
+    let target_service = TargetService::new(
+        &target_repo,
+        &transaction_repo,
+        &asset_service,
+        &valuation_service,
+    );
+    let snapshot_service = SnapshotService::new(
+        &snapshot_repo,
+        &account_repo,
+        &transaction_repo,
+        &valuation_service,
+        &conf.snapshots,
+    );
+    let account_service = AccountService::new(
+        &account_repo,
+        &transaction_repo,
+        &snapshot_repo,
+        &asset_service,
+        &lot_service,
+        &recurring_service,
+        &goal_repo,
+    );
+    let watchlist_repo = WatchlistRepository::new(&pool);
+    let watchlist_service = WatchlistService::new(&watchlist_repo, &asset_service, &rate_service);
+    let webhook_repo = WebhookRepository::new(&pool);
+    let webhook_delivery_repo = WebhookDeliveryRepository::new(&pool);
+    let webhook_service =
+        WebhookService::new(&webhook_repo, &webhook_delivery_repo, &conf.webhooks);
+    let alert_repo = AlertRepository::new(&pool);
+    let alert_event_repo = AlertEventRepository::new(&pool);
+    let alert_service = AlertService::new(
+        &alert_repo,
+        &alert_event_repo,
+        &rate_service,
+        &webhook_service,
+        vec![Arc::new(WebhookNotifier::new(&webhook_service))],
+    );
 
     rocket
         .manage(pool)
@@ -90,16 +222,145 @@ fn attach_payload(rocket: Rocket<Build>, conf: Conf) -> Rocket<Build> {
         .manage(user_service)
         .manage(token_repo)
         .manage(token_service)
+        .manage(asset_repo)
+        .manage(asset_service)
+        .manage(account_repo)
+        .manage(account_service)
+        .manage(transaction_repo)
+        .manage(transaction_service)
+        .manage(lot_repo)
+        .manage(lot_match_repo)
+        .manage(lot_service)
+        .manage(category_repo)
+        .manage(category_rule_repo)
+        .manage(category_service)
+        .manage(budget_repo)
+        .manage(budget_service)
+        .manage(recurring_repo)
+        .manage(recurring_exception_repo)
+        .manage(recurring_service)
+        .manage(import_service)
+        .manage(transfer_service)
+        .manage(export_service)
+        .manage(holding_service)
+        .manage(gains_service)
+        .manage(tax_service)
+        .manage(valuation_service)
+        .manage(performance_service)
+        .manage(goal_repo)
+        .manage(goal_service)
+        .manage(allocation_service)
+        .manage(cashflow_service)
+        .manage(target_repo)
+        .manage(target_service)
+        .manage(snapshot_repo)
+        .manage(snapshot_service)
+        .manage(watchlist_repo)
+        .manage(watchlist_service)
         .manage(rate_repo)
         .manage(rate_service)
+        .manage(alert_repo)
+        .manage(alert_event_repo)
+        .manage(alert_service)
+        .manage(webhook_repo)
+        .manage(webhook_delivery_repo)
+        .manage(webhook_service)
         .attach(AdHoc::on_ignite("Run migrations", run_migrations))
         .register("/", catchers![default_catcher])
         .mount(
             "/",
             routes![
                 controller::exchange_rate::get,
+                controller::exchange_rate::get_cache,
                 controller::user::post,
-                controller::auth_token::post
+                controller::auth_token::post,
+                controller::asset::get,
+                controller::asset::get_by_code,
+                controller::asset::post,
+                controller::asset::put,
+                controller::asset::delete,
+                controller::account::get,
+                controller::account::get_by_id,
+                controller::account::post,
+                controller::account::put,
+                controller::account::delete,
+                controller::transaction::get,
+                controller::transaction::get_by_id,
+                controller::transaction::post,
+                controller::transaction::put,
+                controller::transaction::delete,
+                controller::category::get,
+                controller::category::get_by_id,
+                controller::category::post,
+                controller::category::put,
+                controller::category::delete,
+                controller::category::get_rules,
+                controller::category::post_rule,
+                controller::category::put_rule,
+                controller::category::delete_rule,
+                controller::budget::get,
+                controller::budget::get_by_id,
+                controller::budget::post,
+                controller::budget::put,
+                controller::budget::delete,
+                controller::budget::get_progress,
+                controller::budget::get_progress_by_id,
+                controller::recurring::get,
+                controller::recurring::get_by_id,
+                controller::recurring::post,
+                controller::recurring::put,
+                controller::recurring::delete,
+                controller::recurring::get_occurrences,
+                controller::recurring::put_occurrence,
+                controller::recurring::delete_occurrence,
+                controller::goal::get,
+                controller::goal::get_by_id,
+                controller::goal::post,
+                controller::goal::put,
+                controller::goal::delete,
+                controller::goal::get_progress,
+                controller::goal::get_progress_by_id,
+                controller::transfer::get,
+                controller::transfer::get_by_id,
+                controller::transfer::post,
+                controller::transfer::put,
+                controller::transfer::delete,
+                controller::holding::get,
+                controller::holding::get_by_account_id,
+                controller::lot::get,
+                controller::import::get_presets,
+                controller::import::post,
+                controller::export::get,
+                controller::portfolio::get_value,
+                controller::portfolio::get_history,
+                controller::portfolio::get_targets,
+                controller::portfolio::put_targets,
+                controller::portfolio::get_rebalance,
+                controller::report::get_gains,
+                controller::report::get_tax,
+                controller::report::get_performance,
+                controller::report::get_allocation,
+                controller::report::get_cashflow,
+                controller::report::get_account_performance,
+                controller::watchlist::get,
+                controller::watchlist::get_by_id,
+                controller::watchlist::post,
+                controller::watchlist::put,
+                controller::watchlist::delete,
+                controller::watchlist::get_quotes,
+                controller::watchlist::get_quotes_by_id,
+                controller::alert::get,
+                controller::alert::get_by_id,
+                controller::alert::post,
+                controller::alert::put,
+                controller::alert::delete,
+                controller::alert::get_events,
+                controller::webhook::get,
+                controller::webhook::get_by_id,
+                controller::webhook::post,
+                controller::webhook::put,
+                controller::webhook::delete,
+                controller::webhook::get_deliveries,
             ],
         )
 }
@@ -0,0 +1,122 @@
+use crate::model::Id;
+use chrono::NaiveDate;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Account {
+    pub id: Id,
+    pub username: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    /// Asset code which is used for cash balances and reports by default
+    pub currency: String,
+    pub opened_at: NaiveDate,
+    pub closed_at: Option<NaiveDate>,
+    pub cost_basis_method: CostBasisMethod,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    Brokerage,
+    Bank,
+    Wallet,
+    Cash,
+}
+
+/// Order in which sells consume the lots of an asset
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasisMethod {
+    #[default]
+    Fifo,
+    Lifo,
+    /// Every sell consumes all the open lots proportionally
+    Average,
+    /// Sells consume the lot they point to, then fall back to FIFO
+    Specific,
+}
+
+impl std::str::FromStr for AccountType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brokerage" => Ok(AccountType::Brokerage),
+            "bank" => Ok(AccountType::Bank),
+            "wallet" => Ok(AccountType::Wallet),
+            "cash" => Ok(AccountType::Cash),
+            _ => Err(format!("Unknown account type: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AccountType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountType::Brokerage => "brokerage",
+            AccountType::Bank => "bank",
+            AccountType::Wallet => "wallet",
+            AccountType::Cash => "cash",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for AccountType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for AccountType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+impl std::str::FromStr for CostBasisMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(CostBasisMethod::Fifo),
+            "lifo" => Ok(CostBasisMethod::Lifo),
+            "average" => Ok(CostBasisMethod::Average),
+            "specific" => Ok(CostBasisMethod::Specific),
+            _ => Err(format!("Unknown cost basis method: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for CostBasisMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CostBasisMethod::Fifo => "fifo",
+            CostBasisMethod::Lifo => "lifo",
+            CostBasisMethod::Average => "average",
+            CostBasisMethod::Specific => "specific",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for CostBasisMethod {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for CostBasisMethod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
@@ -0,0 +1,83 @@
+use crate::model::Id;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: Id,
+    pub username: String,
+    pub quote: String,
+    pub base: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    pub notifier: String,
+    pub target: String,
+    pub last_rate: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Rate goes above the threshold
+    Above,
+    /// Rate goes below the threshold
+    Below,
+    /// Rate moves by more than threshold percent compared to the previous day
+    Change,
+}
+
+impl Alert {
+    /// Checks the condition against a given rate. The reference rate is only used by
+    /// the `Change` condition and such alerts never trigger without it.
+    pub fn is_triggered(&self, rate: f64, reference: Option<f64>) -> bool {
+        match self.condition {
+            AlertCondition::Above => rate >= self.threshold,
+            AlertCondition::Below => rate <= self.threshold,
+            AlertCondition::Change => match reference {
+                Some(reference) if reference != 0.0 => {
+                    ((rate / reference - 1.0) * 100.0).abs() >= self.threshold
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for AlertCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "above" => Ok(AlertCondition::Above),
+            "below" => Ok(AlertCondition::Below),
+            "change" => Ok(AlertCondition::Change),
+            _ => Err(format!("Unknown alert condition: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertCondition::Above => "above",
+            AlertCondition::Below => "below",
+            AlertCondition::Change => "change",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for AlertCondition {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for AlertCondition {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
@@ -0,0 +1,13 @@
+use crate::model::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub id: Id,
+    pub alert_id: Id,
+    pub rate: f64,
+    pub fired_at: DateTime<Utc>,
+    /// Accepted by the notifier, which may deliver it later
+    pub delivered: bool,
+}
@@ -0,0 +1,53 @@
+use crate::model::ExchangeRate;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Composition of the portfolio, all the values are in the reporting currency
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub currency: String,
+    /// Latest rates are used when there is no date
+    pub date: Option<NaiveDate>,
+    pub by: AllocationBy,
+    /// Sum of all the holdings which have a known price
+    pub total: f64,
+    /// Largest groups come first
+    pub groups: Vec<AllocationGroup>,
+    /// Held assets which don't have a known price, they're left out
+    pub unpriced: Vec<String>,
+    pub rates: Vec<ExchangeRate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AllocationGroup {
+    /// Asset code, asset type, currency code or account ID
+    pub key: String,
+    pub name: String,
+    pub value: f64,
+    /// Share of the total between 0 and 1, negative for short positions and debts
+    pub weight: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationBy {
+    Asset,
+    AssetType,
+    /// Currency an asset is exposed to, see `AllocationService::exposure`
+    Currency,
+    Account,
+}
+
+impl std::str::FromStr for AllocationBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asset" => Ok(AllocationBy::Asset),
+            "asset_type" => Ok(AllocationBy::AssetType),
+            "currency" => Ok(AllocationBy::Currency),
+            "account" => Ok(AllocationBy::Account),
+            _ => Err(format!("Unknown grouping: {}", s)),
+        }
+    }
+}
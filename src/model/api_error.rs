@@ -36,10 +36,8 @@ impl ApiError {
 #[async_trait]
 impl<'r> Responder<'r, 'static> for ApiError {
     fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
-        let body = format!(
-            "{{\"code\": {}, \"message\": \"{}\"}}",
-            self.code, self.message
-        );
+        // Messages may contain user input, which has to be escaped
+        let body = serde_json::to_string(&self).map_err(|_| Status::InternalServerError)?;
 
         Response::build()
             .header(ContentType::JSON)
@@ -57,3 +55,25 @@ impl From<Status> for ApiError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::model::ApiError;
+    use rocket::{http::Status, local::asynchronous::Client, response::Responder};
+    use serde_json::{json, Value};
+
+    #[rocket::async_test]
+    async fn respond_to() {
+        let client = Client::untracked(rocket::build()).await.unwrap();
+        let req = client.get("/");
+        let mut res = ApiError::custom(400, "Invalid pattern: \"[\\")
+            .respond_to(req.inner())
+            .unwrap();
+        assert_eq!(Status::BadRequest, res.status());
+        let body = res.body_mut().to_string().await.unwrap();
+        assert_eq!(
+            json!({ "code": 400, "message": "Invalid pattern: \"[\\" }),
+            serde_json::from_str::<Value>(&body).unwrap()
+        );
+    }
+}
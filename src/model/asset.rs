@@ -0,0 +1,72 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Asset {
+    /// ISO 4217 code for currencies, ticker for everything else
+    pub code: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub asset_type: AssetType,
+    /// Number of decimal places, ISO 4217 minor units for currencies
+    pub precision: u8,
+    /// Free-form attributes such as ISIN or exchange
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetType {
+    Fiat,
+    Crypto,
+    Stock,
+    Etf,
+    Bond,
+    Commodity,
+}
+
+impl std::str::FromStr for AssetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fiat" => Ok(AssetType::Fiat),
+            "crypto" => Ok(AssetType::Crypto),
+            "stock" => Ok(AssetType::Stock),
+            "etf" => Ok(AssetType::Etf),
+            "bond" => Ok(AssetType::Bond),
+            "commodity" => Ok(AssetType::Commodity),
+            _ => Err(format!("Unknown asset type: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AssetType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetType::Fiat => "fiat",
+            AssetType::Crypto => "crypto",
+            AssetType::Stock => "stock",
+            AssetType::Etf => "etf",
+            AssetType::Bond => "bond",
+            AssetType::Commodity => "commodity",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for AssetType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for AssetType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
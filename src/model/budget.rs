@@ -0,0 +1,167 @@
+use crate::model::Id;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+/// Spending limit of a category and its subcategories, which renews every period
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: Id,
+    pub username: String,
+    pub category_id: Id,
+    pub period: BudgetPeriod,
+    /// Amount available every period, in `currency`
+    pub amount: f64,
+    pub currency: String,
+    pub rollover: Rollover,
+    /// First day of the first period
+    pub start: NaiveDate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Month,
+    Year,
+}
+
+/// What happens to the difference between the budget and the spending of a period
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rollover {
+    /// Every period starts from the budgeted amount
+    None,
+    /// Money left over is added to the next period
+    Unspent,
+    /// Money left over is added to the next period and overspending is taken from it
+    All,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BudgetProgress {
+    pub budget_id: Id,
+    pub currency: String,
+    /// Oldest periods come first
+    pub periods: Vec<BudgetPeriodProgress>,
+    /// Currencies which couldn't be converted, their transactions are left out
+    pub unconverted: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BudgetPeriodProgress {
+    /// Such as 2021-08 for months and 2021 for years
+    pub period: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Rolled over from the previous period, negative after overspending
+    pub carried: f64,
+    /// Budgeted amount plus the carried one
+    pub available: f64,
+    /// Expenses less refunds
+    pub spent: f64,
+    pub remaining: f64,
+    /// Share of the available amount which was spent, missing if nothing was available
+    pub used: Option<f64>,
+}
+
+impl BudgetPeriod {
+    /// First day of the period which contains a given day
+    pub fn start(&self, date: &NaiveDate) -> NaiveDate {
+        match self {
+            BudgetPeriod::Month => NaiveDate::from_ymd(date.year(), date.month(), 1),
+            BudgetPeriod::Year => NaiveDate::from_ymd(date.year(), 1, 1),
+        }
+    }
+
+    /// First day of the period after the one which starts on a given day
+    pub fn next(&self, start: &NaiveDate) -> NaiveDate {
+        match (self, start.month()) {
+            (BudgetPeriod::Month, 12) => NaiveDate::from_ymd(start.year() + 1, 1, 1),
+            (BudgetPeriod::Month, month) => NaiveDate::from_ymd(start.year(), month + 1, 1),
+            (BudgetPeriod::Year, _) => NaiveDate::from_ymd(start.year() + 1, 1, 1),
+        }
+    }
+
+    pub fn label(&self, start: &NaiveDate) -> String {
+        match self {
+            BudgetPeriod::Month => format!("{}-{:02}", start.year(), start.month()),
+            BudgetPeriod::Year => start.year().to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for BudgetPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "month" => Ok(BudgetPeriod::Month),
+            "year" => Ok(BudgetPeriod::Year),
+            _ => Err(format!("Unknown budget period: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for BudgetPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetPeriod::Month => "month",
+            BudgetPeriod::Year => "year",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for BudgetPeriod {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for BudgetPeriod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+impl std::str::FromStr for Rollover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Rollover::None),
+            "unspent" => Ok(Rollover::Unspent),
+            "all" => Ok(Rollover::All),
+            _ => Err(format!("Unknown rollover: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Rollover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rollover::None => "none",
+            Rollover::Unspent => "unspent",
+            Rollover::All => "all",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for Rollover {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for Rollover {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
@@ -0,0 +1,72 @@
+use crate::model::Id;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Income and expenses in the reporting currency, amounts are converted with the rates
+/// known on the day of each transaction
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cashflow {
+    pub currency: String,
+    pub group: CashflowGroup,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Only transactions with this tag are counted
+    pub tag: Option<String>,
+    /// Oldest periods come first, periods without any transactions are left out
+    pub periods: Vec<CashflowPeriod>,
+    pub income: f64,
+    pub expense: f64,
+    /// Currencies which couldn't be converted, their transactions are left out
+    pub unconverted: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CashflowPeriod {
+    /// Such as 2021-08 for months and 2021 for years
+    pub period: String,
+    pub income: f64,
+    /// Positive amount spent
+    pub expense: f64,
+    /// Income less expenses
+    pub net: f64,
+    /// Sorted by name, uncategorized transactions come last
+    pub categories: Vec<CashflowCategory>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CashflowCategory {
+    pub category_id: Option<Id>,
+    /// Full path of the category, see `Category::path`
+    pub name: String,
+    pub income: f64,
+    pub expense: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CashflowGroup {
+    Month,
+    Year,
+}
+
+impl CashflowGroup {
+    /// Period which contains a given day
+    pub fn period(&self, date: &NaiveDate) -> String {
+        match self {
+            CashflowGroup::Month => format!("{}-{:02}", date.year(), date.month()),
+            CashflowGroup::Year => date.year().to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for CashflowGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "month" => Ok(CashflowGroup::Month),
+            "year" => Ok(CashflowGroup::Year),
+            _ => Err(format!("Unknown grouping: {}", s)),
+        }
+    }
+}
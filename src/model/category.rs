@@ -0,0 +1,47 @@
+use crate::model::Id;
+use serde::{Deserialize, Serialize};
+
+/// Income or expense category, top-level ones don't have a parent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Category {
+    pub id: Id,
+    pub username: String,
+    pub name: String,
+    pub parent_id: Option<Id>,
+}
+
+/// Categorizes imported transactions whose description matches a pattern. Rules are
+/// tried in the order they were created and the first match wins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CategoryRule {
+    pub id: Id,
+    pub username: String,
+    /// Regular expression, matched case-insensitively anywhere in the description
+    pub pattern: String,
+    pub category_id: Id,
+    /// Added to the tags the transaction already has
+    pub tags: Vec<String>,
+}
+
+impl Category {
+    /// Names from the top-level category down to this one, such as Food / Groceries.
+    /// Unknown parents end the path.
+    pub fn path(&self, categories: &[Category]) -> String {
+        let mut names = vec![self.name.as_str()];
+        let mut parent_id = self.parent_id.as_ref();
+
+        while let Some(parent) = parent_id.and_then(|id| categories.iter().find(|it| &it.id == id))
+        {
+            // Cycles can't be stored, this only guards against a corrupted tree
+            if names.len() > categories.len() {
+                break;
+            }
+
+            names.push(parent.name.as_str());
+            parent_id = parent.parent_id.as_ref();
+        }
+
+        names.reverse();
+        names.join(" / ")
+    }
+}
@@ -0,0 +1,42 @@
+use crate::model::{Account, Transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Everything a user owns, along with the rates which apply to it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Export {
+    pub accounts: Vec<Account>,
+    pub transactions: Vec<Transaction>,
+    pub rates: Vec<HistoricalRate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoricalRate {
+    pub date: NaiveDate,
+    pub quote: String,
+    pub base: String,
+    pub rate: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// Zip archive with a file per table
+    Csv,
+    Json,
+    Beancount,
+    Ledger,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "beancount" => Ok(ExportFormat::Beancount),
+            "ledger" => Ok(ExportFormat::Ledger),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::model::{ExchangeRate, Id};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// All the amounts are in the reporting currency
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GainsReport {
+    pub currency: String,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub realized: Vec<RealizedGain>,
+    pub unrealized: Vec<UnrealizedGain>,
+    pub total_realized: f64,
+    pub total_unrealized: f64,
+    /// Lots which were left out because some of the rates are missing
+    pub unpriced_lots: Vec<Id>,
+    pub rates: Vec<ExchangeRate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub account_id: Id,
+    pub lot_id: Id,
+    pub sell_id: Id,
+    pub asset: String,
+    pub quantity: f64,
+    pub acquired_at: DateTime<Utc>,
+    pub sold_at: DateTime<Utc>,
+    pub cost: f64,
+    pub proceeds: f64,
+    /// Part of the gain caused by the asset price change
+    pub price_gain: f64,
+    /// Part of the gain caused by the rate change between the asset and reporting currencies
+    pub fx_gain: f64,
+    pub gain: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnrealizedGain {
+    pub account_id: Id,
+    pub lot_id: Id,
+    pub asset: String,
+    pub quantity: f64,
+    pub acquired_at: DateTime<Utc>,
+    pub cost: f64,
+    pub value: f64,
+    pub price_gain: f64,
+    pub fx_gain: f64,
+    pub gain: f64,
+}
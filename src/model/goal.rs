@@ -0,0 +1,40 @@
+use crate::model::Id;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Amount a user is saving up for in a set of accounts
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub id: Id,
+    pub username: String,
+    pub name: String,
+    pub target: f64,
+    pub currency: String,
+    pub deadline: Option<NaiveDate>,
+    /// Accounts whose value counts towards the target
+    pub account_ids: Vec<Id>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoalProgress {
+    pub goal_id: Id,
+    pub currency: String,
+    pub date: NaiveDate,
+    /// Value of the linked accounts at the end of `date`
+    pub value: f64,
+    pub target: f64,
+    /// Share of the target reached, it goes past 1 once the goal is met
+    pub progress: f64,
+    /// Net contributions per month over the last year, missing until there's a quarter of
+    /// history
+    pub monthly_contribution: Option<f64>,
+    /// Annualized money-weighted return over the last year, missing until there's a
+    /// quarter of history
+    pub annual_return: Option<f64>,
+    /// When the target is met at the current pace, missing if it's not met within a
+    /// century
+    pub projected_completion: Option<NaiveDate>,
+    /// Monthly contribution which meets the target by the deadline at the current return
+    pub required_contribution: Option<f64>,
+    pub on_track: Option<bool>,
+}
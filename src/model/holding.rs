@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Position in a single asset, cash balances use currency codes as assets
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Holding {
+    pub asset: String,
+    pub quantity: f64,
+}
@@ -1,3 +1,4 @@
+use rocket::request::FromParam;
 use rusqlite::types::FromSql;
 use rusqlite::types::FromSqlError;
 use rusqlite::types::FromSqlResult;
@@ -8,7 +9,7 @@ use rusqlite::ToSql;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Id(pub Uuid);
 
 impl Id {
@@ -57,3 +58,11 @@ impl From<Id> for uuid::Uuid {
         id.0
     }
 }
+
+impl<'a> FromParam<'a> for Id {
+    type Error = uuid::Error;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        param.parse()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::model::{Transaction, TransactionType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Describes where transaction fields are in a CSV file, columns are referenced by their
+/// headers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CsvMapping {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    pub time: String,
+    /// Chrono format of the time column, common ISO 8601 variants are tried if it's missing
+    pub time_format: Option<String>,
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    /// Values of the type column, matched case-insensitively
+    pub types: BTreeMap<String, TransactionType>,
+    pub asset: Option<String>,
+    /// Asset received by spin-offs and ticker changes
+    #[serde(default)]
+    pub new_asset: Option<String>,
+    /// Payee or memo, matched by category rules
+    #[serde(default)]
+    pub description: Option<String>,
+    pub quantity: String,
+    /// Cash amount of deposits, withdrawals, fees, interest and dividends, if the source
+    /// keeps it apart from the quantity
+    pub amount: Option<String>,
+    pub price: Option<String>,
+    /// Account currency is used if it's missing
+    pub currency: Option<String>,
+    pub fee: Option<String>,
+    /// Rows without an external ID are identified by a hash of their contents
+    pub external_id: Option<String>,
+    /// Numbers use a decimal comma, such as 1.234,56
+    #[serde(default)]
+    pub decimal_comma: bool,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub reconciled: usize,
+    pub errors: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportRow {
+    /// Line number in the source file
+    pub line: u64,
+    pub status: ImportStatus,
+    pub transaction: Option<Transaction>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// Imported, or would be imported on a dry run
+    Ok,
+    Duplicate,
+    /// Matched a transaction which wasn't imported, it takes the external ID of the row
+    Reconciled,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    #[default]
+    Csv,
+    /// QFX is OFX with a few Quicken specific additions
+    #[serde(alias = "qfx")]
+    Ofx,
+    Qif,
+}
@@ -0,0 +1,37 @@
+use crate::model::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Quantity of an asset acquired by a single transaction
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    /// Same as the id of the acquiring transaction
+    pub id: Id,
+    pub account_id: Id,
+    pub asset: String,
+    pub quantity: f64,
+    /// Quantity which hasn't been sold or transferred out yet
+    pub remaining: f64,
+    /// Total cost of the whole quantity including fees
+    pub cost: f64,
+    pub currency: String,
+    pub acquired_at: DateTime<Utc>,
+}
+
+/// Part of a sell which was matched against a lot
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LotMatch {
+    pub id: Id,
+    pub account_id: Id,
+    pub sell_id: Id,
+    pub lot_id: Id,
+    pub asset: String,
+    pub quantity: f64,
+    pub cost: f64,
+    pub cost_currency: String,
+    /// Share of the sell proceeds after fees
+    pub proceeds: f64,
+    pub proceeds_currency: String,
+    pub acquired_at: DateTime<Utc>,
+    pub sold_at: DateTime<Utc>,
+}
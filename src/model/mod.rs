@@ -10,3 +10,53 @@ mod auth_token;
 pub use auth_token::AuthToken;
 mod id;
 pub use id::Id;
+mod alert;
+pub use alert::{Alert, AlertCondition};
+mod alert_event;
+pub use alert_event::AlertEvent;
+mod webhook;
+pub use webhook::{Webhook, WebhookEvent};
+mod webhook_delivery;
+pub use webhook_delivery::{DeliveryStatus, WebhookDelivery};
+mod asset;
+pub use asset::{Asset, AssetType};
+mod account;
+pub use account::{Account, AccountType, CostBasisMethod};
+mod transaction;
+pub use transaction::{Transaction, TransactionType};
+mod holding;
+pub use holding::Holding;
+mod portfolio_value;
+pub use portfolio_value::{HoldingValue, PortfolioValue};
+mod lot;
+pub use lot::{Lot, LotMatch};
+mod gains_report;
+pub use gains_report::{GainsReport, RealizedGain, UnrealizedGain};
+mod performance;
+pub use performance::{Performance, Period};
+mod portfolio_snapshot;
+pub use portfolio_snapshot::PortfolioSnapshot;
+mod import;
+pub use import::{CsvMapping, ImportFormat, ImportReport, ImportRow, ImportStatus};
+mod export;
+pub use export::{Export, ExportFormat, HistoricalRate};
+mod transfer;
+pub use transfer::Transfer;
+mod target;
+pub use target::{Rebalance, RebalancePosition, RebalanceTrade, Target};
+mod allocation;
+pub use allocation::{Allocation, AllocationBy, AllocationGroup};
+mod category;
+pub use category::{Category, CategoryRule};
+mod cashflow;
+pub use cashflow::{Cashflow, CashflowCategory, CashflowGroup, CashflowPeriod};
+mod budget;
+pub use budget::{Budget, BudgetPeriod, BudgetPeriodProgress, BudgetProgress, Rollover};
+mod recurring;
+pub use recurring::{Occurrence, RecurringException, RecurringTransaction};
+mod goal;
+pub use goal::{Goal, GoalProgress};
+mod watchlist;
+pub use watchlist::{Quote, Watchlist, WatchlistQuotes};
+mod tax_report;
+pub use tax_report::{Disposal, DividendIncome, HoldingPeriod, TaxReport, TaxReportFormat};
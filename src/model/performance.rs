@@ -0,0 +1,50 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Performance {
+    pub currency: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Value at the end of the day before `from`
+    pub start_value: f64,
+    pub end_value: f64,
+    /// Deposits and incoming transfers minus withdrawals and outgoing transfers
+    pub net_flows: f64,
+    /// Time-weighted return, not annualized
+    pub twr: Option<f64>,
+    /// Money-weighted return, annualized
+    pub xirr: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    YearToDate,
+    OneYear,
+    SinceInception,
+}
+
+impl Period {
+    /// First day of the period which ends on a given day. Inception depends on the ledger
+    /// so it's left to the caller.
+    pub fn start(&self, end: &NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Period::YearToDate => Some(NaiveDate::from_ymd(end.year(), 1, 1)),
+            Period::OneYear => Some(*end - chrono::Duration::days(365) + chrono::Duration::days(1)),
+            Period::SinceInception => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ytd" => Ok(Period::YearToDate),
+            "1y" => Ok(Period::OneYear),
+            "inception" => Ok(Period::SinceInception),
+            _ => Err(format!("Unknown period: {}", s)),
+        }
+    }
+}
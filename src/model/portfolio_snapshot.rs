@@ -0,0 +1,10 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub username: String,
+    pub currency: String,
+    pub date: NaiveDate,
+    pub value: f64,
+}
@@ -0,0 +1,24 @@
+use crate::model::ExchangeRate;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PortfolioValue {
+    pub currency: String,
+    /// Latest rates are used when there is no date
+    pub date: Option<NaiveDate>,
+    /// Sum of all the holdings which have a known price
+    pub total: f64,
+    pub holdings: Vec<HoldingValue>,
+    /// Every rate and trade price the values are based on
+    pub rates: Vec<ExchangeRate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HoldingValue {
+    pub asset: String,
+    pub quantity: f64,
+    /// Unit price in the reporting currency, missing if the asset has no known price
+    pub price: Option<f64>,
+    pub value: Option<f64>,
+}
@@ -0,0 +1,329 @@
+use crate::model::{Id, Transaction, TransactionType};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Template of a transaction which repeats on a schedule, such as a salary, a rent or a
+/// savings plan. Due occurrences are turned into transactions by a background job.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecurringTransaction {
+    pub id: Id,
+    pub username: String,
+    pub account_id: Id,
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    pub asset: Option<String>,
+    pub quantity: f64,
+    pub price: Option<f64>,
+    pub currency: String,
+    pub fee: f64,
+    pub description: Option<String>,
+    pub category_id: Option<Id>,
+    pub tags: Vec<String>,
+    /// Cron expression with seconds, such as 0 0 12 1 * * *, or an RRULE such as
+    /// FREQ=MONTHLY;BYMONTHDAY=-1, see `Recurrence`
+    pub schedule: String,
+    /// Nothing occurs before it, RRULE occurrences also take its time of day
+    pub start: DateTime<Utc>,
+    /// Last day which can have an occurrence
+    pub end: Option<NaiveDate>,
+    /// Last day whose occurrences were turned into transactions
+    pub materialized_until: Option<NaiveDate>,
+}
+
+/// Skips or changes a single occurrence
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecurringException {
+    pub recurring_id: Id,
+    /// Scheduled day of the occurrence
+    pub date: NaiveDate,
+    pub skip: bool,
+    /// Replaces the quantity of the template
+    pub quantity: Option<f64>,
+    /// Replaces the price of the template
+    pub price: Option<f64>,
+    /// Moves the occurrence to another time
+    pub time: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Occurrence {
+    /// Scheduled day, exceptions refer to it
+    pub date: NaiveDate,
+    /// Same as the scheduled time unless the occurrence was moved
+    pub time: DateTime<Utc>,
+    pub quantity: f64,
+    pub price: Option<f64>,
+    pub skipped: bool,
+    /// Already turned into a transaction
+    pub materialized: bool,
+}
+
+impl RecurringTransaction {
+    pub fn recurrence(&self) -> Result<Recurrence, String> {
+        self.schedule.parse()
+    }
+
+    /// Transaction of a single occurrence, an exception can change its quantity and price
+    pub fn transaction(
+        &self,
+        time: DateTime<Utc>,
+        exception: Option<&RecurringException>,
+    ) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: self.account_id.clone(),
+            transaction_type: self.transaction_type,
+            asset: self.asset.clone(),
+            quantity: exception
+                .and_then(|it| it.quantity)
+                .unwrap_or(self.quantity),
+            price: exception.and_then(|it| it.price).or(self.price),
+            currency: self.currency.clone(),
+            fee: self.fee,
+            time,
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: self.description.clone(),
+            category_id: self.category_id.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// Parsed schedule, occurrences can't be closer than a day apart
+#[derive(Clone, Debug)]
+pub enum Recurrence {
+    Cron(cron::Schedule),
+    Rule(RecurrenceRule),
+}
+
+/// Supported subset of RFC 5545 recurrence rules
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// Weekly rules only, defaults to the weekday of the start
+    pub weekdays: Vec<Weekday>,
+    /// Monthly rules only, negative days count from the end of the month. Defaults to
+    /// the day of the start, months which don't have the day are skipped.
+    pub month_days: Vec<i32>,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    /// Occurrences between the start of a schedule and a given time, both included
+    pub fn occurrences(&self, start: &DateTime<Utc>, until: &DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        match self {
+            Recurrence::Cron(schedule) => schedule
+                .after(&(*start - Duration::seconds(1)))
+                .take_while(|it| it <= until)
+                .collect(),
+            Recurrence::Rule(rule) => rule.occurrences(start, until),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.starts_with("RRULE:") || s.starts_with("FREQ=") {
+            return s.parse().map(Recurrence::Rule);
+        }
+
+        let schedule =
+            cron::Schedule::from_str(s).map_err(|e| format!("Invalid cron expression: {}", e))?;
+        let upcoming: Vec<DateTime<Utc>> = schedule.upcoming(Utc).take(10).collect();
+
+        if upcoming
+            .windows(2)
+            .any(|it| it[1] - it[0] < Duration::hours(23))
+        {
+            return Err("Schedule can't repeat more than once a day".into());
+        }
+
+        Ok(Recurrence::Cron(schedule))
+    }
+}
+
+impl RecurrenceRule {
+    fn occurrences(&self, start: &DateTime<Utc>, until: &DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let first = start.date().naive_utc();
+        let mut last = until.date().naive_utc();
+
+        if let Some(rule_until) = self.until {
+            last = last.min(rule_until);
+        }
+
+        let mut occurrences = vec![];
+        let mut count = 0;
+        let mut date = first;
+
+        while date <= last {
+            if self.matches(&first, &date) {
+                count += 1;
+
+                if self.count.map(|it| count > it).unwrap_or(false) {
+                    break;
+                }
+
+                let time = Utc.from_utc_datetime(&date.and_time(start.time()));
+
+                if &time >= start && &time <= until {
+                    occurrences.push(time);
+                }
+            }
+
+            date = date.succ();
+        }
+
+        occurrences
+    }
+
+    fn matches(&self, first: &NaiveDate, date: &NaiveDate) -> bool {
+        let interval = self.interval as i64;
+        let months =
+            (date.year() - first.year()) as i64 * 12 + date.month() as i64 - first.month() as i64;
+
+        match self.frequency {
+            Frequency::Daily => (*date - *first).num_days() % interval == 0,
+            Frequency::Weekly => {
+                let week_start = |it: &NaiveDate| {
+                    *it - Duration::days(it.weekday().num_days_from_monday() as i64)
+                };
+                let weeks = (week_start(date) - week_start(first)).num_weeks();
+                let weekdays = match self.weekdays.is_empty() {
+                    true => vec![first.weekday()],
+                    false => self.weekdays.clone(),
+                };
+
+                weeks % interval == 0 && weekdays.contains(&date.weekday())
+            }
+            Frequency::Monthly => {
+                let month_days = match self.month_days.is_empty() {
+                    true => vec![first.day() as i32],
+                    false => self.month_days.clone(),
+                };
+                let days_in_month = Self::days_in_month(date);
+                let day = date.day() as i32;
+
+                months % interval == 0
+                    && month_days
+                        .iter()
+                        .any(|it| *it == day || (*it < 0 && days_in_month as i32 + 1 + *it == day))
+            }
+            Frequency::Yearly => months % (12 * interval) == 0 && date.day() == first.day(),
+        }
+    }
+
+    fn days_in_month(date: &NaiveDate) -> u32 {
+        let next = match date.month() {
+            12 => NaiveDate::from_ymd(date.year() + 1, 1, 1),
+            month => NaiveDate::from_ymd(date.year(), month + 1, 1),
+        };
+
+        next.pred().day()
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            weekdays: vec![],
+            month_days: vec![],
+            count: None,
+            until: None,
+        };
+        let mut frequency = None;
+
+        for part in s.trim_start_matches("RRULE:").split(';') {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid rule part: {}", part))?;
+            let invalid = || format!("Invalid {}: {}", key, value);
+
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported FREQ: {}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse().map_err(|_| invalid())?;
+
+                    if rule.interval == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "BYDAY" => {
+                    rule.weekdays = value
+                        .split(',')
+                        .map(|it| match it {
+                            "MO" => Ok(Weekday::Mon),
+                            "TU" => Ok(Weekday::Tue),
+                            "WE" => Ok(Weekday::Wed),
+                            "TH" => Ok(Weekday::Thu),
+                            "FR" => Ok(Weekday::Fri),
+                            "SA" => Ok(Weekday::Sat),
+                            "SU" => Ok(Weekday::Sun),
+                            _ => Err(format!("Unsupported BYDAY: {}", it)),
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.month_days = value
+                        .split(',')
+                        .map(|it| {
+                            it.parse::<i32>()
+                                .ok()
+                                .filter(|it| *it != 0 && it.abs() <= 31)
+                                .ok_or_else(invalid)
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => {
+                    rule.until = Some(
+                        NaiveDate::parse_from_str(value.get(..8).unwrap_or(value), "%Y%m%d")
+                            .map_err(|_| invalid())?,
+                    )
+                }
+                _ => return Err(format!("Unsupported rule part: {}", key)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| "Rule needs a FREQ".to_string())?;
+
+        if !rule.weekdays.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported by weekly rules".into());
+        }
+
+        if !rule.month_days.is_empty() && rule.frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported by monthly rules".into());
+        }
+
+        Ok(rule)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::model::AssetType;
+use serde::{Deserialize, Serialize};
+
+/// Desired share of the portfolio value held in an asset, or in all the assets of a type
+/// which don't have a target of their own
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Target {
+    pub username: String,
+    /// Either this or `asset_type` is set
+    pub asset: Option<String>,
+    #[serde(rename = "type")]
+    pub asset_type: Option<AssetType>,
+    /// Share of the total value between 0 and 1, the weights of a user add up to 1
+    pub weight: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rebalance {
+    pub currency: String,
+    /// Value of the portfolio plus the cash to invest
+    pub total: f64,
+    /// Cash to invest, including the currencies held without a target
+    pub cash: f64,
+    /// Largest difference between the current and the target weight which doesn't call
+    /// for any trades
+    pub tolerance: f64,
+    pub positions: Vec<RebalancePosition>,
+    /// Held assets which don't have a known price, they're left out
+    pub unpriced: Vec<String>,
+}
+
+/// Part of the portfolio covered by a target. Held assets without a target form their own
+/// positions with a zero target weight.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RebalancePosition {
+    pub asset: Option<String>,
+    #[serde(rename = "type")]
+    pub asset_type: Option<AssetType>,
+    pub value: f64,
+    pub weight: f64,
+    pub target_value: f64,
+    pub target_weight: f64,
+    /// Value to buy, or to sell when negative, zero within the tolerance band
+    pub amount: f64,
+    /// Type targets spread the amount over the held assets in proportion to their values
+    pub trades: Vec<RebalanceTrade>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RebalanceTrade {
+    pub asset: String,
+    /// Units to buy, or to sell when negative, rounded to the precision of the asset
+    pub quantity: f64,
+    pub price: f64,
+    pub amount: f64,
+}
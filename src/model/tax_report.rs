@@ -0,0 +1,122 @@
+use crate::model::{CostBasisMethod, ExchangeRate, Id};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Taxable events of a calendar year, all the amounts are in the reporting currency at
+/// the rates of the days the events happened
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaxReport {
+    pub currency: String,
+    pub year: i32,
+    /// Missing when each account uses its own method
+    pub method: Option<CostBasisMethod>,
+    pub disposals: Vec<Disposal>,
+    pub dividends: Vec<DividendIncome>,
+    pub total_proceeds: f64,
+    pub total_cost: f64,
+    pub short_term_gain: f64,
+    pub long_term_gain: f64,
+    pub total_gain: f64,
+    pub total_dividends: f64,
+    /// Tax withheld from dividends
+    pub total_withheld: f64,
+    /// Taxes which aren't tied to an asset, so they can't count as withholding
+    pub unattributed_taxes: f64,
+    /// Sells and income transactions which were left out because some of the rates are
+    /// missing
+    pub unpriced: Vec<Id>,
+    pub rates: Vec<ExchangeRate>,
+}
+
+/// Part of a sell matched against a single lot
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Disposal {
+    pub account_id: Id,
+    pub sell_id: Id,
+    pub lot_id: Id,
+    pub asset: String,
+    pub quantity: f64,
+    pub acquired_at: DateTime<Utc>,
+    pub sold_at: DateTime<Utc>,
+    pub proceeds: f64,
+    pub cost: f64,
+    pub gain: f64,
+    pub holding_period: HoldingPeriod,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DividendIncome {
+    pub asset: String,
+    /// Cash and reinvested dividends before withholding
+    pub gross: f64,
+    /// Tax withheld at source
+    pub withheld: f64,
+    pub net: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldingPeriod {
+    Short,
+    /// Held for more than a year
+    Long,
+}
+
+impl HoldingPeriod {
+    pub fn of(acquired_at: &DateTime<Utc>, sold_at: &DateTime<Utc>) -> HoldingPeriod {
+        let acquired = acquired_at.date().naive_utc();
+        // Assets bought on February 29 reach a year on March 1
+        let anniversary = acquired
+            .with_year(acquired.year() + 1)
+            .unwrap_or_else(|| NaiveDate::from_ymd(acquired.year() + 1, 3, 1));
+
+        match sold_at.date().naive_utc() > anniversary {
+            true => HoldingPeriod::Long,
+            false => HoldingPeriod::Short,
+        }
+    }
+}
+
+impl std::fmt::Display for HoldingPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HoldingPeriod::Short => "short",
+            HoldingPeriod::Long => "long",
+        }
+        .fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaxReportFormat {
+    Json,
+    /// Zip archive with the disposals and the dividends as CSV, `csv` is accepted too
+    Zip,
+}
+
+impl std::str::FromStr for TaxReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(TaxReportFormat::Json),
+            "zip" | "csv" => Ok(TaxReportFormat::Zip),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::HoldingPeriod;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn holding_period() {
+        let bought = Utc.ymd(2020, 2, 29).and_hms(12, 0, 0);
+        let period = |y, m, d| HoldingPeriod::of(&bought, &Utc.ymd(y, m, d).and_hms(12, 0, 0));
+        assert_eq!(HoldingPeriod::Short, period(2021, 2, 28));
+        assert_eq!(HoldingPeriod::Short, period(2021, 3, 1));
+        assert_eq!(HoldingPeriod::Long, period(2021, 3, 2));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::model::Id;
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: Id,
+    pub account_id: Id,
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    /// Traded asset, cash movements in `currency` don't have it
+    pub asset: Option<String>,
+    /// Number of units for trades, reinvested dividends and transfers of an asset, cash
+    /// amount otherwise. Transfers are negative when they leave the account. Corporate
+    /// actions use it for the number of new units received per unit of `asset`.
+    pub quantity: f64,
+    /// Unit price in `currency` of trades and reinvested dividends. Spin-offs use it for
+    /// the share of the cost basis of `asset` which moves to `new_asset`.
+    pub price: Option<f64>,
+    pub currency: String,
+    /// Fee in `currency` which is paid on top of the transaction
+    pub fee: f64,
+    pub time: DateTime<Utc>,
+    /// Lot which a sell should consume first when the account uses specific identification
+    pub lot_id: Option<Id>,
+    /// Identifier given by the source of an import, used to skip duplicates
+    pub external_id: Option<String>,
+    /// Asset received by ticker changes in place of `asset`, or by spin-offs on top of it
+    pub new_asset: Option<String>,
+    /// Shared by both legs of a transfer between two accounts of the same user
+    pub transfer_id: Option<Id>,
+    /// Payee or memo given by the source of an import, matched by category rules
+    pub description: Option<String>,
+    pub category_id: Option<Id>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionType {
+    Buy,
+    Sell,
+    Deposit,
+    Withdrawal,
+    Fee,
+    Interest,
+    Dividend,
+    /// Dividend paid out in units of `asset` instead of cash
+    DividendReinvestment,
+    /// Tax withheld at source, such as on the dividends of `asset`
+    Tax,
+    Transfer,
+    /// Covers reverse splits too, they receive less than one unit per unit
+    Split,
+    SpinOff,
+    /// Also covers mergers which swap the shares for those of another company
+    TickerChange,
+}
+
+impl Transaction {
+    /// Change of the cash balance in `currency`
+    pub fn cash_amount(&self) -> f64 {
+        let amount = match self.transaction_type {
+            TransactionType::Buy => -self.quantity * self.price.unwrap_or(0.0),
+            TransactionType::Sell => self.quantity * self.price.unwrap_or(0.0),
+            TransactionType::Deposit | TransactionType::Interest | TransactionType::Dividend => {
+                self.quantity
+            }
+            TransactionType::Withdrawal | TransactionType::Fee | TransactionType::Tax => {
+                -self.quantity
+            }
+            TransactionType::Transfer => match self.asset {
+                Some(_) => 0.0,
+                None => self.quantity,
+            },
+            TransactionType::DividendReinvestment
+            | TransactionType::Split
+            | TransactionType::SpinOff
+            | TransactionType::TickerChange => 0.0,
+        };
+
+        amount - self.fee
+    }
+
+    /// Change of the `asset` balance, if any. Corporate actions depend on the balance they
+    /// apply to, see `is_corporate_action`.
+    pub fn asset_quantity(&self) -> f64 {
+        match (self.transaction_type, &self.asset) {
+            (TransactionType::Buy, Some(_)) => self.quantity,
+            (TransactionType::DividendReinvestment, Some(_)) => self.quantity,
+            (TransactionType::Sell, Some(_)) => -self.quantity,
+            (TransactionType::Transfer, Some(_)) => self.quantity,
+            _ => 0.0,
+        }
+    }
+
+    /// Splits, spin-offs and ticker changes, which scale or convert the whole balance of
+    /// `asset` held in the account at the time
+    pub fn is_corporate_action(&self) -> bool {
+        matches!(
+            self.transaction_type,
+            TransactionType::Split | TransactionType::SpinOff | TransactionType::TickerChange
+        )
+    }
+}
+
+impl std::str::FromStr for TransactionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buy" => Ok(TransactionType::Buy),
+            "sell" => Ok(TransactionType::Sell),
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdrawal" => Ok(TransactionType::Withdrawal),
+            "fee" => Ok(TransactionType::Fee),
+            "interest" => Ok(TransactionType::Interest),
+            "dividend" => Ok(TransactionType::Dividend),
+            "dividend_reinvestment" => Ok(TransactionType::DividendReinvestment),
+            "tax" => Ok(TransactionType::Tax),
+            "transfer" => Ok(TransactionType::Transfer),
+            "split" => Ok(TransactionType::Split),
+            "spin_off" => Ok(TransactionType::SpinOff),
+            "ticker_change" => Ok(TransactionType::TickerChange),
+            _ => Err(format!("Unknown transaction type: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for TransactionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionType::Buy => "buy",
+            TransactionType::Sell => "sell",
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Fee => "fee",
+            TransactionType::Interest => "interest",
+            TransactionType::Dividend => "dividend",
+            TransactionType::DividendReinvestment => "dividend_reinvestment",
+            TransactionType::Tax => "tax",
+            TransactionType::Transfer => "transfer",
+            TransactionType::Split => "split",
+            TransactionType::SpinOff => "spin_off",
+            TransactionType::TickerChange => "ticker_change",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for TransactionType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for TransactionType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::model::{Id, Transaction, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Movement of cash or an asset between two accounts of the same user. It's stored as a
+/// pair of transfer transactions which share the transfer ID.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: Id,
+    pub from_account_id: Id,
+    pub to_account_id: Id,
+    /// Transferred asset, cash transfers don't have it
+    pub asset: Option<String>,
+    /// Number of units, or cash amount in `from_currency`, which leaves the source account
+    pub quantity: f64,
+    pub from_currency: String,
+    /// Same as `from_currency` unless cash is converted on the way
+    pub to_currency: String,
+    /// Units of `to_currency` received per unit of `from_currency`
+    pub rate: f64,
+    /// Fee in `from_currency` which the source account pays on top of the transfer
+    pub fee: f64,
+    pub time: DateTime<Utc>,
+    pub from_transaction_id: Id,
+    pub to_transaction_id: Id,
+}
+
+impl Transfer {
+    /// Builds a transfer from its outgoing and incoming legs
+    pub fn from_legs(from: &Transaction, to: &Transaction) -> Option<Transfer> {
+        let (transfer_id, rate) = match (&from.transfer_id, &from.asset) {
+            (Some(transfer_id), Some(_)) => (transfer_id, 1.0),
+            (Some(transfer_id), None) if from.quantity != 0.0 => {
+                (transfer_id, -to.quantity / from.quantity)
+            }
+            _ => return None,
+        };
+
+        Some(Transfer {
+            id: transfer_id.clone(),
+            from_account_id: from.account_id.clone(),
+            to_account_id: to.account_id.clone(),
+            asset: from.asset.clone(),
+            quantity: -from.quantity,
+            from_currency: from.currency.clone(),
+            to_currency: to.currency.clone(),
+            rate,
+            fee: from.fee,
+            time: from.time,
+            from_transaction_id: from.id.clone(),
+            to_transaction_id: to.id.clone(),
+        })
+    }
+
+    /// Outgoing and incoming transactions
+    pub fn legs(&self) -> (Transaction, Transaction) {
+        let from = Transaction {
+            id: self.from_transaction_id.clone(),
+            account_id: self.from_account_id.clone(),
+            transaction_type: TransactionType::Transfer,
+            asset: self.asset.clone(),
+            quantity: -self.quantity,
+            price: None,
+            currency: self.from_currency.clone(),
+            fee: self.fee,
+            time: self.time,
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: Some(self.id.clone()),
+            description: None,
+            category_id: None,
+            tags: vec![],
+        };
+
+        let to = Transaction {
+            id: self.to_transaction_id.clone(),
+            account_id: self.to_account_id.clone(),
+            quantity: match self.asset {
+                Some(_) => self.quantity,
+                None => self.quantity * self.rate,
+            },
+            currency: self.to_currency.clone(),
+            fee: 0.0,
+            ..from.clone()
+        };
+
+        (from, to)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::model::Id;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub id: Id,
+    pub username: String,
+    pub name: String,
+    /// Asset codes in the order the user put them
+    pub assets: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WatchlistQuotes {
+    pub watchlist_id: Id,
+    pub name: String,
+    pub currency: String,
+    /// Day the daily change is measured against
+    pub previous_date: NaiveDate,
+    pub quotes: Vec<Quote>,
+}
+
+/// Latest price of an asset in the reporting currency, missing when there are no rates
+/// to convert it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    pub asset: String,
+    pub name: Option<String>,
+    pub price: Option<f64>,
+    /// Price at the end of the previous day
+    pub previous_price: Option<f64>,
+    pub change: Option<f64>,
+    /// Change as a share of the previous price, 0.01 is 1%
+    pub change_percent: Option<f64>,
+}
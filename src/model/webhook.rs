@@ -0,0 +1,60 @@
+use crate::model::Id;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Id,
+    pub username: String,
+    pub url: String,
+    /// Used to sign payloads, receivers should use it to verify the signature header
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    RateUpdated,
+    SyncFailed,
+    AlertFired,
+}
+
+impl std::str::FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rate_updated" => Ok(WebhookEvent::RateUpdated),
+            "sync_failed" => Ok(WebhookEvent::SyncFailed),
+            "alert_fired" => Ok(WebhookEvent::AlertFired),
+            _ => Err(format!("Unknown webhook event: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookEvent::RateUpdated => "rate_updated",
+            WebhookEvent::SyncFailed => "sync_failed",
+            WebhookEvent::AlertFired => "alert_fired",
+        }
+        .fmt(f)
+    }
+}
+
+impl ToSql for WebhookEvent {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_string().into()))
+    }
+}
+
+impl FromSql for WebhookEvent {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
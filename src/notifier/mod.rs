@@ -0,0 +1,15 @@
+use crate::model::{Alert, AlertEvent};
+use anyhow::Result;
+
+mod webhook;
+pub use webhook::WebhookNotifier;
+
+#[rocket::async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Validates the notifier specific target of an alert, such as a webhook or an email
+    fn validate_target(&self, alert: &Alert) -> Result<()>;
+
+    async fn notify(&self, alert: &Alert, event: &AlertEvent) -> Result<()>;
+}
@@ -0,0 +1,59 @@
+use crate::{
+    model::{Alert, AlertEvent, Id, Webhook, WebhookEvent},
+    notifier::Notifier,
+    service::WebhookService,
+};
+use anyhow::{Error, Result};
+use serde_json::json;
+
+/// Queues alerts for one of the user's webhooks, whose ID is the target. Deliveries are
+/// signed and retried by the webhook queue.
+pub struct WebhookNotifier {
+    webhook_service: WebhookService,
+}
+
+impl WebhookNotifier {
+    pub fn new(webhook_service: &WebhookService) -> Self {
+        Self {
+            webhook_service: webhook_service.clone(),
+        }
+    }
+
+    fn webhook(&self, alert: &Alert) -> Result<Webhook> {
+        alert
+            .target
+            .parse::<Id>()
+            .ok()
+            .map(|id| self.webhook_service.select_by_id(&id))
+            .transpose()?
+            .flatten()
+            .filter(|it| it.username == alert.username)
+            .ok_or_else(|| Error::msg(format!("Unknown webhook: {}", alert.target)))
+    }
+}
+
+#[rocket::async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> String {
+        "webhook".into()
+    }
+
+    fn validate_target(&self, alert: &Alert) -> Result<()> {
+        self.webhook(alert).map(|_| ())
+    }
+
+    async fn notify(&self, alert: &Alert, event: &AlertEvent) -> Result<()> {
+        let webhook = self.webhook(alert)?;
+
+        // Subscribed webhooks already get every fired alert
+        if webhook.events.contains(&WebhookEvent::AlertFired) {
+            return Ok(());
+        }
+
+        self.webhook_service.enqueue_to(
+            &webhook,
+            WebhookEvent::AlertFired,
+            &json!({ "alert": alert, "event": event }),
+        )
+    }
+}
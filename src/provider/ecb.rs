@@ -1,12 +1,22 @@
-use crate::{model::ExchangeRate, provider::Provider, repository::ExchangeRateRepository};
-use anyhow::Result;
+use crate::{
+    model::{ExchangeRate, HistoricalRate},
+    provider::{Provider, SyncListener},
+    repository::ExchangeRateRepository,
+};
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
 use serde::Deserialize;
-use std::io::{copy, Cursor};
+use std::{
+    io::{copy, Cursor},
+    sync::Arc,
+};
+use tracing::{info, warn};
 use zip::ZipArchive;
 
 pub struct Ecb {
     conf: EcbConf,
     repo: ExchangeRateRepository,
+    listeners: Vec<Arc<dyn SyncListener>>,
 }
 
 #[derive(Deserialize)]
@@ -16,12 +26,73 @@ pub struct EcbConf {
 }
 
 impl Ecb {
-    pub fn new(conf: EcbConf, repo: ExchangeRateRepository) -> Self {
+    pub fn new(
+        conf: EcbConf,
+        repo: ExchangeRateRepository,
+        listeners: Vec<Arc<dyn SyncListener>>,
+    ) -> Self {
         Self {
             conf: conf,
             repo: repo,
+            listeners,
         }
     }
+
+    /// Loads all the reference rates published since 1999, so older transactions can be
+    /// valued too. Returns the number of stored rates.
+    pub async fn sync_history(&self) -> Result<usize> {
+        let url = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-hist.zip";
+        let csv = Self::download_csv(url).await?;
+        let rates = Self::parse_history(&csv)?;
+        self.repo.insert_or_replace_history_all(&rates)?;
+        info!(rates = rates.len(), "Synced rate history");
+        Ok(rates.len())
+    }
+
+    async fn download_csv(url: &str) -> Result<String> {
+        let res = reqwest::get(url).await?;
+        let body = Cursor::new(res.bytes().await?);
+        let mut archive = ZipArchive::new(body)?;
+        let mut compressed_csv = archive.by_index(0)?;
+        let mut csv: Vec<u8> = vec![];
+        copy(&mut compressed_csv, &mut csv)?;
+        Ok(String::from_utf8(csv)?)
+    }
+
+    /// Rows are dates, columns are currencies. Currencies which weren't quoted on a date
+    /// are marked as N/A.
+    fn parse_history(csv: &str) -> Result<Vec<HistoricalRate>> {
+        let mut lines = csv.lines();
+        let headers: Vec<&str> = lines
+            .next()
+            .context("Empty rate history")?
+            .split(',')
+            .map(str::trim)
+            .collect();
+        let codes = &headers[1..];
+        let mut rates = vec![];
+
+        for line in lines.filter(|it| !it.trim().is_empty()) {
+            let mut values = line.split(',').map(str::trim);
+            let date = values.next().unwrap_or_default();
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .with_context(|| format!("Invalid date: {}", date))?;
+
+            for (code, value) in codes.iter().zip(values) {
+                match value.parse::<f64>() {
+                    Ok(value) if !code.is_empty() && value > 0.0 => rates.push(HistoricalRate {
+                        date,
+                        quote: code.to_string(),
+                        base: "EUR".to_string(),
+                        rate: 1.0 / value,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(rates)
+    }
 }
 
 #[rocket::async_trait]
@@ -38,15 +109,9 @@ impl Provider for Ecb {
         self.conf.fiat_schedule.clone()
     }
 
-    async fn sync_fiat(&self) -> Result<()> {
+    async fn sync_fiat(&self) -> Result<Vec<ExchangeRate>> {
         let url = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref.zip";
-        let res = reqwest::get(url).await?;
-        let body = Cursor::new(res.bytes().await?);
-        let mut archive = ZipArchive::new(body)?;
-        let mut compressed_csv = archive.by_index(0)?;
-        let mut csv: Vec<u8> = vec![];
-        copy(&mut compressed_csv, &mut csv)?;
-        let csv = String::from_utf8(csv)?;
+        let csv = Self::download_csv(url).await?;
         let lines: Vec<&str> = csv.lines().collect();
         let headers: Vec<&str> = lines[0].strip_suffix(", ").unwrap().split(", ").collect();
         let codes = &headers[1..];
@@ -63,11 +128,21 @@ impl Provider for Ecb {
             })
             .collect();
 
-        for rate in rates {
-            self.repo.insert_or_replace(&rate)?;
+        for rate in &rates {
+            self.repo.insert_or_replace(rate)?;
         }
 
-        Ok(())
+        // Rates are only recorded from the first sync onwards otherwise
+        if !self
+            .repo
+            .has_history_before("EUR", &Utc::today().naive_utc())?
+        {
+            if let Err(e) = self.sync_history().await {
+                warn!(?e, "Unable to sync rate history");
+            }
+        }
+
+        Ok(rates)
     }
 
     fn crypto_sync_enabled(&self) -> bool {
@@ -78,7 +153,34 @@ impl Provider for Ecb {
         "".into()
     }
 
-    async fn sync_crypto(&self) -> Result<()> {
+    async fn sync_crypto(&self) -> Result<Vec<ExchangeRate>> {
+        Ok(vec![])
+    }
+
+    fn listeners(&self) -> &[Arc<dyn SyncListener>] {
+        &self.listeners
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::provider::Ecb;
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parse_history() -> Result<()> {
+        let csv = "Date,USD,CYP,\n\
+            2021-08-02,1.1861,N/A,\n\
+            1999-01-04,1.1789,0.58231,\n";
+        let rates = Ecb::parse_history(csv)?;
+        assert_eq!(3, rates.len());
+        assert_eq!(NaiveDate::from_ymd(2021, 8, 2), rates[0].date);
+        assert_eq!("USD", rates[0].quote);
+        assert_eq!("EUR", rates[0].base);
+        assert_eq!(1.0 / 1.1861, rates[0].rate);
+        assert_eq!("CYP", rates[2].quote);
+        assert!(Ecb::parse_history("Date,USD,\nyesterday,1.0,\n").is_err());
         Ok(())
     }
 }
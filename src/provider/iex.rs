@@ -1,10 +1,16 @@
-use crate::{model::ExchangeRate, provider::Provider, repository::ExchangeRateRepository};
+use crate::{
+    model::ExchangeRate,
+    provider::{Provider, SyncListener},
+    repository::ExchangeRateRepository,
+};
 use anyhow::Result;
 use serde::Deserialize;
+use std::sync::Arc;
 
 pub struct Iex {
     conf: IexConf,
     repo: ExchangeRateRepository,
+    listeners: Vec<Arc<dyn SyncListener>>,
 }
 
 #[derive(Deserialize)]
@@ -21,10 +27,15 @@ struct IexCryptoQuote {
 }
 
 impl Iex {
-    pub fn new(conf: IexConf, repo: ExchangeRateRepository) -> Self {
+    pub fn new(
+        conf: IexConf,
+        repo: ExchangeRateRepository,
+        listeners: Vec<Arc<dyn SyncListener>>,
+    ) -> Self {
         Self {
             conf: conf,
             repo: repo,
+            listeners,
         }
     }
 }
@@ -43,8 +54,8 @@ impl Provider for Iex {
         "".into()
     }
 
-    async fn sync_fiat(&self) -> Result<()> {
-        Ok(())
+    async fn sync_fiat(&self) -> Result<Vec<ExchangeRate>> {
+        Ok(vec![])
     }
 
     fn crypto_sync_enabled(&self) -> bool {
@@ -55,7 +66,7 @@ impl Provider for Iex {
         self.conf.crypto_schedule.clone()
     }
 
-    async fn sync_crypto(&self) -> Result<()> {
+    async fn sync_crypto(&self) -> Result<Vec<ExchangeRate>> {
         let url = format!(
             "https://cloud.iexapis.com/stable/crypto/BTCEUR/quote?token={}",
             self.conf.token
@@ -67,6 +78,10 @@ impl Provider for Iex {
             rate: quote.latest_price.parse::<f64>()?,
         };
         self.repo.insert_or_replace(&rate)?;
-        Ok(())
+        Ok(vec![rate])
+    }
+
+    fn listeners(&self) -> &[Arc<dyn SyncListener>] {
+        &self.listeners
     }
 }
@@ -1,5 +1,5 @@
 mod provider;
-pub use provider::Provider;
+pub use provider::{run_on_schedule, Provider, SyncListener};
 mod ecb;
 pub use ecb::{Ecb, EcbConf};
 mod iex;
@@ -1,10 +1,19 @@
-use anyhow::Result;
+use crate::model::ExchangeRate;
+use anyhow::{Error, Result};
 use chrono::Utc;
 use cron::Schedule;
 use futures::join;
-use std::str::FromStr;
+use std::{future::Future, str::FromStr, sync::Arc};
 use tokio::time::sleep;
-use tracing::warn;
+use tracing::{error, warn};
+
+/// Gets notified each time a provider finishes syncing
+#[rocket::async_trait]
+pub trait SyncListener: Send + Sync {
+    async fn on_sync(&self, provider: &str, rates: &[ExchangeRate]) -> Result<()>;
+
+    async fn on_sync_failed(&self, provider: &str, error: &Error) -> Result<()>;
+}
 
 #[rocket::async_trait]
 pub trait Provider {
@@ -14,13 +23,32 @@ pub trait Provider {
 
     fn fiat_sync_schedule(&self) -> String;
 
-    async fn sync_fiat(&self) -> Result<()>;
+    async fn sync_fiat(&self) -> Result<Vec<ExchangeRate>>;
 
     fn crypto_sync_enabled(&self) -> bool;
 
     fn crypto_sync_schedule(&self) -> String;
 
-    async fn sync_crypto(&self) -> Result<()>;
+    async fn sync_crypto(&self) -> Result<Vec<ExchangeRate>>;
+
+    fn listeners(&self) -> &[Arc<dyn SyncListener>];
+
+    async fn notify_listeners(&self, result: &Result<Vec<ExchangeRate>>) {
+        if let Err(e) = result {
+            error!(provider = %self.name(), ?e, "Sync failed");
+        }
+
+        for listener in self.listeners() {
+            let res = match result {
+                Ok(rates) => listener.on_sync(&self.name(), rates).await,
+                Err(e) => listener.on_sync_failed(&self.name(), e).await,
+            };
+
+            if let Err(e) = res {
+                error!(provider = %self.name(), ?e, "Sync listener failed");
+            }
+        }
+    }
 
     async fn schedule(&self) -> Result<()> {
         let (_, _) = join!(self.schedule_fiat(), self.schedule_crypto());
@@ -33,27 +61,14 @@ pub trait Provider {
         }
 
         warn!(provider = %self.name(), "Scheduling fiat sync...");
-        let schedule = Schedule::from_str(&self.fiat_sync_schedule())?;
-
-        for next_sync in schedule.upcoming(Utc) {
-            warn!(provider = %self.name(), %next_sync, "Got next sync date");
-            let time_to_next_sync = next_sync.signed_duration_since(Utc::now());
-            if time_to_next_sync.num_nanoseconds().unwrap() < 0 {
-                warn!("Skipping next sync because the old one didn't finish in time");
-                continue;
-            }
-            let time_to_next_sync = time_to_next_sync.to_std().unwrap();
-            warn!(
-                provider = %self.name(),
-                secs_to_next_sync = time_to_next_sync.as_secs(),
-                "Going to sleep till next sync"
-            );
-            sleep(time_to_next_sync).await;
-            warn!(provider = %self.name(), "Syncing...");
-            self.sync_fiat().await.unwrap();
-        }
+        let job = format!("{} fiat sync", self.name());
 
-        Ok(())
+        run_on_schedule(&job, &self.fiat_sync_schedule(), || async {
+            warn!(provider = %self.name(), "Syncing...");
+            let res = self.sync_fiat().await;
+            self.notify_listeners(&res).await;
+        })
+        .await
     }
 
     async fn schedule_crypto(&self) -> Result<()> {
@@ -62,38 +77,58 @@ pub trait Provider {
         }
 
         warn!(provider = %self.name(), "Scheduling crypto sync...");
-        let schedule = Schedule::from_str(&self.crypto_sync_schedule())?;
-
-        for next_sync in schedule.upcoming(Utc) {
-            warn!(provider = %self.name(), %next_sync, "Got next sync date");
-            let time_to_next_sync = next_sync.signed_duration_since(Utc::now());
-            if time_to_next_sync.num_nanoseconds().unwrap() < 0 {
-                warn!("Skipping next sync because the old one didn't finish in time");
-                continue;
-            }
-            let time_to_next_sync = time_to_next_sync.to_std().unwrap();
-            warn!(
-                provider = %self.name(),
-                secs_to_next_sync = time_to_next_sync.as_secs(),
-                "Going to sleep till next sync"
-            );
-            sleep(time_to_next_sync).await;
-            warn!(provider = %self.name(), "Syncing...");
-            self.sync_crypto().await.unwrap();
-        }
+        let job = format!("{} crypto sync", self.name());
 
-        Ok(())
+        run_on_schedule(&job, &self.crypto_sync_schedule(), || async {
+            warn!(provider = %self.name(), "Syncing...");
+            let res = self.sync_crypto().await;
+            self.notify_listeners(&res).await;
+        })
+        .await
     }
 
     async fn sync(&self) -> Result<()> {
         if self.fiat_sync_enabled() {
-            self.sync_fiat().await?
+            let res = self.sync_fiat().await;
+            self.notify_listeners(&res).await;
+            res?;
         }
 
         if self.crypto_sync_enabled() {
-            self.sync_crypto().await?
+            let res = self.sync_crypto().await;
+            self.notify_listeners(&res).await;
+            res?;
         }
 
         Ok(())
     }
 }
+
+/// Runs a job on a cron schedule. Runs which are already due by the time the previous one
+/// finishes are skipped.
+pub async fn run_on_schedule<F, Fut>(job: &str, schedule: &str, run: F) -> Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let schedule = Schedule::from_str(schedule)?;
+
+    for next_run in schedule.upcoming(Utc) {
+        warn!(%job, %next_run, "Got next run date");
+        let time_to_next_run = next_run.signed_duration_since(Utc::now());
+        if time_to_next_run.num_nanoseconds().unwrap() < 0 {
+            warn!(%job, "Skipping next run because the old one didn't finish in time");
+            continue;
+        }
+        let time_to_next_run = time_to_next_run.to_std().unwrap();
+        warn!(
+            %job,
+            secs_to_next_run = time_to_next_run.as_secs(),
+            "Going to sleep till next run"
+        );
+        sleep(time_to_next_run).await;
+        run().await;
+    }
+
+    Ok(())
+}
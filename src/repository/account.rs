@@ -0,0 +1,194 @@
+use crate::model::{Account, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct AccountRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, name, type, currency, opened_at, closed_at, cost_basis_method";
+
+impl AccountRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> AccountRepository {
+        AccountRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Account) -> Result<()> {
+        let query = format!(
+            "INSERT INTO account ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.name,
+            &row.account_type,
+            &row.currency,
+            &row.opened_at,
+            &row.closed_at,
+            &row.cost_basis_method,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Account) -> Result<()> {
+        let query = "UPDATE account SET name = ?, type = ?, currency = ?, opened_at = ?, closed_at = ?, cost_basis_method = ? WHERE id = ?";
+        let params = params![
+            &row.name,
+            &row.account_type,
+            &row.currency,
+            &row.opened_at,
+            &row.closed_at,
+            &row.cost_basis_method,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM account WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Account>> {
+        let query = format!(
+            "SELECT {} FROM account WHERE username = ? ORDER BY opened_at, name",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    /// Owners of at least one account
+    pub fn select_usernames(&self) -> Result<Vec<String>> {
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare("SELECT DISTINCT username FROM account ORDER BY username")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Account>> {
+        let query = format!("SELECT {} FROM account WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Account> {
+    Ok(Account {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        name: row.get(2)?,
+        account_type: row.get(3)?,
+        currency: row.get(4)?,
+        opened_at: row.get(5)?,
+        closed_at: row.get(6)?,
+        cost_basis_method: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Account, AccountType, CostBasisMethod, Id},
+        repository::AccountRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        repo.insert(&account())?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        let mut row = account();
+        repo.insert(&row)?;
+        row.name = "Closed".into();
+        row.closed_at = Some(NaiveDate::from_ymd(2021, 8, 1));
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        let row = account();
+        repo.insert(&row)?;
+        repo.delete(&row.id)?;
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        let row = account();
+        repo.insert(&row)?;
+        assert_eq!(vec![row.clone()], repo.select_by_username(&row.username)?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_usernames() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        assert!(repo.select_usernames()?.is_empty());
+        repo.insert(&account())?;
+        repo.insert(&account())?;
+        assert_eq!(vec!["test".to_string()], repo.select_usernames()?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_id() -> Result<()> {
+        let repo = AccountRepository::new(&pool());
+        let row = account();
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        repo.insert(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    fn account() -> Account {
+        Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        }
+    }
+}
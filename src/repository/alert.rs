@@ -0,0 +1,220 @@
+use crate::model::{Alert, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct AlertRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str =
+    "id, username, quote, base, condition, threshold, notifier, target, last_rate";
+
+impl AlertRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> AlertRepository {
+        AlertRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Alert) -> Result<()> {
+        let query = format!(
+            "INSERT INTO alert ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.quote,
+            &row.base,
+            &row.condition,
+            row.threshold,
+            &row.notifier,
+            &row.target,
+            row.last_rate,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Alert) -> Result<()> {
+        let query = "UPDATE alert SET quote = ?, base = ?, condition = ?, threshold = ?, notifier = ?, target = ?, last_rate = ? WHERE id = ?";
+        let params = params![
+            &row.quote,
+            &row.base,
+            &row.condition,
+            row.threshold,
+            &row.notifier,
+            &row.target,
+            row.last_rate,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update_last_rate(&self, id: &Id, last_rate: f64) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE alert SET last_rate = ? WHERE id = ?",
+                params![last_rate, id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM alert WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_all(&self) -> Result<Vec<Alert>> {
+        let query = format!("SELECT {} FROM alert", COLUMNS);
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map([], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Alert>> {
+        let query = format!("SELECT {} FROM alert WHERE username = ?", COLUMNS);
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Alert>> {
+        let query = format!("SELECT {} FROM alert WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Alert> {
+    Ok(Alert {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        quote: row.get(2)?,
+        base: row.get(3)?,
+        condition: row.get(4)?,
+        threshold: row.get(5)?,
+        notifier: row.get(6)?,
+        target: row.get(7)?,
+        last_rate: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Alert, AlertCondition, Id},
+        repository::AlertRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        repo.insert(&alert())?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        let mut row = alert();
+        repo.insert(&row)?;
+        row.condition = AlertCondition::Below;
+        row.threshold = 2.0;
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn update_last_rate() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        let row = alert();
+        repo.insert(&row)?;
+        repo.update_last_rate(&row.id, 1.5)?;
+        let res = repo.select_by_id(&row.id)?.unwrap();
+        assert_eq!(Some(1.5), res.last_rate);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        let row = alert();
+        repo.insert(&row)?;
+        repo.delete(&row.id)?;
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn select_all() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        assert!(repo.select_all()?.is_empty());
+        let row = alert();
+        repo.insert(&row)?;
+        assert_eq!(vec![row], repo.select_all()?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        let row = alert();
+        repo.insert(&row)?;
+        assert_eq!(vec![row.clone()], repo.select_by_username(&row.username)?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_id() -> Result<()> {
+        let repo = AlertRepository::new(&pool());
+        let row = alert();
+        let res = repo.select_by_id(&row.id)?;
+        assert!(res.is_none());
+        repo.insert(&row)?;
+        let res = repo.select_by_id(&row.id)?;
+        assert_eq!(Some(row), res);
+        Ok(())
+    }
+
+    fn alert() -> Alert {
+        Alert {
+            id: Id::new(),
+            username: "test".into(),
+            quote: "TST".into(),
+            base: "TST".into(),
+            condition: AlertCondition::Above,
+            threshold: 1.0,
+            notifier: "webhook".into(),
+            target: "https://example.com".into(),
+            last_rate: None,
+        }
+    }
+}
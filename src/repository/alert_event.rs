@@ -0,0 +1,134 @@
+use crate::model::{AlertEvent, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+#[derive(Clone)]
+pub struct AlertEventRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl AlertEventRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> AlertEventRepository {
+        AlertEventRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &AlertEvent) -> Result<()> {
+        let query = "INSERT INTO alert_event (id, alert_id, rate, fired_at, delivered) VALUES (?, ?, ?, ?, ?)";
+        let params = params![
+            &row.id,
+            &row.alert_id,
+            row.rate,
+            &row.fired_at,
+            row.delivered
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update_delivered(&self, id: &Id, delivered: bool) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE alert_event SET delivered = ? WHERE id = ?",
+                params![delivered, id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete_by_alert_id(&self, alert_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM alert_event WHERE alert_id = ?",
+                params![alert_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_alert_id(&self, alert_id: &Id) -> Result<Vec<AlertEvent>> {
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, alert_id, rate, fired_at, delivered FROM alert_event WHERE alert_id = ? ORDER BY fired_at DESC",
+        )?;
+        let rows = stmt.query_map(params![alert_id], |row| {
+            Ok(AlertEvent {
+                id: row.get(0)?,
+                alert_id: row.get(1)?,
+                rate: row.get(2)?,
+                fired_at: row.get(3)?,
+                delivered: row.get(4)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{AlertEvent, Id},
+        repository::AlertEventRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = AlertEventRepository::new(&pool());
+        repo.insert(&event(&Id::new()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update_delivered() -> Result<()> {
+        let repo = AlertEventRepository::new(&pool());
+        let row = event(&Id::new());
+        repo.insert(&row)?;
+        repo.update_delivered(&row.id, true)?;
+        let res = repo.select_by_alert_id(&row.alert_id)?;
+        assert!(res[0].delivered);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_alert_id() -> Result<()> {
+        let repo = AlertEventRepository::new(&pool());
+        let row = event(&Id::new());
+        repo.insert(&row)?;
+        repo.delete_by_alert_id(&row.alert_id)?;
+        assert!(repo.select_by_alert_id(&row.alert_id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_alert_id() -> Result<()> {
+        let repo = AlertEventRepository::new(&pool());
+        let row = event(&Id::new());
+        assert!(repo.select_by_alert_id(&row.alert_id)?.is_empty());
+        repo.insert(&row)?;
+        repo.insert(&event(&Id::new()))?;
+        assert_eq!(vec![row.clone()], repo.select_by_alert_id(&row.alert_id)?);
+        Ok(())
+    }
+
+    fn event(alert_id: &Id) -> AlertEvent {
+        AlertEvent {
+            id: Id::new(),
+            alert_id: alert_id.clone(),
+            rate: 1.0,
+            fired_at: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            delivered: false,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::model::Asset;
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct AssetRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "code, name, type, precision, metadata";
+
+impl AssetRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> AssetRepository {
+        AssetRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Asset) -> Result<()> {
+        let query = format!("INSERT INTO asset ({}) VALUES (?, ?, ?, ?, ?)", COLUMNS);
+        let params = params![
+            &row.code,
+            &row.name,
+            &row.asset_type,
+            row.precision,
+            serde_json::to_string(&row.metadata)?,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Asset) -> Result<()> {
+        let query =
+            "UPDATE asset SET name = ?, type = ?, precision = ?, metadata = ? WHERE code = ?";
+        let params = params![
+            &row.name,
+            &row.asset_type,
+            row.precision,
+            serde_json::to_string(&row.metadata)?,
+            &row.code,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, code: &str) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM asset WHERE code = ?", params![code])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Tells whether any user data refers to an asset
+    pub fn is_referenced(&self, code: &str) -> Result<bool> {
+        // Watchlists store a JSON array of codes, which can't contain quotes
+        let query = r#"SELECT
+            EXISTS (SELECT 1 FROM account WHERE currency = ?1)
+            OR EXISTS (SELECT 1 FROM "transaction" WHERE asset = ?1 OR currency = ?1)
+            OR EXISTS (SELECT 1 FROM recurring_transaction WHERE asset = ?1 OR currency = ?1)
+            OR EXISTS (SELECT 1 FROM portfolio_target WHERE asset = ?1)
+            OR EXISTS (SELECT 1 FROM watchlist WHERE instr(assets, '"' || ?1 || '"') > 0)
+            OR EXISTS (SELECT 1 FROM alert WHERE quote = ?1 OR base = ?1)
+            OR EXISTS (SELECT 1 FROM goal WHERE currency = ?1)
+            OR EXISTS (SELECT 1 FROM budget WHERE currency = ?1)"#;
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(query, params![code], |row| row.get(0))
+            .map_err(Error::new)
+    }
+
+    pub fn select_all(&self) -> Result<Vec<Asset>> {
+        let query = format!("SELECT {} FROM asset ORDER BY code", COLUMNS);
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map([], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_code(&self, code: &str) -> Result<Option<Asset>> {
+        let query = format!("SELECT {} FROM asset WHERE code = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![code], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Asset> {
+    let metadata: String = row.get(4)?;
+
+    Ok(Asset {
+        code: row.get(0)?,
+        name: row.get(1)?,
+        asset_type: row.get(2)?,
+        precision: row.get(3)?,
+        metadata: serde_json::from_str(&metadata)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Asset, AssetType},
+        repository::AssetRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        repo.insert(&asset())?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        let mut row = asset();
+        repo.insert(&row)?;
+        row.name = "Test 2".into();
+        row.asset_type = AssetType::Etf;
+        row.metadata.clear();
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_code(&row.code)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        let row = asset();
+        repo.insert(&row)?;
+        repo.delete(&row.code)?;
+        assert!(repo.select_by_code(&row.code)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn select_all() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        let row = asset();
+        repo.insert(&row)?;
+        let res = repo.select_all()?;
+        assert!(res.contains(&row));
+        assert!(res.iter().any(|it| it.code == "EUR"));
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_code() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        let row = asset();
+        assert!(repo.select_by_code(&row.code)?.is_none());
+        repo.insert(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_code(&row.code)?);
+        Ok(())
+    }
+
+    #[test]
+    fn seeded() -> Result<()> {
+        let repo = AssetRepository::new(&pool());
+        let jpy = repo.select_by_code("JPY")?.unwrap();
+        assert_eq!(AssetType::Fiat, jpy.asset_type);
+        assert_eq!(0, jpy.precision);
+        let btc = repo.select_by_code("BTC")?.unwrap();
+        assert_eq!(AssetType::Crypto, btc.asset_type);
+        Ok(())
+    }
+
+    fn asset() -> Asset {
+        Asset {
+            code: "TST".into(),
+            name: "Test".into(),
+            asset_type: AssetType::Stock,
+            precision: 2,
+            metadata: vec![("isin".to_string(), "US0000000000".to_string())]
+                .into_iter()
+                .collect(),
+        }
+    }
+}
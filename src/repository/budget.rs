@@ -0,0 +1,181 @@
+use crate::model::{Budget, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct BudgetRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, category_id, period, amount, currency, rollover, start";
+
+impl BudgetRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> BudgetRepository {
+        BudgetRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Budget) -> Result<()> {
+        let query = format!(
+            "INSERT INTO budget ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.category_id,
+            &row.period,
+            row.amount,
+            &row.currency,
+            &row.rollover,
+            &row.start,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Budget) -> Result<()> {
+        let query = "UPDATE budget SET category_id = ?, period = ?, amount = ?, currency = ?, rollover = ?, start = ? WHERE id = ?";
+        let params = params![
+            &row.category_id,
+            &row.period,
+            row.amount,
+            &row.currency,
+            &row.rollover,
+            &row.start,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM budget WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete_by_category_id(&self, category_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM budget WHERE category_id = ?",
+                params![category_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Budget>> {
+        let query = format!(
+            "SELECT {} FROM budget WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Budget>> {
+        let query = format!("SELECT {} FROM budget WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Budget> {
+    Ok(Budget {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        category_id: row.get(2)?,
+        period: row.get(3)?,
+        amount: row.get(4)?,
+        currency: row.get(5)?,
+        rollover: row.get(6)?,
+        start: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Budget, BudgetPeriod, Id, Rollover},
+        repository::BudgetRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = BudgetRepository::new(&pool());
+        repo.insert(&budget(&Id::new()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = BudgetRepository::new(&pool());
+        let mut row = budget(&Id::new());
+        repo.insert(&row)?;
+        row.period = BudgetPeriod::Year;
+        row.amount = 5000.0;
+        row.rollover = Rollover::All;
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_category_id() -> Result<()> {
+        let repo = BudgetRepository::new(&pool());
+        let row = budget(&Id::new());
+        let other = budget(&Id::new());
+        repo.insert(&row)?;
+        repo.insert(&other)?;
+        repo.delete_by_category_id(&row.category_id)?;
+        assert_eq!(vec![other], repo.select_by_username("test")?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = BudgetRepository::new(&pool());
+        let row = budget(&Id::new());
+        repo.insert(&row)?;
+        assert_eq!(vec![row], repo.select_by_username("test")?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    fn budget(category_id: &Id) -> Budget {
+        Budget {
+            id: Id::new(),
+            username: "test".into(),
+            category_id: category_id.clone(),
+            period: BudgetPeriod::Month,
+            amount: 400.0,
+            currency: "EUR".into(),
+            rollover: Rollover::None,
+            start: NaiveDate::from_ymd(2021, 8, 1),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::model::{Category, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct CategoryRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, name, parent_id";
+
+impl CategoryRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> CategoryRepository {
+        CategoryRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Category) -> Result<()> {
+        let query = format!("INSERT INTO category ({}) VALUES (?, ?, ?, ?)", COLUMNS);
+        let params = params![&row.id, &row.username, &row.name, &row.parent_id];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Category) -> Result<()> {
+        let query = "UPDATE category SET name = ?, parent_id = ? WHERE id = ?";
+        let params = params![&row.name, &row.parent_id, &row.id];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Moves all the subcategories of a category under another parent
+    pub fn update_parent_id(&self, parent_id: &Id, new_parent_id: Option<&Id>) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE category SET parent_id = ? WHERE parent_id = ?",
+                params![new_parent_id, parent_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM category WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Category>> {
+        let query = format!(
+            "SELECT {} FROM category WHERE username = ? ORDER BY name",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Category>> {
+        let query = format!("SELECT {} FROM category WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        name: row.get(2)?,
+        parent_id: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Category, Id},
+        repository::CategoryRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = CategoryRepository::new(&pool());
+        repo.insert(&category("Food", None))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = CategoryRepository::new(&pool());
+        let parent = category("Food", None);
+        let mut row = category("Groceries", None);
+        repo.insert(&parent)?;
+        repo.insert(&row)?;
+        row.name = "Supermarket".into();
+        row.parent_id = Some(parent.id.clone());
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn update_parent_id() -> Result<()> {
+        let repo = CategoryRepository::new(&pool());
+        let parent = category("Food", None);
+        let child = category("Groceries", Some(&parent.id));
+        repo.insert(&parent)?;
+        repo.insert(&child)?;
+        repo.update_parent_id(&parent.id, None)?;
+        assert_eq!(None, repo.select_by_id(&child.id)?.unwrap().parent_id);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = CategoryRepository::new(&pool());
+        let row = category("Food", None);
+        repo.insert(&row)?;
+        repo.delete(&row.id)?;
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = CategoryRepository::new(&pool());
+        let food = category("Food", None);
+        let car = category("Car", None);
+        repo.insert(&food)?;
+        repo.insert(&car)?;
+        assert_eq!(vec![car, food], repo.select_by_username("test")?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    fn category(name: &str, parent_id: Option<&Id>) -> Category {
+        Category {
+            id: Id::new(),
+            username: "test".into(),
+            name: name.into(),
+            parent_id: parent_id.cloned(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::model::{CategoryRule, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct CategoryRuleRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, pattern, category_id, tags";
+
+impl CategoryRuleRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> CategoryRuleRepository {
+        CategoryRuleRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &CategoryRule) -> Result<()> {
+        let query = format!(
+            "INSERT INTO category_rule ({}) VALUES (?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.pattern,
+            &row.category_id,
+            serde_json::to_string(&row.tags)?,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &CategoryRule) -> Result<()> {
+        let query = "UPDATE category_rule SET pattern = ?, category_id = ?, tags = ? WHERE id = ?";
+        let params = params![
+            &row.pattern,
+            &row.category_id,
+            serde_json::to_string(&row.tags)?,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM category_rule WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete_by_category_id(&self, category_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM category_rule WHERE category_id = ?",
+                params![category_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Oldest rules come first
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<CategoryRule>> {
+        let query = format!(
+            "SELECT {} FROM category_rule WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<CategoryRule>> {
+        let query = format!("SELECT {} FROM category_rule WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<CategoryRule> {
+    let tags: String = row.get(4)?;
+
+    Ok(CategoryRule {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        pattern: row.get(2)?,
+        category_id: row.get(3)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{CategoryRule, Id},
+        repository::CategoryRuleRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = CategoryRuleRepository::new(&pool());
+        repo.insert(&rule(&Id::new()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = CategoryRuleRepository::new(&pool());
+        let mut row = rule(&Id::new());
+        repo.insert(&row)?;
+        row.pattern = "lidl".into();
+        row.tags = vec![];
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_category_id() -> Result<()> {
+        let repo = CategoryRuleRepository::new(&pool());
+        let row = rule(&Id::new());
+        let other = rule(&Id::new());
+        repo.insert(&row)?;
+        repo.insert(&other)?;
+        repo.delete_by_category_id(&row.category_id)?;
+        assert_eq!(vec![other], repo.select_by_username("test")?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = CategoryRuleRepository::new(&pool());
+        let first = rule(&Id::new());
+        let second = rule(&Id::new());
+        repo.insert(&first)?;
+        repo.insert(&second)?;
+        assert_eq!(vec![first, second], repo.select_by_username("test")?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    fn rule(category_id: &Id) -> CategoryRule {
+        CategoryRule {
+            id: Id::new(),
+            username: "test".into(),
+            pattern: "^aldi".into(),
+            category_id: category_id.clone(),
+            tags: vec!["groceries".into()],
+        }
+    }
+}
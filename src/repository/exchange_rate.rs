@@ -1,5 +1,6 @@
-use crate::model::ExchangeRate;
+use crate::model::{ExchangeRate, HistoricalRate};
 use anyhow::Error;
+use chrono::{NaiveDate, Utc};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{params, OptionalExtension};
@@ -14,6 +15,19 @@ impl ExchangeRateRepository {
         ExchangeRateRepository { pool: pool.clone() }
     }
 
+    /// Gets incremented by triggers on every write to the rates, so it also reflects the
+    /// writes made by other processes such as `db sync`
+    pub fn version(&self) -> anyhow::Result<u64> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row("SELECT version FROM exchange_rate_version", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|it| it as u64)
+            .map_err(Error::new)
+    }
+
     pub fn insert_or_replace(&self, row: &ExchangeRate) -> anyhow::Result<()> {
         let query = "INSERT OR REPLACE INTO exchange_rate (quote, base, rate) VALUES (?, ?, ?)";
         let params = params![&row.quote, &row.base, row.rate];
@@ -22,7 +36,56 @@ impl ExchangeRateRepository {
             .unwrap()
             .execute(query, params)
             .map(|_| ())
-            .map_err(|e| Error::new(e))
+            .map_err(|e| Error::new(e))?;
+        self.insert_or_replace_history(row, &Utc::today().naive_utc())
+    }
+
+    pub fn insert_or_replace_history(
+        &self,
+        row: &ExchangeRate,
+        date: &NaiveDate,
+    ) -> anyhow::Result<()> {
+        let query = "INSERT OR REPLACE INTO exchange_rate_history (quote, base, date, rate) VALUES (?, ?, ?, ?)";
+        let params = params![&row.quote, &row.base, date, row.rate];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Writes a batch of historical rates in a single transaction
+    pub fn insert_or_replace_history_all(&self, rows: &[HistoricalRate]) -> anyhow::Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO exchange_rate_history (quote, base, date, rate) VALUES (?, ?, ?, ?)",
+            )?;
+
+            for row in rows {
+                stmt.execute(params![&row.quote, &row.base, &row.date, row.rate])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Tells whether any rate to a base was recorded before a given date
+    pub fn has_history_before(&self, base: &str, date: &NaiveDate) -> anyhow::Result<bool> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM exchange_rate_history WHERE base = ? AND date < ?)",
+                params![base, date],
+                |row| row.get(0),
+            )
+            .map_err(Error::new)
     }
 
     pub fn select_by_quote_and_base(
@@ -47,12 +110,63 @@ impl ExchangeRateRepository {
             .optional()
             .map_err(|e| Error::new(e))
     }
+
+    /// Returns the last known rate on or before a given date
+    pub fn select_by_quote_and_base_and_date(
+        &self,
+        quote: &str,
+        base: &str,
+        date: &NaiveDate,
+    ) -> anyhow::Result<Option<ExchangeRate>> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT rate FROM exchange_rate_history WHERE quote = ? AND base = ? AND date <= ? ORDER BY date DESC LIMIT 1",
+                params![quote, base, date],
+                |row| {
+                    Ok(ExchangeRate {
+                        quote: quote.to_string(),
+                        base: base.to_string(),
+                        rate: row.get(0)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(Error::new)
+    }
+
+    /// Oldest rates come first
+    pub fn select_history_by_quote_and_base(
+        &self,
+        quote: &str,
+        base: &str,
+    ) -> anyhow::Result<Vec<HistoricalRate>> {
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT date, rate FROM exchange_rate_history WHERE quote = ? AND base = ? ORDER BY date",
+        )?;
+        let rows = stmt.query_map(params![quote, base], |row| {
+            Ok(HistoricalRate {
+                date: row.get(0)?,
+                quote: quote.to_string(),
+                base: base.to_string(),
+                rate: row.get(1)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{model::ExchangeRate, repository::ExchangeRateRepository, test::pool};
+    use crate::{
+        model::{ExchangeRate, HistoricalRate},
+        repository::ExchangeRateRepository,
+        test::pool,
+    };
     use anyhow::Result;
+    use chrono::NaiveDate;
 
     #[test]
     fn insert_or_replace() -> Result<()> {
@@ -61,6 +175,20 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn version() -> Result<()> {
+        let pool = pool();
+        let repo = ExchangeRateRepository::new(&pool);
+        let version = repo.version()?;
+        repo.insert_or_replace(&rate())?;
+        assert!(repo.version()? > version);
+        let version = repo.version()?;
+        // Writes which bypass the repository, such as from another process
+        pool.get()?.execute("DELETE FROM exchange_rate", [])?;
+        assert!(repo.version()? > version);
+        Ok(())
+    }
+
     #[test]
     fn select_by_quote_and_base() -> Result<()> {
         let repo = ExchangeRateRepository::new(&pool());
@@ -73,6 +201,67 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn select_by_quote_and_base_and_date() -> Result<()> {
+        let repo = ExchangeRateRepository::new(&pool());
+        let row = rate();
+        let date = NaiveDate::from_ymd(2021, 8, 2);
+        let res = repo.select_by_quote_and_base_and_date(&row.quote, &row.base, &date)?;
+        assert!(res.is_none());
+        repo.insert_or_replace_history(&row, &date.pred())?;
+        let res = repo.select_by_quote_and_base_and_date(&row.quote, &row.base, &date)?;
+        assert_eq!(Some(row), res);
+        Ok(())
+    }
+
+    #[test]
+    fn select_history_by_quote_and_base() -> Result<()> {
+        let repo = ExchangeRateRepository::new(&pool());
+        let row = rate();
+        let date = NaiveDate::from_ymd(2021, 8, 2);
+        repo.insert_or_replace_history(&row, &date)?;
+        repo.insert_or_replace_history(&row, &date.pred())?;
+        let res = repo.select_history_by_quote_and_base(&row.quote, &row.base)?;
+        assert_eq!(
+            vec![date.pred(), date],
+            res.iter().map(|it| it.date).collect::<Vec<_>>()
+        );
+        assert!(repo
+            .select_history_by_quote_and_base("TST", "EUR")?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn insert_or_replace_history_all() -> Result<()> {
+        let repo = ExchangeRateRepository::new(&pool());
+        let date = NaiveDate::from_ymd(2021, 8, 2);
+        let version = repo.version()?;
+        assert!(!repo.has_history_before("TST", &date)?);
+        repo.insert_or_replace_history_all(&[
+            HistoricalRate {
+                date: date.pred(),
+                quote: "TST".into(),
+                base: "TST".into(),
+                rate: 1.0,
+            },
+            HistoricalRate {
+                date,
+                quote: "TST".into(),
+                base: "TST".into(),
+                rate: 2.0,
+            },
+        ])?;
+        assert!(repo.version()? > version);
+        assert!(repo.has_history_before("TST", &date)?);
+        assert!(!repo.has_history_before("TST", &date.pred())?);
+        assert_eq!(
+            2,
+            repo.select_history_by_quote_and_base("TST", "TST")?.len()
+        );
+        Ok(())
+    }
+
     fn rate() -> ExchangeRate {
         ExchangeRate {
             quote: "TST".into(),
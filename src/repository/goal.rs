@@ -0,0 +1,157 @@
+use crate::model::{Goal, Id};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct GoalRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, name, target, currency, deadline, account_ids";
+
+impl GoalRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> GoalRepository {
+        GoalRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Goal) -> Result<()> {
+        let query = format!(
+            "INSERT INTO goal ({}) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.name,
+            row.target,
+            &row.currency,
+            &row.deadline,
+            serde_json::to_string(&row.account_ids)?,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Goal) -> Result<()> {
+        let query = "UPDATE goal SET name = ?, target = ?, currency = ?, deadline = ?, account_ids = ? WHERE id = ?";
+        let params = params![
+            &row.name,
+            row.target,
+            &row.currency,
+            &row.deadline,
+            serde_json::to_string(&row.account_ids)?,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM goal WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Goal>> {
+        let query = format!(
+            "SELECT {} FROM goal WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Goal>> {
+        let query = format!("SELECT {} FROM goal WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Goal> {
+    let account_ids: String = row.get(6)?;
+
+    Ok(Goal {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        name: row.get(2)?,
+        target: row.get(3)?,
+        currency: row.get(4)?,
+        deadline: row.get(5)?,
+        account_ids: serde_json::from_str(&account_ids)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Goal, Id},
+        repository::GoalRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = GoalRepository::new(&pool());
+        repo.insert(&goal("test"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = GoalRepository::new(&pool());
+        let mut row = goal("test");
+        repo.insert(&row)?;
+        row.name = "House".into();
+        row.target = 50000.0;
+        row.deadline = None;
+        row.account_ids.push(Id::new());
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = GoalRepository::new(&pool());
+        let row = goal("test");
+        repo.insert(&row)?;
+        repo.insert(&goal("test2"))?;
+        assert_eq!(vec![row], repo.select_by_username("test")?);
+        Ok(())
+    }
+
+    fn goal(username: &str) -> Goal {
+        Goal {
+            id: Id::new(),
+            username: username.into(),
+            name: "Car".into(),
+            target: 20000.0,
+            currency: "EUR".into(),
+            deadline: Some(NaiveDate::from_ymd(2023, 6, 30)),
+            account_ids: vec![Id::new()],
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    model::{Id, Lot, LotMatch},
+    repository::lot_match,
+};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, Row};
+
+#[derive(Clone)]
+pub struct LotRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, account_id, asset, quantity, remaining, cost, currency, acquired_at";
+
+impl LotRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> LotRepository {
+        LotRepository { pool: pool.clone() }
+    }
+
+    /// Replaces the lots and the matches of the given accounts in a single transaction, so
+    /// readers never see them half written
+    pub fn replace_by_account_ids(
+        &self,
+        account_ids: &[Id],
+        lots: &[Lot],
+        matches: &[LotMatch],
+    ) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+
+        for account_id in account_ids {
+            tx.execute(
+                "DELETE FROM lot_match WHERE account_id = ?",
+                params![account_id],
+            )?;
+            tx.execute("DELETE FROM lot WHERE account_id = ?", params![account_id])?;
+        }
+
+        for lot in lots {
+            insert(&tx, lot)?;
+        }
+
+        for lot_match in matches {
+            lot_match::insert(&tx, lot_match)?;
+        }
+
+        tx.commit().map_err(Error::new)
+    }
+
+    pub fn delete_by_account_id(&self, account_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM lot WHERE account_id = ?", params![account_id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Oldest lots come first
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<Lot>> {
+        let query = format!(
+            "SELECT {} FROM lot WHERE account_id = ? ORDER BY acquired_at, rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![account_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+fn insert(conn: &Connection, row: &Lot) -> rusqlite::Result<()> {
+    let query = format!(
+        "INSERT INTO lot ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        COLUMNS
+    );
+    let params = params![
+        &row.id,
+        &row.account_id,
+        &row.asset,
+        row.quantity,
+        row.remaining,
+        row.cost,
+        &row.currency,
+        &row.acquired_at,
+    ];
+    conn.execute(&query, params).map(|_| ())
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Lot> {
+    Ok(Lot {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        asset: row.get(2)?,
+        quantity: row.get(3)?,
+        remaining: row.get(4)?,
+        cost: row.get(5)?,
+        currency: row.get(6)?,
+        acquired_at: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, Lot, LotMatch},
+        repository::{LotMatchRepository, LotRepository},
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let pool = pool();
+        let repo = LotRepository::new(&pool);
+        let row = lot(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_account_id(&row.account_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_account_id() -> Result<()> {
+        let pool = pool();
+        let repo = LotRepository::new(&pool);
+        let row = lot(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        repo.delete_by_account_id(&row.account_id)?;
+        assert!(repo.select_by_account_id(&row.account_id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_account_id() -> Result<()> {
+        let pool = pool();
+        let repo = LotRepository::new(&pool);
+        let row = lot(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        super::insert(&pool.get().unwrap(), &lot(&Id::new()))?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_account_id(&row.account_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replace_by_account_ids() -> Result<()> {
+        let pool = pool();
+        let repo = LotRepository::new(&pool);
+        let match_repo = LotMatchRepository::new(&pool);
+        let account_id = Id::new();
+        let other = lot(&Id::new());
+        super::insert(&pool.get().unwrap(), &lot(&account_id))?;
+        super::insert(&pool.get().unwrap(), &other)?;
+        let row = lot(&account_id);
+        let lot_match = LotMatch {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            sell_id: Id::new(),
+            lot_id: row.id.clone(),
+            asset: "BTC".into(),
+            quantity: 0.5,
+            cost: 20000.0,
+            cost_currency: "USD".into(),
+            proceeds: 25000.0,
+            proceeds_currency: "USD".into(),
+            acquired_at: row.acquired_at,
+            sold_at: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+        };
+        repo.replace_by_account_ids(
+            std::slice::from_ref(&account_id),
+            std::slice::from_ref(&row),
+            std::slice::from_ref(&lot_match),
+        )?;
+        assert_eq!(vec![row], repo.select_by_account_id(&account_id)?);
+        assert_eq!(
+            vec![lot_match],
+            match_repo.select_by_account_id(&account_id)?
+        );
+        assert_eq!(
+            vec![other.clone()],
+            repo.select_by_account_id(&other.account_id)?
+        );
+        Ok(())
+    }
+
+    fn lot(account_id: &Id) -> Lot {
+        Lot {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            asset: "BTC".into(),
+            quantity: 1.0,
+            remaining: 0.5,
+            cost: 40000.0,
+            currency: "USD".into(),
+            acquired_at: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::model::{Id, LotMatch};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, Row};
+
+#[derive(Clone)]
+pub struct LotMatchRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, account_id, sell_id, lot_id, asset, quantity, cost, cost_currency, proceeds, proceeds_currency, acquired_at, sold_at";
+
+impl LotMatchRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> LotMatchRepository {
+        LotMatchRepository { pool: pool.clone() }
+    }
+
+    pub fn delete_by_account_id(&self, account_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM lot_match WHERE account_id = ?",
+                params![account_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Earliest sells come first
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<LotMatch>> {
+        let query = format!(
+            "SELECT {} FROM lot_match WHERE account_id = ? ORDER BY sold_at, rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![account_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+/// Also used by `LotRepository`, which writes matches along with their lots
+pub(super) fn insert(conn: &Connection, row: &LotMatch) -> rusqlite::Result<()> {
+    let query = format!(
+        "INSERT INTO lot_match ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        COLUMNS
+    );
+    let params = params![
+        &row.id,
+        &row.account_id,
+        &row.sell_id,
+        &row.lot_id,
+        &row.asset,
+        row.quantity,
+        row.cost,
+        &row.cost_currency,
+        row.proceeds,
+        &row.proceeds_currency,
+        &row.acquired_at,
+        &row.sold_at,
+    ];
+    conn.execute(&query, params).map(|_| ())
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<LotMatch> {
+    Ok(LotMatch {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        sell_id: row.get(2)?,
+        lot_id: row.get(3)?,
+        asset: row.get(4)?,
+        quantity: row.get(5)?,
+        cost: row.get(6)?,
+        cost_currency: row.get(7)?,
+        proceeds: row.get(8)?,
+        proceeds_currency: row.get(9)?,
+        acquired_at: row.get(10)?,
+        sold_at: row.get(11)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, LotMatch},
+        repository::LotMatchRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let pool = pool();
+        let repo = LotMatchRepository::new(&pool);
+        let row = lot_match(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_account_id(&row.account_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_account_id() -> Result<()> {
+        let pool = pool();
+        let repo = LotMatchRepository::new(&pool);
+        let row = lot_match(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        repo.delete_by_account_id(&row.account_id)?;
+        assert!(repo.select_by_account_id(&row.account_id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_account_id() -> Result<()> {
+        let pool = pool();
+        let repo = LotMatchRepository::new(&pool);
+        let row = lot_match(&Id::new());
+        super::insert(&pool.get().unwrap(), &row)?;
+        super::insert(&pool.get().unwrap(), &lot_match(&Id::new()))?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_account_id(&row.account_id)?
+        );
+        Ok(())
+    }
+
+    fn lot_match(account_id: &Id) -> LotMatch {
+        LotMatch {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            sell_id: Id::new(),
+            lot_id: Id::new(),
+            asset: "BTC".into(),
+            quantity: 0.5,
+            cost: 20000.0,
+            cost_currency: "USD".into(),
+            proceeds: 25000.0,
+            proceeds_currency: "USD".into(),
+            acquired_at: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            sold_at: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+        }
+    }
+}
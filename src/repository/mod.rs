@@ -1,6 +1,42 @@
+pub mod account;
+pub use account::AccountRepository;
+pub mod alert;
+pub use alert::AlertRepository;
+pub mod alert_event;
+pub use alert_event::AlertEventRepository;
+pub mod asset;
+pub use asset::AssetRepository;
 pub mod auth_token;
 pub use auth_token::AuthTokenRepository;
+pub mod budget;
+pub use budget::BudgetRepository;
+pub mod category;
+pub use category::CategoryRepository;
+pub mod category_rule;
+pub use category_rule::CategoryRuleRepository;
 pub mod exchange_rate;
 pub use exchange_rate::ExchangeRateRepository;
+pub mod goal;
+pub use goal::GoalRepository;
+pub mod lot;
+pub use lot::LotRepository;
+pub mod lot_match;
+pub use lot_match::LotMatchRepository;
+pub mod portfolio_snapshot;
+pub use portfolio_snapshot::PortfolioSnapshotRepository;
+pub mod recurring_exception;
+pub use recurring_exception::RecurringExceptionRepository;
+pub mod recurring_transaction;
+pub use recurring_transaction::RecurringTransactionRepository;
+pub mod target;
+pub use target::TargetRepository;
+pub mod transaction;
+pub use transaction::TransactionRepository;
 pub mod user;
 pub use user::UserRepository;
+pub mod watchlist;
+pub use watchlist::WatchlistRepository;
+pub mod webhook;
+pub use webhook::WebhookRepository;
+pub mod webhook_delivery;
+pub use webhook_delivery::WebhookDeliveryRepository;
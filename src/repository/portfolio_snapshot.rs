@@ -0,0 +1,180 @@
+use crate::model::{Id, PortfolioSnapshot};
+use anyhow::{Error, Result};
+use chrono::NaiveDate;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Row};
+
+#[derive(Clone)]
+pub struct PortfolioSnapshotRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "username, currency, date, value";
+
+impl PortfolioSnapshotRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> PortfolioSnapshotRepository {
+        PortfolioSnapshotRepository { pool: pool.clone() }
+    }
+
+    pub fn insert_or_replace(&self, row: &PortfolioSnapshot) -> Result<()> {
+        let query = format!(
+            "INSERT OR REPLACE INTO portfolio_snapshot ({}) VALUES (?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![&row.username, &row.currency, &row.date, row.value];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Drops the snapshots of the account owner which are affected by a change made on a
+    /// given date
+    pub fn delete_by_account_id_since(&self, account_id: &Id, date: &NaiveDate) -> Result<()> {
+        let query = "DELETE FROM portfolio_snapshot WHERE date >= ? AND username = (SELECT username FROM account WHERE id = ?)";
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params![date, account_id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username_and_currency(
+        &self,
+        username: &str,
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<PortfolioSnapshot>> {
+        let query = format!(
+            "SELECT {} FROM portfolio_snapshot WHERE username = ?1 AND currency = ?2 AND (?3 IS NULL OR date >= ?3) AND (?4 IS NULL OR date <= ?4) ORDER BY date",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username, currency, from, to], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_last_date(&self, username: &str, currency: &str) -> Result<Option<NaiveDate>> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT MAX(date) FROM portfolio_snapshot WHERE username = ? AND currency = ?",
+                params![username, currency],
+                |row| row.get(0),
+            )
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<PortfolioSnapshot> {
+    Ok(PortfolioSnapshot {
+        username: row.get(0)?,
+        currency: row.get(1)?,
+        date: row.get(2)?,
+        value: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Account, AccountType, CostBasisMethod, Id, PortfolioSnapshot},
+        repository::{AccountRepository, PortfolioSnapshotRepository},
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn insert_or_replace() -> Result<()> {
+        let repo = PortfolioSnapshotRepository::new(&pool());
+        let mut row = snapshot("test", 1);
+        repo.insert_or_replace(&row)?;
+        row.value = 2.0;
+        repo.insert_or_replace(&row)?;
+        assert_eq!(
+            vec![row],
+            repo.select_by_username_and_currency("test", "USD", None, None)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_account_id_since() -> Result<()> {
+        let pool = pool();
+        let repo = PortfolioSnapshotRepository::new(&pool);
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: date(1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        AccountRepository::new(&pool).insert(&account)?;
+        let kept = snapshot("test", 1);
+        repo.insert_or_replace(&kept)?;
+        repo.insert_or_replace(&snapshot("test", 2))?;
+        let foreign = snapshot("test2", 2);
+        repo.insert_or_replace(&foreign)?;
+        repo.delete_by_account_id_since(&account.id, &date(2))?;
+        assert_eq!(
+            vec![kept],
+            repo.select_by_username_and_currency("test", "USD", None, None)?
+        );
+        assert_eq!(
+            vec![foreign],
+            repo.select_by_username_and_currency("test2", "USD", None, None)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username_and_currency() -> Result<()> {
+        let repo = PortfolioSnapshotRepository::new(&pool());
+        for day in 1..=3 {
+            repo.insert_or_replace(&snapshot("test", day))?;
+        }
+        let res =
+            repo.select_by_username_and_currency("test", "USD", Some(date(2)), Some(date(2)))?;
+        assert_eq!(vec![snapshot("test", 2)], res);
+        let res = repo.select_by_username_and_currency("test", "USD", Some(date(2)), None)?;
+        assert_eq!(2, res.len());
+        assert!(repo
+            .select_by_username_and_currency("test", "EUR", None, None)?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_last_date() -> Result<()> {
+        let repo = PortfolioSnapshotRepository::new(&pool());
+        assert_eq!(None, repo.select_last_date("test", "USD")?);
+        repo.insert_or_replace(&snapshot("test", 2))?;
+        repo.insert_or_replace(&snapshot("test", 1))?;
+        assert_eq!(Some(date(2)), repo.select_last_date("test", "USD")?);
+        Ok(())
+    }
+
+    fn snapshot(username: &str, day: u32) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            username: username.into(),
+            currency: "USD".into(),
+            date: date(day),
+            value: 1.0,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2021, 8, day)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::model::{Id, RecurringException};
+use anyhow::{Error, Result};
+use chrono::NaiveDate;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Row};
+
+#[derive(Clone)]
+pub struct RecurringExceptionRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "recurring_id, date, skip, quantity, price, time";
+
+impl RecurringExceptionRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> RecurringExceptionRepository {
+        RecurringExceptionRepository { pool: pool.clone() }
+    }
+
+    pub fn insert_or_replace(&self, row: &RecurringException) -> Result<()> {
+        let query = format!(
+            "INSERT OR REPLACE INTO recurring_exception ({}) VALUES (?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.recurring_id,
+            &row.date,
+            row.skip,
+            row.quantity,
+            row.price,
+            &row.time,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, recurring_id: &Id, date: &NaiveDate) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM recurring_exception WHERE recurring_id = ? AND date = ?",
+                params![recurring_id, date],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete_by_recurring_id(&self, recurring_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM recurring_exception WHERE recurring_id = ?",
+                params![recurring_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_recurring_id(&self, recurring_id: &Id) -> Result<Vec<RecurringException>> {
+        let query = format!(
+            "SELECT {} FROM recurring_exception WHERE recurring_id = ? ORDER BY date",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![recurring_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<RecurringException> {
+    Ok(RecurringException {
+        recurring_id: row.get(0)?,
+        date: row.get(1)?,
+        skip: row.get(2)?,
+        quantity: row.get(3)?,
+        price: row.get(4)?,
+        time: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, RecurringException},
+        repository::RecurringExceptionRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn insert_or_replace() -> Result<()> {
+        let repo = RecurringExceptionRepository::new(&pool());
+        let mut row = exception(&Id::new());
+        repo.insert_or_replace(&row)?;
+        row.skip = false;
+        row.quantity = Some(2100.0);
+        row.time = Some(Utc.ymd(2021, 8, 27).and_hms(9, 0, 0));
+        repo.insert_or_replace(&row)?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_recurring_id(&row.recurring_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = RecurringExceptionRepository::new(&pool());
+        let row = exception(&Id::new());
+        repo.insert_or_replace(&row)?;
+        repo.delete(&row.recurring_id, &row.date)?;
+        assert!(repo.select_by_recurring_id(&row.recurring_id)?.is_empty());
+        Ok(())
+    }
+
+    fn exception(recurring_id: &Id) -> RecurringException {
+        RecurringException {
+            recurring_id: recurring_id.clone(),
+            date: NaiveDate::from_ymd(2021, 8, 25),
+            skip: true,
+            quantity: None,
+            price: None,
+            time: None,
+        }
+    }
+}
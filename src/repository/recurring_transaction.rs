@@ -0,0 +1,256 @@
+use crate::model::{Id, RecurringTransaction};
+use anyhow::{Error, Result};
+use chrono::NaiveDate;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct RecurringTransactionRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, account_id, type, asset, quantity, price, currency, fee, description, category_id, tags, schedule, start, end, materialized_until";
+
+impl RecurringTransactionRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> RecurringTransactionRepository {
+        RecurringTransactionRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &RecurringTransaction) -> Result<()> {
+        let query = format!(
+            "INSERT INTO recurring_transaction ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.account_id,
+            &row.transaction_type,
+            &row.asset,
+            row.quantity,
+            row.price,
+            &row.currency,
+            row.fee,
+            &row.description,
+            &row.category_id,
+            serde_json::to_string(&row.tags)?,
+            &row.schedule,
+            &row.start,
+            &row.end,
+            &row.materialized_until,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &RecurringTransaction) -> Result<()> {
+        let query = "UPDATE recurring_transaction SET account_id = ?, type = ?, asset = ?, quantity = ?, price = ?, currency = ?, fee = ?, description = ?, category_id = ?, tags = ?, schedule = ?, start = ?, end = ?, materialized_until = ? WHERE id = ?";
+        let params = params![
+            &row.account_id,
+            &row.transaction_type,
+            &row.asset,
+            row.quantity,
+            row.price,
+            &row.currency,
+            row.fee,
+            &row.description,
+            &row.category_id,
+            serde_json::to_string(&row.tags)?,
+            &row.schedule,
+            &row.start,
+            &row.end,
+            &row.materialized_until,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update_materialized_until(&self, id: &Id, materialized_until: &NaiveDate) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE recurring_transaction SET materialized_until = ? WHERE id = ?",
+                params![materialized_until, id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn clear_category_id(&self, category_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE recurring_transaction SET category_id = NULL WHERE category_id = ?",
+                params![category_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM recurring_transaction WHERE id = ?",
+                params![id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_all(&self) -> Result<Vec<RecurringTransaction>> {
+        let query = format!(
+            "SELECT {} FROM recurring_transaction ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map([], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<RecurringTransaction>> {
+        let query = format!(
+            "SELECT {} FROM recurring_transaction WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<RecurringTransaction>> {
+        let query = format!(
+            "SELECT {} FROM recurring_transaction WHERE account_id = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![account_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<RecurringTransaction>> {
+        let query = format!("SELECT {} FROM recurring_transaction WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<RecurringTransaction> {
+    let tags: String = row.get(11)?;
+
+    Ok(RecurringTransaction {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        account_id: row.get(2)?,
+        transaction_type: row.get(3)?,
+        asset: row.get(4)?,
+        quantity: row.get(5)?,
+        price: row.get(6)?,
+        currency: row.get(7)?,
+        fee: row.get(8)?,
+        description: row.get(9)?,
+        category_id: row.get(10)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(11, Type::Text, e.into()))?,
+        schedule: row.get(12)?,
+        start: row.get(13)?,
+        end: row.get(14)?,
+        materialized_until: row.get(15)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, RecurringTransaction, TransactionType},
+        repository::RecurringTransactionRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = RecurringTransactionRepository::new(&pool());
+        repo.insert(&recurring("test"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = RecurringTransactionRepository::new(&pool());
+        let mut row = recurring("test");
+        repo.insert(&row)?;
+        row.quantity = 2500.0;
+        row.schedule = "FREQ=MONTHLY;BYMONTHDAY=-1".into();
+        row.end = Some(NaiveDate::from_ymd(2022, 12, 31));
+        row.tags = vec!["salary".into()];
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn update_materialized_until() -> Result<()> {
+        let repo = RecurringTransactionRepository::new(&pool());
+        let row = recurring("test");
+        repo.insert(&row)?;
+        let date = NaiveDate::from_ymd(2021, 9, 1);
+        repo.update_materialized_until(&row.id, &date)?;
+        let row = repo.select_by_id(&row.id)?.unwrap();
+        assert_eq!(Some(date), row.materialized_until);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = RecurringTransactionRepository::new(&pool());
+        let row = recurring("test");
+        repo.insert(&row)?;
+        repo.insert(&recurring("test2"))?;
+        assert_eq!(vec![row], repo.select_by_username("test")?);
+        assert_eq!(2, repo.select_all()?.len());
+        Ok(())
+    }
+
+    fn recurring(username: &str) -> RecurringTransaction {
+        RecurringTransaction {
+            id: Id::new(),
+            username: username.into(),
+            account_id: Id::new(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 2000.0,
+            price: None,
+            currency: "EUR".into(),
+            fee: 0.0,
+            description: Some("Salary".into()),
+            category_id: None,
+            tags: vec![],
+            schedule: "0 0 12 25 * * *".into(),
+            start: Utc.ymd(2021, 8, 1).and_hms(0, 0, 0),
+            end: None,
+            materialized_until: None,
+        }
+    }
+}
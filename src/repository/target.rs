@@ -0,0 +1,99 @@
+use crate::model::Target;
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Row};
+
+#[derive(Clone)]
+pub struct TargetRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "username, asset, asset_type, weight";
+
+impl TargetRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> TargetRepository {
+        TargetRepository { pool: pool.clone() }
+    }
+
+    /// Swaps all the targets of a user at once, so their weights never stop adding up
+    pub fn replace_by_username(&self, username: &str, rows: &[Target]) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM portfolio_target WHERE username = ?",
+            params![username],
+        )?;
+
+        let query = format!(
+            "INSERT INTO portfolio_target ({}) VALUES (?, ?, ?, ?)",
+            COLUMNS
+        );
+
+        for row in rows {
+            tx.execute(
+                &query,
+                params![&row.username, &row.asset, &row.asset_type, row.weight],
+            )?;
+        }
+
+        tx.commit().map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Target>> {
+        let query = format!(
+            "SELECT {} FROM portfolio_target WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Target> {
+    Ok(Target {
+        username: row.get(0)?,
+        asset: row.get(1)?,
+        asset_type: row.get(2)?,
+        weight: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{AssetType, Target},
+        repository::TargetRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn replace_by_username() -> Result<()> {
+        let repo = TargetRepository::new(&pool());
+        let btc = target("test", Some("BTC"), None, 0.4);
+        let stocks = target("test", None, Some(AssetType::Stock), 0.6);
+        repo.replace_by_username("test", std::slice::from_ref(&btc))?;
+        repo.replace_by_username("test2", &[target("test2", Some("BTC"), None, 1.0)])?;
+        repo.replace_by_username("test", &[btc.clone(), stocks.clone()])?;
+        assert_eq!(vec![btc, stocks], repo.select_by_username("test")?);
+        assert_eq!(1, repo.select_by_username("test2")?.len());
+        Ok(())
+    }
+
+    fn target(
+        username: &str,
+        asset: Option<&str>,
+        asset_type: Option<AssetType>,
+        weight: f64,
+    ) -> Target {
+        Target {
+            username: username.into(),
+            asset: asset.map(|it| it.into()),
+            asset_type,
+            weight,
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::model::{Id, Transaction};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct TransactionRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, account_id, type, asset, quantity, price, currency, fee, time, lot_id, external_id, transfer_id, new_asset, description, category_id, tags";
+
+impl TransactionRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> TransactionRepository {
+        TransactionRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Transaction) -> Result<()> {
+        insert(&self.pool.get().unwrap(), row)
+    }
+
+    /// Inserts all the rows or none of them
+    pub fn insert_all(&self, rows: &[Transaction]) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+
+        for row in rows {
+            insert(&tx, row)?;
+        }
+
+        tx.commit().map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Transaction) -> Result<()> {
+        update(&self.pool.get().unwrap(), row)
+    }
+
+    /// Updates all the rows or none of them
+    pub fn update_all(&self, rows: &[Transaction]) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+
+        for row in rows {
+            update(&tx, row)?;
+        }
+
+        tx.commit().map_err(Error::new)
+    }
+
+    /// Deletes all the rows or none of them
+    pub fn delete_all(&self, ids: &[Id]) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let tx = conn.transaction()?;
+
+        for id in ids {
+            tx.execute(r#"DELETE FROM "transaction" WHERE id = ?"#, params![id])?;
+        }
+
+        tx.commit().map_err(Error::new)
+    }
+
+    pub fn delete_by_account_id(&self, account_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                r#"DELETE FROM "transaction" WHERE account_id = ?"#,
+                params![account_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Leaves the transactions of a deleted category uncategorized
+    pub fn clear_category_id(&self, category_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                r#"UPDATE "transaction" SET category_id = NULL WHERE category_id = ?"#,
+                params![category_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Oldest transactions come first
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        let query = format!(
+            r#"SELECT {} FROM "transaction" WHERE account_id = ? ORDER BY time, rowid"#,
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![account_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    /// Transactions of all the user's accounts, oldest come first
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Transaction>> {
+        let query = format!(
+            r#"SELECT {} FROM "transaction" WHERE account_id IN (SELECT id FROM account WHERE username = ?) ORDER BY time, rowid"#,
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    /// Both legs of a transfer, the outgoing one comes first
+    pub fn select_by_transfer_id(&self, transfer_id: &Id) -> Result<Vec<Transaction>> {
+        let query = format!(
+            r#"SELECT {} FROM "transaction" WHERE transfer_id = ? ORDER BY quantity"#,
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![transfer_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Transaction>> {
+        let query = format!(r#"SELECT {} FROM "transaction" WHERE id = ?"#, COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn insert(conn: &Connection, row: &Transaction) -> Result<()> {
+    let query = format!(
+        r#"INSERT INTO "transaction" ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        COLUMNS
+    );
+    let params = params![
+        &row.id,
+        &row.account_id,
+        &row.transaction_type,
+        &row.asset,
+        row.quantity,
+        row.price,
+        &row.currency,
+        row.fee,
+        &row.time,
+        &row.lot_id,
+        &row.external_id,
+        &row.transfer_id,
+        &row.new_asset,
+        &row.description,
+        &row.category_id,
+        serde_json::to_string(&row.tags)?,
+    ];
+    conn.execute(&query, params).map(|_| ()).map_err(Error::new)
+}
+
+fn update(conn: &Connection, row: &Transaction) -> Result<()> {
+    let query = r#"UPDATE "transaction" SET type = ?, asset = ?, quantity = ?, price = ?, currency = ?, fee = ?, time = ?, lot_id = ?, external_id = ?, new_asset = ?, description = ?, category_id = ?, tags = ? WHERE id = ?"#;
+    let params = params![
+        &row.transaction_type,
+        &row.asset,
+        row.quantity,
+        row.price,
+        &row.currency,
+        row.fee,
+        &row.time,
+        &row.lot_id,
+        &row.external_id,
+        &row.new_asset,
+        &row.description,
+        &row.category_id,
+        serde_json::to_string(&row.tags)?,
+        &row.id,
+    ];
+    conn.execute(query, params).map(|_| ()).map_err(Error::new)
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Transaction> {
+    let tags: String = row.get(15)?;
+
+    Ok(Transaction {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        transaction_type: row.get(2)?,
+        asset: row.get(3)?,
+        quantity: row.get(4)?,
+        price: row.get(5)?,
+        currency: row.get(6)?,
+        fee: row.get(7)?,
+        time: row.get(8)?,
+        lot_id: row.get(9)?,
+        external_id: row.get(10)?,
+        new_asset: row.get(12)?,
+        transfer_id: row.get(11)?,
+        description: row.get(13)?,
+        category_id: row.get(14)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(15, Type::Text, e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Account, AccountType, CostBasisMethod, Id, Transaction, TransactionType},
+        repository::{AccountRepository, TransactionRepository},
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        repo.insert(&transaction(&Id::new()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn insert_all() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let account_id = Id::new();
+        let first = transaction(&account_id);
+        let second = transaction(&account_id);
+        assert!(repo
+            .insert_all(&[first.clone(), second.clone(), first.clone()])
+            .is_err());
+        assert!(repo.select_by_account_id(&account_id)?.is_empty());
+        repo.insert_all(&[first, second])?;
+        assert_eq!(2, repo.select_by_account_id(&account_id)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let mut row = transaction(&Id::new());
+        repo.insert(&row)?;
+        row.transaction_type = TransactionType::Sell;
+        row.quantity = 5.0;
+        row.category_id = Some(Id::new());
+        row.tags = vec!["holiday".into()];
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn clear_category_id() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let category_id = Id::new();
+        let row = Transaction {
+            category_id: Some(category_id.clone()),
+            ..transaction(&Id::new())
+        };
+        repo.insert(&row)?;
+        repo.clear_category_id(&category_id)?;
+        assert_eq!(None, repo.select_by_id(&row.id)?.unwrap().category_id);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_all() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let row = transaction(&Id::new());
+        let other = transaction(&Id::new());
+        repo.insert(&row)?;
+        repo.insert(&other)?;
+        repo.delete_all(std::slice::from_ref(&row.id))?;
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        assert!(repo.select_by_id(&other.id)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_account_id() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let row = transaction(&Id::new());
+        repo.insert(&row)?;
+        repo.delete_by_account_id(&row.account_id)?;
+        assert!(repo.select_by_account_id(&row.account_id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_account_id() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let account_id = Id::new();
+        let later = Transaction {
+            time: Utc.ymd(2021, 8, 2).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+            ..transaction(&account_id)
+        };
+        let earlier = transaction(&account_id);
+        repo.insert(&later)?;
+        repo.insert(&earlier)?;
+        repo.insert(&transaction(&Id::new()))?;
+        assert_eq!(
+            vec![earlier, later],
+            repo.select_by_account_id(&account_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let pool = pool();
+        let repo = TransactionRepository::new(&pool);
+        let account_repo = AccountRepository::new(&pool);
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        account_repo.insert(&account)?;
+        let row = transaction(&account.id);
+        repo.insert(&row)?;
+        repo.insert(&transaction(&Id::new()))?;
+        assert_eq!(vec![row], repo.select_by_username("test")?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_transfer_id() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let transfer_id = Id::new();
+        let incoming = Transaction {
+            transaction_type: TransactionType::Transfer,
+            transfer_id: Some(transfer_id.clone()),
+            description: None,
+            category_id: None,
+            tags: vec![],
+            ..transaction(&Id::new())
+        };
+        let outgoing = Transaction {
+            id: Id::new(),
+            quantity: -10.0,
+            ..incoming.clone()
+        };
+        repo.insert(&incoming)?;
+        repo.insert(&outgoing)?;
+        repo.insert(&transaction(&Id::new()))?;
+        assert_eq!(
+            vec![outgoing, incoming],
+            repo.select_by_transfer_id(&transfer_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_id() -> Result<()> {
+        let repo = TransactionRepository::new(&pool());
+        let row = transaction(&Id::new());
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        repo.insert(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    fn transaction(account_id: &Id) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Buy,
+            asset: Some("TST".into()),
+            quantity: 10.0,
+            price: Some(100.0),
+            currency: "USD".into(),
+            fee: 1.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+}
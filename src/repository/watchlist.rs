@@ -0,0 +1,135 @@
+use crate::model::{Id, Watchlist};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct WatchlistRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str = "id, username, name, assets";
+
+impl WatchlistRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> WatchlistRepository {
+        WatchlistRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Watchlist) -> Result<()> {
+        let query = format!("INSERT INTO watchlist ({}) VALUES (?, ?, ?, ?)", COLUMNS);
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.name,
+            serde_json::to_string(&row.assets)?,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Watchlist) -> Result<()> {
+        let query = "UPDATE watchlist SET name = ?, assets = ? WHERE id = ?";
+        let params = params![&row.name, serde_json::to_string(&row.assets)?, &row.id];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM watchlist WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Watchlist>> {
+        let query = format!(
+            "SELECT {} FROM watchlist WHERE username = ? ORDER BY rowid",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Watchlist>> {
+        let query = format!("SELECT {} FROM watchlist WHERE id = ?", COLUMNS);
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(&query, params![id], mapper)
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Watchlist> {
+    let assets: String = row.get(3)?;
+
+    Ok(Watchlist {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        name: row.get(2)?,
+        assets: serde_json::from_str(&assets)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, e.into()))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, Watchlist},
+        repository::WatchlistRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = WatchlistRepository::new(&pool());
+        repo.insert(&watchlist("test"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = WatchlistRepository::new(&pool());
+        let mut row = watchlist("test");
+        repo.insert(&row)?;
+        row.name = "Coins".into();
+        row.assets = vec!["USD".into(), "BTC".into()];
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = WatchlistRepository::new(&pool());
+        let row = watchlist("test");
+        repo.insert(&row)?;
+        repo.insert(&watchlist("test2"))?;
+        assert_eq!(vec![row], repo.select_by_username("test")?);
+        Ok(())
+    }
+
+    fn watchlist(username: &str) -> Watchlist {
+        Watchlist {
+            id: Id::new(),
+            username: username.into(),
+            name: "Crypto".into(),
+            assets: vec!["BTC".into()],
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::model::{Id, Webhook, WebhookEvent};
+use anyhow::{Error, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension, Row};
+
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> WebhookRepository {
+        WebhookRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &Webhook) -> Result<()> {
+        let query =
+            "INSERT INTO webhook (id, username, url, secret, events) VALUES (?, ?, ?, ?, ?)";
+        let params = params![
+            &row.id,
+            &row.username,
+            &row.url,
+            &row.secret,
+            join_events(&row.events),
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn update(&self, row: &Webhook) -> Result<()> {
+        let query = "UPDATE webhook SET url = ?, secret = ?, events = ? WHERE id = ?";
+        let params = params![&row.url, &row.secret, join_events(&row.events), &row.id];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute("DELETE FROM webhook WHERE id = ?", params![id])
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_all(&self) -> Result<Vec<Webhook>> {
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare("SELECT id, username, url, secret, events FROM webhook")?;
+        let rows = stmt.query_map([], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Webhook>> {
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT id, username, url, secret, events FROM webhook WHERE username = ?")?;
+        let rows = stmt.query_map(params![username], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Webhook>> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT id, username, url, secret, events FROM webhook WHERE id = ?",
+                params![id],
+                mapper,
+            )
+            .optional()
+            .map_err(Error::new)
+    }
+}
+
+fn join_events(events: &[WebhookEvent]) -> String {
+    events
+        .iter()
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<Webhook> {
+    let events: String = row.get(4)?;
+    let events = events
+        .split(',')
+        .filter(|it| !it.is_empty())
+        .map(|it| it.parse())
+        .collect::<Result<_, String>>()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, e.into())
+        })?;
+
+    Ok(Webhook {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        url: row.get(2)?,
+        secret: row.get(3)?,
+        events,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, Webhook, WebhookEvent},
+        repository::WebhookRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        repo.insert(&webhook())?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        let mut row = webhook();
+        repo.insert(&row)?;
+        row.url = "https://example.com/hook2".into();
+        row.events = vec![WebhookEvent::SyncFailed];
+        repo.update(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        let row = webhook();
+        repo.insert(&row)?;
+        repo.delete(&row.id)?;
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn select_all() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        assert!(repo.select_all()?.is_empty());
+        let row = webhook();
+        repo.insert(&row)?;
+        assert_eq!(vec![row], repo.select_all()?);
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_username() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        let row = webhook();
+        repo.insert(&row)?;
+        assert_eq!(vec![row.clone()], repo.select_by_username(&row.username)?);
+        assert!(repo.select_by_username("test2")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_id() -> Result<()> {
+        let repo = WebhookRepository::new(&pool());
+        let row = webhook();
+        assert!(repo.select_by_id(&row.id)?.is_none());
+        repo.insert(&row)?;
+        assert_eq!(Some(row.clone()), repo.select_by_id(&row.id)?);
+        Ok(())
+    }
+
+    fn webhook() -> Webhook {
+        Webhook {
+            id: Id::new(),
+            username: "test".into(),
+            url: "https://example.com/hook".into(),
+            secret: "secret".into(),
+            events: vec![WebhookEvent::RateUpdated, WebhookEvent::AlertFired],
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::model::{Id, WebhookDelivery};
+use anyhow::{Error, Result};
+use chrono::{DateTime, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Row};
+
+#[derive(Clone)]
+pub struct WebhookDeliveryRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+const COLUMNS: &str =
+    "id, webhook_id, event, payload, status, attempts, next_attempt_at, last_error, created_at";
+
+impl WebhookDeliveryRepository {
+    pub fn new(pool: &Pool<SqliteConnectionManager>) -> WebhookDeliveryRepository {
+        WebhookDeliveryRepository { pool: pool.clone() }
+    }
+
+    pub fn insert(&self, row: &WebhookDelivery) -> Result<()> {
+        let query = format!(
+            "INSERT INTO webhook_delivery ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            COLUMNS
+        );
+        let params = params![
+            &row.id,
+            &row.webhook_id,
+            &row.event,
+            &row.payload,
+            &row.status,
+            row.attempts,
+            &row.next_attempt_at,
+            &row.last_error,
+            &row.created_at,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(&query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    /// Saves the outcome of a delivery attempt
+    pub fn update(&self, row: &WebhookDelivery) -> Result<()> {
+        let query = "UPDATE webhook_delivery SET status = ?, attempts = ?, next_attempt_at = ?, last_error = ? WHERE id = ?";
+        let params = params![
+            &row.status,
+            row.attempts,
+            &row.next_attempt_at,
+            &row.last_error,
+            &row.id,
+        ];
+        self.pool
+            .get()
+            .unwrap()
+            .execute(query, params)
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn delete_by_webhook_id(&self, webhook_id: &Id) -> Result<()> {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "DELETE FROM webhook_delivery WHERE webhook_id = ?",
+                params![webhook_id],
+            )
+            .map(|_| ())
+            .map_err(Error::new)
+    }
+
+    pub fn select_by_webhook_id(&self, webhook_id: &Id) -> Result<Vec<WebhookDelivery>> {
+        let query = format!(
+            "SELECT {} FROM webhook_delivery WHERE webhook_id = ? ORDER BY created_at DESC",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![webhook_id], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+
+    /// Returns pending deliveries which should be attempted at or before a given time
+    pub fn select_due(&self, now: &DateTime<Utc>) -> Result<Vec<WebhookDelivery>> {
+        let query = format!(
+            "SELECT {} FROM webhook_delivery WHERE status = 'pending' AND next_attempt_at <= ? ORDER BY next_attempt_at",
+            COLUMNS
+        );
+        let conn = self.pool.get().unwrap();
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![now], mapper)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Error::new)
+    }
+}
+
+fn mapper(row: &Row) -> rusqlite::Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        id: row.get(0)?,
+        webhook_id: row.get(1)?,
+        event: row.get(2)?,
+        payload: row.get(3)?,
+        status: row.get(4)?,
+        attempts: row.get(5)?,
+        next_attempt_at: row.get(6)?,
+        last_error: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{DeliveryStatus, Id, WebhookDelivery, WebhookEvent},
+        repository::WebhookDeliveryRepository,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn insert() -> Result<()> {
+        let repo = WebhookDeliveryRepository::new(&pool());
+        repo.insert(&delivery(&Id::new()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn update() -> Result<()> {
+        let repo = WebhookDeliveryRepository::new(&pool());
+        let mut row = delivery(&Id::new());
+        repo.insert(&row)?;
+        row.status = DeliveryStatus::Failed;
+        row.attempts = 8;
+        row.last_error = Some("Timeout".into());
+        repo.update(&row)?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_webhook_id(&row.webhook_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_webhook_id() -> Result<()> {
+        let repo = WebhookDeliveryRepository::new(&pool());
+        let row = delivery(&Id::new());
+        repo.insert(&row)?;
+        repo.delete_by_webhook_id(&row.webhook_id)?;
+        assert!(repo.select_by_webhook_id(&row.webhook_id)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_webhook_id() -> Result<()> {
+        let repo = WebhookDeliveryRepository::new(&pool());
+        let row = delivery(&Id::new());
+        assert!(repo.select_by_webhook_id(&row.webhook_id)?.is_empty());
+        repo.insert(&row)?;
+        repo.insert(&delivery(&Id::new()))?;
+        assert_eq!(
+            vec![row.clone()],
+            repo.select_by_webhook_id(&row.webhook_id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn select_due() -> Result<()> {
+        let repo = WebhookDeliveryRepository::new(&pool());
+        let row = delivery(&Id::new());
+        repo.insert(&row)?;
+        let before = row.next_attempt_at - Duration::seconds(1);
+        assert!(repo.select_due(&before)?.is_empty());
+        assert_eq!(vec![row.clone()], repo.select_due(&row.next_attempt_at)?);
+        let delivered = WebhookDelivery {
+            status: DeliveryStatus::Delivered,
+            ..row.clone()
+        };
+        repo.update(&delivered)?;
+        assert!(repo.select_due(&row.next_attempt_at)?.is_empty());
+        Ok(())
+    }
+
+    fn delivery(webhook_id: &Id) -> WebhookDelivery {
+        let created_at = Utc.ymd(2021, 8, 1).and_hms(12, 0, 0);
+        WebhookDelivery {
+            id: Id::new(),
+            webhook_id: webhook_id.clone(),
+            event: WebhookEvent::RateUpdated,
+            payload: "{}".into(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: created_at,
+            last_error: None,
+            created_at,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    model::{Account, Id},
+    repository::{
+        AccountRepository, GoalRepository, PortfolioSnapshotRepository, TransactionRepository,
+    },
+    service::{AssetService, LotService, RecurringService},
+};
+use anyhow::{ensure, Result};
+
+#[derive(Clone)]
+pub struct AccountService {
+    repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    snapshot_repo: PortfolioSnapshotRepository,
+    asset_service: AssetService,
+    lot_service: LotService,
+    recurring_service: RecurringService,
+    goal_repo: GoalRepository,
+}
+
+impl AccountService {
+    pub fn new(
+        repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        snapshot_repo: &PortfolioSnapshotRepository,
+        asset_service: &AssetService,
+        lot_service: &LotService,
+        recurring_service: &RecurringService,
+        goal_repo: &GoalRepository,
+    ) -> AccountService {
+        AccountService {
+            repo: repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            snapshot_repo: snapshot_repo.clone(),
+            asset_service: asset_service.clone(),
+            lot_service: lot_service.clone(),
+            recurring_service: recurring_service.clone(),
+            goal_repo: goal_repo.clone(),
+        }
+    }
+
+    pub fn insert(&self, account: &Account) -> Result<()> {
+        self.repo.insert(account)
+    }
+
+    /// Lots get rebuilt since the cost basis method might have changed
+    pub fn update(&self, account: &Account) -> Result<()> {
+        self.repo.update(account)?;
+        self.lot_service.rebuild(&account.id)
+    }
+
+    /// Goals stop tracking the account but are kept. Transfers with other accounts are
+    /// deleted from both sides, and the lots of those accounts are rebuilt.
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        if let Some(account) = self.repo.select_by_id(id)? {
+            for mut goal in self.goal_repo.select_by_username(&account.username)? {
+                if goal.account_ids.contains(id) {
+                    goal.account_ids.retain(|it| it != id);
+                    self.goal_repo.update(&goal)?;
+                }
+            }
+        }
+
+        let transactions = self.transaction_repo.select_by_account_id(id)?;
+
+        if let Some(first) = transactions.first() {
+            self.snapshot_repo
+                .delete_by_account_id_since(id, &first.time.date().naive_utc())?;
+        }
+
+        let mut counterparts = vec![];
+
+        for transfer_id in transactions.iter().filter_map(|it| it.transfer_id.as_ref()) {
+            for leg in self.transaction_repo.select_by_transfer_id(transfer_id)? {
+                if &leg.account_id != id {
+                    counterparts.push(leg);
+                }
+            }
+        }
+
+        let counterpart_ids: Vec<Id> = counterparts.iter().map(|it| it.id.clone()).collect();
+        self.transaction_repo.delete_all(&counterpart_ids)?;
+
+        for leg in &counterparts {
+            self.snapshot_repo
+                .delete_by_account_id_since(&leg.account_id, &leg.time.date().naive_utc())?;
+        }
+
+        self.lot_service.delete_by_account_id(id)?;
+        self.recurring_service.delete_by_account_id(id)?;
+        self.transaction_repo.delete_by_account_id(id)?;
+        self.repo.delete(id)?;
+
+        let account_ids: Vec<Id> = counterparts.into_iter().map(|it| it.account_id).collect();
+        self.lot_service.rebuild_all(&account_ids)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Account>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Same as `select_by_id` but treats other users' accounts as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Account>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, account: &Account) -> Result<()> {
+        ensure!(
+            !account.name.trim().is_empty(),
+            "Account name can't be empty"
+        );
+        ensure!(
+            self.asset_service.exists(&account.currency)?,
+            "Unknown asset: {}",
+            account.currency
+        );
+
+        if let Some(closed_at) = account.closed_at {
+            ensure!(
+                closed_at >= account.opened_at,
+                "Account can't be closed before it was opened"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    model::{Alert, AlertCondition, AlertEvent, ExchangeRate, Id, WebhookEvent},
+    notifier::Notifier,
+    provider::SyncListener,
+    repository::{AlertEventRepository, AlertRepository},
+    service::{ExchangeRateService, WebhookService},
+};
+use anyhow::{Error, Result};
+use chrono::{NaiveDate, Utc};
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// Notifiers which take longer are given up on, so they don't hold up other alerts
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct AlertService {
+    repo: AlertRepository,
+    event_repo: AlertEventRepository,
+    rate_service: ExchangeRateService,
+    webhook_service: WebhookService,
+    notifiers: Vec<Arc<dyn Notifier>>,
+}
+
+impl AlertService {
+    pub fn new(
+        repo: &AlertRepository,
+        event_repo: &AlertEventRepository,
+        rate_service: &ExchangeRateService,
+        webhook_service: &WebhookService,
+        notifiers: Vec<Arc<dyn Notifier>>,
+    ) -> AlertService {
+        AlertService {
+            repo: repo.clone(),
+            event_repo: event_repo.clone(),
+            rate_service: rate_service.clone(),
+            webhook_service: webhook_service.clone(),
+            notifiers,
+        }
+    }
+
+    pub fn insert(&self, alert: &Alert) -> Result<()> {
+        self.repo.insert(alert)
+    }
+
+    pub fn update(&self, alert: &Alert) -> Result<()> {
+        self.repo.update(alert)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.event_repo.delete_by_alert_id(id)?;
+        self.repo.delete(id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Alert>> {
+        self.repo.select_by_username(username)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Alert>> {
+        self.repo.select_by_id(id)
+    }
+
+    pub fn select_events(&self, alert_id: &Id) -> Result<Vec<AlertEvent>> {
+        self.event_repo.select_by_alert_id(alert_id)
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, alert: &Alert) -> Result<()> {
+        if alert.condition == AlertCondition::Change && alert.threshold <= 0.0 {
+            return Err(Error::msg("Change threshold must be a positive percentage"));
+        }
+
+        match self.notifier(&alert.notifier) {
+            Some(notifier) => notifier.validate_target(alert),
+            None => Err(Error::msg(format!("Unknown notifier: {}", alert.notifier))),
+        }
+    }
+
+    /// Checks all the alerts against the latest rates. An alert fires only when its
+    /// condition becomes true, it won't fire again until the condition resets. A failing
+    /// alert doesn't stop the others.
+    pub async fn evaluate(&self) -> Result<()> {
+        let yesterday = Utc::today().naive_utc().pred();
+
+        for alert in self.repo.select_all()? {
+            if let Err(e) = self.evaluate_alert(&alert, &yesterday).await {
+                warn!(alert = %alert.id, ?e, "Failed to evaluate alert");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn evaluate_alert(&self, alert: &Alert, yesterday: &NaiveDate) -> Result<()> {
+        let rate = match self
+            .rate_service
+            .get_by_quote_and_base(&alert.quote, &alert.base)?
+        {
+            Some(rate) => rate.rate,
+            None => return Ok(()),
+        };
+
+        let reference = match alert.condition {
+            AlertCondition::Change => self
+                .rate_service
+                .get_by_quote_and_base_and_date(&alert.quote, &alert.base, yesterday)?
+                .map(|it| it.rate),
+            _ => None,
+        };
+
+        let was_triggered = alert
+            .last_rate
+            .map(|it| alert.is_triggered(it, reference))
+            .unwrap_or(false);
+
+        self.repo.update_last_rate(&alert.id, rate)?;
+
+        if alert.is_triggered(rate, reference) && !was_triggered {
+            self.fire(alert, rate).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn fire(&self, alert: &Alert, rate: f64) -> Result<()> {
+        info!(alert = %alert.id, %rate, "Alert fired");
+
+        let mut event = AlertEvent {
+            id: Id::new(),
+            alert_id: alert.id.clone(),
+            rate,
+            fired_at: Utc::now(),
+            delivered: false,
+        };
+
+        self.event_repo.insert(&event)?;
+        self.webhook_service.enqueue(
+            Some(&alert.username),
+            WebhookEvent::AlertFired,
+            &json!({ "alert": alert, "event": event }),
+        )?;
+
+        let notifier = match self.notifier(&alert.notifier) {
+            Some(notifier) => notifier,
+            None => {
+                warn!(alert = %alert.id, notifier = %alert.notifier, "Unknown notifier");
+                return Ok(());
+            }
+        };
+
+        match timeout(NOTIFY_TIMEOUT, notifier.notify(alert, &event)).await {
+            Ok(Ok(_)) => {
+                event.delivered = true;
+                self.event_repo.update_delivered(&event.id, event.delivered)
+            }
+            Ok(Err(e)) => {
+                warn!(alert = %alert.id, ?e, "Failed to deliver alert");
+                Ok(())
+            }
+            Err(_) => {
+                warn!(alert = %alert.id, "Alert delivery timed out");
+                Ok(())
+            }
+        }
+    }
+
+    fn notifier(&self, name: &str) -> Option<&Arc<dyn Notifier>> {
+        self.notifiers.iter().find(|it| it.name() == name)
+    }
+}
+
+#[rocket::async_trait]
+impl SyncListener for AlertService {
+    async fn on_sync(&self, _provider: &str, _rates: &[ExchangeRate]) -> Result<()> {
+        self.evaluate().await
+    }
+
+    async fn on_sync_failed(&self, _provider: &str, _error: &Error) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Alert, AlertCondition, AlertEvent, ExchangeRate, Id},
+        notifier::Notifier,
+        repository::{
+            AlertEventRepository, AlertRepository, ExchangeRateRepository,
+            WebhookDeliveryRepository, WebhookRepository,
+        },
+        service::{
+            exchange_rate::RateCacheConf, webhook::WebhookConf, AlertService, ExchangeRateService,
+            WebhookService,
+        },
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::Utc;
+    use r2d2::Pool;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct FakeNotifier {
+        events: Mutex<Vec<AlertEvent>>,
+    }
+
+    #[rocket::async_trait]
+    impl Notifier for FakeNotifier {
+        fn name(&self) -> String {
+            "fake".into()
+        }
+
+        fn validate_target(&self, _alert: &Alert) -> Result<()> {
+            Ok(())
+        }
+
+        async fn notify(&self, _alert: &Alert, event: &AlertEvent) -> Result<()> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn evaluate_above() -> Result<()> {
+        let pool = pool();
+        let rate_repo = ExchangeRateRepository::new(&pool);
+        let notifier = Arc::new(FakeNotifier::default());
+        let service = service(&pool, &rate_repo, notifier.clone());
+        let alert = alert(AlertCondition::Above, 50000.0);
+        service.insert(&alert)?;
+
+        rate_repo.insert_or_replace(&rate(49000.0))?;
+        service.evaluate().await?;
+        assert!(notifier.events.lock().unwrap().is_empty());
+
+        rate_repo.insert_or_replace(&rate(51000.0))?;
+        service.evaluate().await?;
+        rate_repo.insert_or_replace(&rate(52000.0))?;
+        service.evaluate().await?;
+        assert_eq!(1, notifier.events.lock().unwrap().len());
+
+        let events = service.select_events(&alert.id)?;
+        assert_eq!(1, events.len());
+        assert_eq!(51000.0, events[0].rate);
+        assert!(events[0].delivered);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evaluate_change() -> Result<()> {
+        let pool = pool();
+        let rate_repo = ExchangeRateRepository::new(&pool);
+        let notifier = Arc::new(FakeNotifier::default());
+        let service = service(&pool, &rate_repo, notifier.clone());
+        service.insert(&alert(AlertCondition::Change, 5.0))?;
+
+        let yesterday = Utc::today().naive_utc().pred();
+        rate_repo.insert_or_replace_history(&rate(50000.0), &yesterday)?;
+
+        rate_repo.insert_or_replace(&rate(51000.0))?;
+        service.evaluate().await?;
+        assert!(notifier.events.lock().unwrap().is_empty());
+
+        rate_repo.insert_or_replace(&rate(47000.0))?;
+        service.evaluate().await?;
+        assert_eq!(1, notifier.events.lock().unwrap().len());
+        Ok(())
+    }
+
+    fn service(
+        pool: &Pool<SqliteConnectionManager>,
+        rate_repo: &ExchangeRateRepository,
+        notifier: Arc<FakeNotifier>,
+    ) -> AlertService {
+        let webhook_conf = WebhookConf {
+            max_attempts: 1,
+            retry_delay_secs: 60,
+            dispatch_interval_secs: 30,
+            allow_private_urls: false,
+        };
+        AlertService::new(
+            &AlertRepository::new(pool),
+            &AlertEventRepository::new(pool),
+            &ExchangeRateService::new(rate_repo, &RateCacheConf { ttl_secs: 60 }),
+            &WebhookService::new(
+                &WebhookRepository::new(pool),
+                &WebhookDeliveryRepository::new(pool),
+                &webhook_conf,
+            ),
+            vec![notifier],
+        )
+    }
+
+    fn alert(condition: AlertCondition, threshold: f64) -> Alert {
+        Alert {
+            id: Id::new(),
+            username: "test".into(),
+            quote: "BTC".into(),
+            base: "EUR".into(),
+            condition,
+            threshold,
+            notifier: "fake".into(),
+            target: "".into(),
+            last_rate: None,
+        }
+    }
+
+    fn rate(rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            quote: "BTC".into(),
+            base: "EUR".into(),
+            rate,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    model::{Allocation, AllocationBy, AllocationGroup, AssetType, Transaction},
+    repository::{AccountRepository, TransactionRepository},
+    service::{AssetService, HoldingService, ValuationService},
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Transactions valued together, with the account key and name when grouping by account
+type Ledger = (Option<(String, String)>, Vec<Transaction>);
+
+#[derive(Clone)]
+pub struct AllocationService {
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    asset_service: AssetService,
+    valuation_service: ValuationService,
+}
+
+impl AllocationService {
+    pub fn new(
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        asset_service: &AssetService,
+        valuation_service: &ValuationService,
+    ) -> AllocationService {
+        AllocationService {
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            asset_service: asset_service.clone(),
+            valuation_service: valuation_service.clone(),
+        }
+    }
+
+    pub fn report(
+        &self,
+        username: &str,
+        currency: &str,
+        by: AllocationBy,
+        date: Option<NaiveDate>,
+    ) -> Result<Allocation> {
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        let mut groups: BTreeMap<String, AllocationGroup> = BTreeMap::new();
+        let mut unpriced = vec![];
+        let mut rates = vec![];
+
+        // Accounts are valued one by one, the other groupings can share the holdings
+        let ledgers: Vec<Ledger> = match by {
+            AllocationBy::Account => self
+                .account_repo
+                .select_by_username(username)?
+                .into_iter()
+                .map(|account| {
+                    let ledger = transactions
+                        .iter()
+                        .filter(|it| it.account_id == account.id)
+                        .cloned()
+                        .collect();
+                    (Some((account.id.to_string(), account.name)), ledger)
+                })
+                .collect(),
+            _ => vec![(None, transactions.clone())],
+        };
+
+        for (account, ledger) in ledgers {
+            let holdings = HoldingService::replay(&ledger, date);
+            let value = self
+                .valuation_service
+                .value(&holdings, &transactions, currency, date)?;
+
+            for rate in value.rates {
+                if !rates.contains(&rate) {
+                    rates.push(rate);
+                }
+            }
+
+            for holding in value.holdings {
+                let holding_value = match holding.value {
+                    Some(value) => value,
+                    None => {
+                        if !unpriced.contains(&holding.asset) {
+                            unpriced.push(holding.asset);
+                        }
+
+                        continue;
+                    }
+                };
+
+                let (key, name) = match (&account, by) {
+                    (Some(account), _) => account.clone(),
+                    (None, AllocationBy::AssetType) => {
+                        let asset_type = self.asset_type(&holding.asset)?;
+                        (asset_type.clone(), asset_type)
+                    }
+                    (None, AllocationBy::Currency) => {
+                        let exposure = self.exposure(&holding.asset, &transactions)?;
+                        (exposure.clone(), self.name(&exposure)?)
+                    }
+                    (None, _) => (holding.asset.clone(), self.name(&holding.asset)?),
+                };
+
+                groups
+                    .entry(key.clone())
+                    .or_insert(AllocationGroup {
+                        key,
+                        name,
+                        value: 0.0,
+                        weight: 0.0,
+                    })
+                    .value += holding_value;
+            }
+        }
+
+        let total: f64 = groups.values().map(|it| it.value).sum();
+        let mut groups: Vec<AllocationGroup> = groups.into_values().collect();
+
+        for group in &mut groups {
+            if total != 0.0 {
+                group.weight = group.value / total;
+            }
+        }
+
+        groups.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+        Ok(Allocation {
+            currency: currency.into(),
+            date,
+            by,
+            total,
+            groups,
+            unpriced,
+            rates,
+        })
+    }
+
+    /// Currency which drives the value of an asset. Currencies and crypto assets are
+    /// exposed to themselves, anything else to its `currency` metadata or to the currency
+    /// it was last traded in.
+    pub fn exposure(&self, asset: &str, transactions: &[Transaction]) -> Result<String> {
+        let asset_info = self.asset_service.select_by_code(asset)?;
+
+        let asset_info = match asset_info {
+            Some(asset_info) => asset_info,
+            None => return Ok(asset.into()),
+        };
+
+        if matches!(asset_info.asset_type, AssetType::Fiat | AssetType::Crypto) {
+            return Ok(asset.into());
+        }
+
+        if let Some(currency) = asset_info.metadata.get("currency") {
+            return Ok(currency.clone());
+        }
+
+        Ok(transactions
+            .iter()
+            .rev()
+            .find(|it| it.asset.as_deref() == Some(asset) && it.price.is_some())
+            .map(|it| it.currency.clone())
+            .unwrap_or_else(|| asset.into()))
+    }
+
+    fn asset_type(&self, asset: &str) -> Result<String> {
+        Ok(self
+            .asset_service
+            .select_by_code(asset)?
+            .map(|it| it.asset_type.to_string())
+            .unwrap_or_else(|| "unknown".into()))
+    }
+
+    fn name(&self, asset: &str) -> Result<String> {
+        Ok(self
+            .asset_service
+            .select_by_code(asset)?
+            .map(|it| it.name)
+            .unwrap_or_else(|| asset.into()))
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{model::Asset, repository::AssetRepository};
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize)]
+pub struct AssetConf {
+    /// Users who may create, change and delete assets, which are shared by everyone
+    pub admins: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct AssetService {
+    repo: AssetRepository,
+    conf: AssetConf,
+}
+
+impl AssetService {
+    pub fn new(repo: &AssetRepository, conf: &AssetConf) -> AssetService {
+        AssetService {
+            repo: repo.clone(),
+            conf: conf.clone(),
+        }
+    }
+
+    pub fn is_admin(&self, username: &str) -> bool {
+        self.conf.admins.iter().any(|it| it == username)
+    }
+
+    pub fn insert(&self, asset: &Asset) -> Result<()> {
+        self.repo.insert(asset)
+    }
+
+    pub fn update(&self, asset: &Asset) -> Result<()> {
+        self.repo.update(asset)
+    }
+
+    pub fn delete(&self, code: &str) -> Result<()> {
+        self.repo.delete(code)
+    }
+
+    pub fn select_all(&self) -> Result<Vec<Asset>> {
+        self.repo.select_all()
+    }
+
+    pub fn select_by_code(&self, code: &str) -> Result<Option<Asset>> {
+        self.repo.select_by_code(code)
+    }
+
+    pub fn exists(&self, code: &str) -> Result<bool> {
+        Ok(self.repo.select_by_code(code)?.is_some())
+    }
+
+    pub fn is_referenced(&self, code: &str) -> Result<bool> {
+        self.repo.is_referenced(code)
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, asset: &Asset) -> Result<()> {
+        ensure!(
+            !asset.code.is_empty() && asset.code.len() <= 16,
+            "Asset code should be 1 to 16 characters long"
+        );
+        ensure!(
+            asset
+                .code
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '.' || c == '-'),
+            "Asset code may only contain uppercase letters, digits, dots and dashes"
+        );
+        ensure!(!asset.name.trim().is_empty(), "Asset name can't be empty");
+        ensure!(asset.precision <= 18, "Asset precision can't exceed 18");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        repository::AssetRepository,
+        service::{asset::AssetConf, AssetService},
+        test::pool,
+    };
+
+    #[test]
+    fn is_admin() {
+        let service = AssetService::new(
+            &AssetRepository::new(&pool()),
+            &AssetConf {
+                admins: vec!["admin".into()],
+            },
+        );
+        assert!(service.is_admin("admin"));
+        assert!(!service.is_admin("test"));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    model::{Budget, BudgetPeriodProgress, BudgetProgress, Category, Id, Rollover},
+    repository::{BudgetRepository, CategoryRepository, TransactionRepository},
+    service::{AssetService, CashflowService, ExchangeRateService},
+};
+use anyhow::{ensure, Result};
+use chrono::NaiveDate;
+
+#[derive(Clone)]
+pub struct BudgetService {
+    repo: BudgetRepository,
+    category_repo: CategoryRepository,
+    transaction_repo: TransactionRepository,
+    asset_service: AssetService,
+    rate_service: ExchangeRateService,
+}
+
+impl BudgetService {
+    pub fn new(
+        repo: &BudgetRepository,
+        category_repo: &CategoryRepository,
+        transaction_repo: &TransactionRepository,
+        asset_service: &AssetService,
+        rate_service: &ExchangeRateService,
+    ) -> BudgetService {
+        BudgetService {
+            repo: repo.clone(),
+            category_repo: category_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            asset_service: asset_service.clone(),
+            rate_service: rate_service.clone(),
+        }
+    }
+
+    pub fn insert(&self, budget: &Budget) -> Result<()> {
+        self.repo.insert(budget)
+    }
+
+    pub fn update(&self, budget: &Budget) -> Result<()> {
+        self.repo.update(budget)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.repo.delete(id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Budget>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Other users' budgets are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Budget>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, budget: &Budget) -> Result<()> {
+        ensure!(budget.amount > 0.0, "Budget amount must be positive");
+        ensure!(
+            self.asset_service.exists(&budget.currency)?,
+            "Unknown asset: {}",
+            budget.currency
+        );
+        ensure!(
+            self.category_repo
+                .select_by_id(&budget.category_id)?
+                .filter(|it| it.username == budget.username)
+                .is_some(),
+            "Unknown category: {}",
+            budget.category_id
+        );
+        ensure!(
+            budget.start == budget.period.start(&budget.start),
+            "Budget must start on the first day of a period"
+        );
+        Ok(())
+    }
+
+    /// Every period from the start of the budget up to the one which contains a given day.
+    /// Spending in subcategories counts towards the budget, amounts in other currencies
+    /// are converted with the rates known on the day of each transaction.
+    pub fn progress(&self, budget: &Budget, to: &NaiveDate) -> Result<BudgetProgress> {
+        let categories = self.category_repo.select_by_username(&budget.username)?;
+        let category_ids = Self::subtree(&categories, &budget.category_id);
+        let mut spending = vec![];
+        let mut unconverted = vec![];
+
+        for transaction in self.transaction_repo.select_by_username(&budget.username)? {
+            let date = transaction.time.date().naive_utc();
+
+            if date < budget.start
+                || date > *to
+                || !CashflowService::is_cashflow(&transaction)
+                || !transaction
+                    .category_id
+                    .as_ref()
+                    .map(|it| category_ids.contains(it))
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let rate = if transaction.currency == budget.currency {
+                Some(1.0)
+            } else {
+                self.rate_service
+                    .get_by_quote_and_base_and_date(&transaction.currency, &budget.currency, &date)?
+                    .map(|it| it.rate)
+            };
+
+            match rate {
+                Some(rate) => spending.push((date, -transaction.cash_amount() * rate)),
+                None if !unconverted.contains(&transaction.currency) => {
+                    unconverted.push(transaction.currency.clone())
+                }
+                None => {}
+            }
+        }
+
+        let mut periods = vec![];
+        let mut start = budget.start;
+        let mut carried = 0.0;
+
+        while start <= *to {
+            let next = budget.period.next(&start);
+            let spent: f64 = spending
+                .iter()
+                .filter(|(date, _)| *date >= start && *date < next)
+                .map(|(_, amount)| amount)
+                .sum();
+            let available = budget.amount + carried;
+            let remaining = available - spent;
+
+            periods.push(BudgetPeriodProgress {
+                period: budget.period.label(&start),
+                from: start,
+                to: next.pred(),
+                carried,
+                available,
+                spent,
+                remaining,
+                used: Some(spent / available).filter(|_| available > 0.0),
+            });
+
+            carried = match budget.rollover {
+                Rollover::None => 0.0,
+                Rollover::Unspent => remaining.max(0.0),
+                Rollover::All => remaining,
+            };
+            start = next;
+        }
+
+        Ok(BudgetProgress {
+            budget_id: budget.id.clone(),
+            currency: budget.currency.clone(),
+            periods,
+            unconverted,
+        })
+    }
+
+    /// A category along with all of its subcategories
+    fn subtree(categories: &[Category], id: &Id) -> Vec<Id> {
+        let mut ids = vec![id.clone()];
+        let mut i = 0;
+
+        while i < ids.len() {
+            for category in categories {
+                if category.parent_id.as_ref() == Some(&ids[i]) && !ids.contains(&category.id) {
+                    ids.push(category.id.clone());
+                }
+            }
+
+            i += 1;
+        }
+
+        ids
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    model::{
+        Cashflow, CashflowCategory, CashflowGroup, CashflowPeriod, Category, Transaction,
+        TransactionType,
+    },
+    repository::{CategoryRepository, TransactionRepository},
+    service::ExchangeRateService,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Uncategorized transactions and those of unknown categories go by this name
+const UNCATEGORIZED: &str = "Uncategorized";
+
+#[derive(Clone)]
+pub struct CashflowService {
+    transaction_repo: TransactionRepository,
+    category_repo: CategoryRepository,
+    rate_service: ExchangeRateService,
+}
+
+impl CashflowService {
+    pub fn new(
+        transaction_repo: &TransactionRepository,
+        category_repo: &CategoryRepository,
+        rate_service: &ExchangeRateService,
+    ) -> CashflowService {
+        CashflowService {
+            transaction_repo: transaction_repo.clone(),
+            category_repo: category_repo.clone(),
+            rate_service: rate_service.clone(),
+        }
+    }
+
+    /// Income and expenses of a user between two days, both included. Trades, transfers
+    /// between the user's accounts and corporate actions are neither.
+    pub fn report(
+        &self,
+        username: &str,
+        currency: &str,
+        group: CashflowGroup,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        tag: Option<&str>,
+    ) -> Result<Cashflow> {
+        let categories = self.category_repo.select_by_username(username)?;
+        let mut periods: BTreeMap<String, BTreeMap<(bool, String), CashflowCategory>> =
+            BTreeMap::new();
+        let mut unconverted = vec![];
+
+        for transaction in self.transaction_repo.select_by_username(username)? {
+            let date = transaction.time.date().naive_utc();
+
+            if !Self::is_cashflow(&transaction)
+                || from.map(|it| date < it).unwrap_or(false)
+                || to.map(|it| date > it).unwrap_or(false)
+                || tag
+                    .map(|it| !transaction.tags.iter().any(|tag| tag == it))
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let rate = if transaction.currency == currency {
+                Some(1.0)
+            } else {
+                self.rate_service
+                    .get_by_quote_and_base_and_date(&transaction.currency, currency, &date)?
+                    .map(|it| it.rate)
+            };
+
+            let amount = match rate {
+                Some(rate) => transaction.cash_amount() * rate,
+                None => {
+                    if !unconverted.contains(&transaction.currency) {
+                        unconverted.push(transaction.currency.clone());
+                    }
+
+                    continue;
+                }
+            };
+
+            let category = transaction
+                .category_id
+                .as_ref()
+                .and_then(|id| categories.iter().find(|it| &it.id == id));
+            let name = category
+                .map(|it| it.path(&categories))
+                .unwrap_or_else(|| UNCATEGORIZED.into());
+
+            let entry = periods
+                .entry(group.period(&date))
+                .or_default()
+                .entry((category.is_none(), Self::key(category, &name)))
+                .or_insert(CashflowCategory {
+                    category_id: category.map(|it| it.id.clone()),
+                    name,
+                    income: 0.0,
+                    expense: 0.0,
+                });
+
+            if amount > 0.0 {
+                entry.income += amount;
+            } else {
+                entry.expense -= amount;
+            }
+        }
+
+        let periods: Vec<CashflowPeriod> = periods
+            .into_iter()
+            .map(|(period, categories)| {
+                let categories: Vec<CashflowCategory> = categories.into_values().collect();
+                let income: f64 = categories.iter().map(|it| it.income).sum();
+                let expense: f64 = categories.iter().map(|it| it.expense).sum();
+
+                CashflowPeriod {
+                    period,
+                    income,
+                    expense,
+                    net: income - expense,
+                    categories,
+                }
+            })
+            .collect();
+
+        Ok(Cashflow {
+            currency: currency.into(),
+            group,
+            from,
+            to,
+            tag: tag.map(|it| it.to_string()),
+            income: periods.iter().map(|it| it.income).sum(),
+            expense: periods.iter().map(|it| it.expense).sum(),
+            periods,
+            unconverted,
+        })
+    }
+
+    /// Deposits, withdrawals, fees, interest, dividends and taxes which aren't part of a
+    /// transfer
+    pub fn is_cashflow(transaction: &Transaction) -> bool {
+        transaction.transfer_id.is_none()
+            && matches!(
+                transaction.transaction_type,
+                TransactionType::Deposit
+                    | TransactionType::Withdrawal
+                    | TransactionType::Fee
+                    | TransactionType::Interest
+                    | TransactionType::Dividend
+                    | TransactionType::Tax
+            )
+    }
+
+    /// Sorts by name, categories which share a path are still kept apart
+    fn key(category: Option<&Category>, name: &str) -> String {
+        match category {
+            Some(category) => format!("{}|{}", name, category.id),
+            None => name.into(),
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    model::{Category, CategoryRule, Id, Transaction},
+    repository::{
+        BudgetRepository, CategoryRepository, CategoryRuleRepository,
+        RecurringTransactionRepository, TransactionRepository,
+    },
+};
+use anyhow::{ensure, Result};
+use regex::{Regex, RegexBuilder};
+
+#[derive(Clone)]
+pub struct CategoryService {
+    repo: CategoryRepository,
+    rule_repo: CategoryRuleRepository,
+    transaction_repo: TransactionRepository,
+    budget_repo: BudgetRepository,
+    recurring_repo: RecurringTransactionRepository,
+}
+
+impl CategoryService {
+    pub fn new(
+        repo: &CategoryRepository,
+        rule_repo: &CategoryRuleRepository,
+        transaction_repo: &TransactionRepository,
+        budget_repo: &BudgetRepository,
+        recurring_repo: &RecurringTransactionRepository,
+    ) -> CategoryService {
+        CategoryService {
+            repo: repo.clone(),
+            rule_repo: rule_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            budget_repo: budget_repo.clone(),
+            recurring_repo: recurring_repo.clone(),
+        }
+    }
+
+    pub fn insert(&self, category: &Category) -> Result<()> {
+        self.repo.insert(category)
+    }
+
+    pub fn update(&self, category: &Category) -> Result<()> {
+        self.repo.update(category)
+    }
+
+    /// Subcategories move up to the parent of the deleted category, its transactions and
+    /// recurring transactions become uncategorized and its rules and budgets are deleted
+    pub fn delete(&self, category: &Category) -> Result<()> {
+        self.repo
+            .update_parent_id(&category.id, category.parent_id.as_ref())?;
+        self.rule_repo.delete_by_category_id(&category.id)?;
+        self.budget_repo.delete_by_category_id(&category.id)?;
+        self.transaction_repo.clear_category_id(&category.id)?;
+        self.recurring_repo.clear_category_id(&category.id)?;
+        self.repo.delete(&category.id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Category>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Other users' categories are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Category>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, category: &Category) -> Result<()> {
+        ensure!(
+            !category.name.trim().is_empty(),
+            "Category name can't be empty"
+        );
+
+        let mut parent_id = category.parent_id.clone();
+
+        while let Some(id) = parent_id {
+            ensure!(id != category.id, "Category can't be its own parent");
+
+            let parent = self.select_owned(&id, &category.username)?;
+            ensure!(parent.is_some(), "Unknown parent category: {}", id);
+            parent_id = parent.and_then(|it| it.parent_id);
+        }
+
+        Ok(())
+    }
+
+    pub fn insert_rule(&self, rule: &CategoryRule) -> Result<()> {
+        self.rule_repo.insert(rule)
+    }
+
+    pub fn update_rule(&self, rule: &CategoryRule) -> Result<()> {
+        self.rule_repo.update(rule)
+    }
+
+    pub fn delete_rule(&self, id: &Id) -> Result<()> {
+        self.rule_repo.delete(id)
+    }
+
+    pub fn select_rules(&self, username: &str) -> Result<Vec<CategoryRule>> {
+        self.rule_repo.select_by_username(username)
+    }
+
+    /// Other users' rules are treated as non-existent
+    pub fn select_owned_rule(&self, id: &Id, username: &str) -> Result<Option<CategoryRule>> {
+        Ok(self
+            .rule_repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate_rule(&self, rule: &CategoryRule) -> Result<()> {
+        ensure!(!rule.pattern.is_empty(), "Pattern can't be empty");
+
+        if let Err(e) = Self::regex(&rule.pattern) {
+            return Err(anyhow::Error::msg(format!("Invalid pattern: {}", e)));
+        }
+
+        ensure!(
+            self.select_owned(&rule.category_id, &rule.username)?
+                .is_some(),
+            "Unknown category: {}",
+            rule.category_id
+        );
+        ensure!(
+            rule.tags.iter().all(|it| !it.trim().is_empty()),
+            "Tags can't be empty"
+        );
+        Ok(())
+    }
+
+    /// Applies the first matching rule of a user to each transaction which has a
+    /// description and no category yet
+    pub fn categorize(&self, username: &str, transactions: &mut [&mut Transaction]) -> Result<()> {
+        let rules: Vec<(Regex, CategoryRule)> = self
+            .rule_repo
+            .select_by_username(username)?
+            .into_iter()
+            .filter_map(|it| Self::regex(&it.pattern).ok().map(|regex| (regex, it)))
+            .collect();
+
+        for transaction in transactions.iter_mut() {
+            if transaction.category_id.is_some() {
+                continue;
+            }
+
+            let description = match &transaction.description {
+                Some(description) => description,
+                None => continue,
+            };
+
+            if let Some((_, rule)) = rules.iter().find(|(it, _)| it.is_match(description)) {
+                transaction.category_id = Some(rule.category_id.clone());
+
+                for tag in &rule.tags {
+                    if !transaction.tags.contains(tag) {
+                        transaction.tags.push(tag.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn regex(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern).case_insensitive(true).build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Category, CategoryRule, Id, Transaction, TransactionType},
+        repository::{
+            BudgetRepository, CategoryRepository, CategoryRuleRepository,
+            RecurringTransactionRepository, TransactionRepository,
+        },
+        service::CategoryService,
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::Utc;
+
+    #[test]
+    fn validate_cycle() -> Result<()> {
+        let service = service();
+        let food = category("Food", None);
+        let groceries = category("Groceries", Some(&food.id));
+        service.insert(&food)?;
+        service.insert(&groceries)?;
+
+        let food = Category {
+            parent_id: Some(groceries.id.clone()),
+            ..food
+        };
+        assert!(service.validate(&food).is_err());
+        assert!(service.validate(&groceries).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_rule_pattern() -> Result<()> {
+        let service = service();
+        let food = category("Food", None);
+        service.insert(&food)?;
+
+        let mut rule = rule("aldi|lidl", &food.id);
+        assert!(service.validate_rule(&rule).is_ok());
+        rule.pattern = "(aldi".into();
+        assert!(service.validate_rule(&rule).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn categorize() -> Result<()> {
+        let service = service();
+        let food = category("Food", None);
+        let fuel = category("Fuel", None);
+        service.insert(&food)?;
+        service.insert(&fuel)?;
+        service.insert_rule(&rule("^aldi", &food.id))?;
+        service.insert_rule(&rule("shell|aral", &fuel.id))?;
+        service.insert_rule(&rule(".", &food.id))?;
+
+        let mut aldi = transaction(Some("ALDI SUED 123"));
+        let mut shell = transaction(Some("Card payment SHELL"));
+        let mut unknown = transaction(None);
+        let mut categorized = Transaction {
+            category_id: Some(Id::new()),
+            ..transaction(Some("Shell"))
+        };
+        service.categorize(
+            "test",
+            &mut [&mut aldi, &mut shell, &mut unknown, &mut categorized],
+        )?;
+
+        assert_eq!(Some(food.id), aldi.category_id);
+        assert_eq!(vec!["auto".to_string()], aldi.tags);
+        assert_eq!(Some(fuel.id.clone()), shell.category_id);
+        assert_eq!(None, unknown.category_id);
+        assert_ne!(Some(fuel.id), categorized.category_id);
+        Ok(())
+    }
+
+    fn service() -> CategoryService {
+        let pool = pool();
+        CategoryService::new(
+            &CategoryRepository::new(&pool),
+            &CategoryRuleRepository::new(&pool),
+            &TransactionRepository::new(&pool),
+            &BudgetRepository::new(&pool),
+            &RecurringTransactionRepository::new(&pool),
+        )
+    }
+
+    fn category(name: &str, parent_id: Option<&Id>) -> Category {
+        Category {
+            id: Id::new(),
+            username: "test".into(),
+            name: name.into(),
+            parent_id: parent_id.cloned(),
+        }
+    }
+
+    fn rule(pattern: &str, category_id: &Id) -> CategoryRule {
+        CategoryRule {
+            id: Id::new(),
+            username: "test".into(),
+            pattern: pattern.into(),
+            category_id: category_id.clone(),
+            tags: vec!["auto".into()],
+        }
+    }
+
+    fn transaction(description: Option<&str>) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: Id::new(),
+            transaction_type: TransactionType::Withdrawal,
+            asset: None,
+            quantity: 10.0,
+            price: None,
+            currency: "EUR".into(),
+            fee: 0.0,
+            time: Utc::now(),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: description.map(|it| it.to_string()),
+            category_id: None,
+            tags: vec![],
+        }
+    }
+}
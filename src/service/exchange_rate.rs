@@ -1,23 +1,126 @@
 use crate::{model::ExchangeRate, repository::ExchangeRateRepository};
 use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
+#[derive(Clone)]
 pub struct ExchangeRateService {
     repo: ExchangeRateRepository,
+    cache: Arc<RateCache>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct RateCacheConf {
+    /// Writes made by other processes, such as a standalone sync, become visible after that
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RateCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Resolved rates, including the missing ones. Every write to the rates, including the
+/// ones made by other processes, invalidates the whole cache.
+struct RateCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct CacheEntry {
+    rate: Option<ExchangeRate>,
+    repo_version: u64,
+    created_at: Instant,
 }
 
 impl ExchangeRateService {
-    pub fn new(repo: &ExchangeRateRepository) -> ExchangeRateService {
-        ExchangeRateService { repo: repo.clone() }
+    pub fn new(repo: &ExchangeRateRepository, cache_conf: &RateCacheConf) -> ExchangeRateService {
+        ExchangeRateService {
+            repo: repo.clone(),
+            cache: Arc::new(RateCache {
+                ttl: Duration::from_secs(cache_conf.ttl_secs),
+                entries: Mutex::new(HashMap::new()),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+        }
     }
 
     pub fn get_by_quote_and_base(&self, quote: &str, base: &str) -> Result<Option<ExchangeRate>> {
-        let rate = self.repo.select_by_quote_and_base(&quote, &base);
+        let key = (quote.to_string(), base.to_string());
+        let repo_version = self.repo.version()?;
+
+        if let Some(entry) = self.cache.entries.lock().unwrap().get(&key) {
+            if entry.repo_version == repo_version && entry.created_at.elapsed() < self.cache.ttl {
+                self.cache.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry.rate.clone());
+            }
+        }
+
+        self.cache.misses.fetch_add(1, Ordering::Relaxed);
+
+        let rate = self.resolve(quote, base, |quote, base| {
+            self.repo.select_by_quote_and_base(quote, base)
+        })?;
+
+        let mut entries = self.cache.entries.lock().unwrap();
+        entries.retain(|_, it| it.repo_version == repo_version);
+        entries.insert(
+            key,
+            CacheEntry {
+                rate: rate.clone(),
+                repo_version,
+                created_at: Instant::now(),
+            },
+        );
+
+        Ok(rate)
+    }
+
+    pub fn cache_stats(&self) -> RateCacheStats {
+        RateCacheStats {
+            hits: self.cache.hits.load(Ordering::Relaxed),
+            misses: self.cache.misses.load(Ordering::Relaxed),
+            entries: self.cache.entries.lock().unwrap().len(),
+        }
+    }
+
+    /// Same as `get_by_quote_and_base` but uses the last rates known on a given date
+    pub fn get_by_quote_and_base_and_date(
+        &self,
+        quote: &str,
+        base: &str,
+        date: &NaiveDate,
+    ) -> Result<Option<ExchangeRate>> {
+        self.resolve(quote, base, |quote, base| {
+            self.repo
+                .select_by_quote_and_base_and_date(quote, base, date)
+        })
+    }
+
+    fn resolve<F>(&self, quote: &str, base: &str, select: F) -> Result<Option<ExchangeRate>>
+    where
+        F: Fn(&str, &str) -> Result<Option<ExchangeRate>>,
+    {
+        let rate = select(quote, base);
 
         if let Some(v) = rate? {
             return Ok(Some(v));
         }
 
-        let rate = self.repo.select_by_quote_and_base(&base, &quote);
+        let rate = select(base, quote);
 
         if let Some(v) = rate? {
             return Ok(Some(ExchangeRate {
@@ -27,18 +130,14 @@ impl ExchangeRateService {
             }));
         }
 
-        let indirect_rate_1 = self
-            .repo
-            .select_by_quote_and_base(&quote, &"EUR".to_string())?;
-        let indirect_rate_2 = self
-            .repo
-            .select_by_quote_and_base(&base, &"EUR".to_string())?;
+        let indirect_rate_1 = select(quote, "EUR")?;
+        let indirect_rate_2 = select(base, "EUR")?;
 
-        if indirect_rate_1.is_some() && indirect_rate_2.is_some() {
+        if let (Some(indirect_rate_1), Some(indirect_rate_2)) = (indirect_rate_1, indirect_rate_2) {
             return Ok(Some(ExchangeRate {
                 quote: quote.into(),
                 base: base.into(),
-                rate: indirect_rate_1.unwrap().rate / indirect_rate_2.unwrap().rate,
+                rate: indirect_rate_1.rate / indirect_rate_2.rate,
             }));
         }
 
@@ -0,0 +1,716 @@
+use crate::{
+    model::{Account, Export, ExportFormat, Transaction, TransactionType},
+    repository::{AccountRepository, ExchangeRateRepository, TransactionRepository},
+};
+use anyhow::Result;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{Cursor, Write},
+};
+use zip::{write::FileOptions, ZipWriter};
+
+#[derive(Clone)]
+pub struct ExportService {
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    rate_repo: ExchangeRateRepository,
+}
+
+/// Plain-text accounting dialects, they share the structure but not the syntax
+#[derive(Clone, Copy, PartialEq)]
+enum Dialect {
+    Beancount,
+    Ledger,
+}
+
+struct Entry {
+    date: String,
+    description: String,
+    postings: Vec<Posting>,
+}
+
+/// Postings without an amount get the balance of the entry
+struct Posting {
+    account: String,
+    amount: Option<(f64, String)>,
+    cost: Cost,
+}
+
+enum Cost {
+    None,
+    /// Per unit cost of an acquired lot
+    Unit(f64, String),
+    /// Sale of any lot at a given price, the tool picks the lot
+    Sale(f64, String),
+}
+
+impl ExportService {
+    pub fn new(
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        rate_repo: &ExchangeRateRepository,
+    ) -> ExportService {
+        ExportService {
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            rate_repo: rate_repo.clone(),
+        }
+    }
+
+    /// Includes the rate history of every traded asset against its trade currency and of
+    /// every trade currency against the account currency
+    pub fn export(&self, username: &str) -> Result<Export> {
+        let accounts = self.account_repo.select_by_username(username)?;
+        let transactions = self.transaction_repo.select_by_username(username)?;
+
+        let account_currencies: HashMap<_, _> = accounts
+            .iter()
+            .map(|it| (it.id.to_string(), it.currency.clone()))
+            .collect();
+        let mut pairs = BTreeSet::new();
+
+        for transaction in &transactions {
+            if let Some(asset) = &transaction.asset {
+                pairs.insert((asset.clone(), transaction.currency.clone()));
+            }
+
+            if let Some(currency) = account_currencies.get(&transaction.account_id.to_string()) {
+                pairs.insert((transaction.currency.clone(), currency.clone()));
+            }
+        }
+
+        let mut rates = vec![];
+
+        // Rates which aren't known directly are derived from the rates against EUR
+        let pairs: BTreeSet<_> = pairs
+            .into_iter()
+            .filter(|(quote, base)| quote != base)
+            .flat_map(|(quote, base)| {
+                vec![
+                    (quote.clone(), base.clone()),
+                    (base.clone(), quote.clone()),
+                    (quote, "EUR".to_string()),
+                    (base, "EUR".to_string()),
+                ]
+            })
+            .filter(|(quote, base)| quote != base)
+            .collect();
+
+        for (quote, base) in pairs {
+            rates.extend(
+                self.rate_repo
+                    .select_history_by_quote_and_base(&quote, &base)?,
+            );
+        }
+
+        rates.sort_by(|a, b| (a.date, &a.quote, &a.base).cmp(&(b.date, &b.quote, &b.base)));
+        rates.dedup();
+
+        Ok(Export {
+            accounts,
+            transactions,
+            rates,
+        })
+    }
+
+    pub fn render(export: &Export, format: ExportFormat) -> Result<Vec<u8>> {
+        match format {
+            ExportFormat::Csv => Self::to_csv(export),
+            ExportFormat::Json => Ok(serde_json::to_vec_pretty(export)?),
+            ExportFormat::Beancount => Ok(Self::to_text(export, Dialect::Beancount).into_bytes()),
+            ExportFormat::Ledger => Ok(Self::to_text(export, Dialect::Ledger).into_bytes()),
+        }
+    }
+
+    /// Transactions are written in the format of the `pfd` import preset. Transactions
+    /// which weren't imported use their own ID as an external one, so importing them again
+    /// doesn't duplicate them. Transfers are rejected by imports since their legs have to
+    /// be linked, and transfer IDs, categories and tags are only kept for reference.
+    fn to_csv(export: &Export) -> Result<Vec<u8>> {
+        let mut accounts = csv::Writer::from_writer(vec![]);
+        accounts.write_record([
+            "id",
+            "name",
+            "type",
+            "currency",
+            "opened_at",
+            "closed_at",
+            "cost_basis_method",
+        ])?;
+
+        for account in &export.accounts {
+            accounts.write_record([
+                account.id.to_string(),
+                account.name.clone(),
+                account.account_type.to_string(),
+                account.currency.clone(),
+                account.opened_at.to_string(),
+                account
+                    .closed_at
+                    .map(|it| it.to_string())
+                    .unwrap_or_default(),
+                account.cost_basis_method.to_string(),
+            ])?;
+        }
+
+        let mut transactions = csv::Writer::from_writer(vec![]);
+        transactions.write_record([
+            "account_id",
+            "time",
+            "type",
+            "asset",
+            "new_asset",
+            "quantity",
+            "price",
+            "currency",
+            "fee",
+            "external_id",
+            "description",
+            "transfer_id",
+            "category_id",
+            "tags",
+        ])?;
+
+        for transaction in &export.transactions {
+            transactions.write_record([
+                transaction.account_id.to_string(),
+                transaction.time.to_rfc3339(),
+                transaction.transaction_type.to_string(),
+                transaction.asset.clone().unwrap_or_default(),
+                transaction.new_asset.clone().unwrap_or_default(),
+                transaction.quantity.to_string(),
+                transaction
+                    .price
+                    .map(|it| it.to_string())
+                    .unwrap_or_default(),
+                transaction.currency.clone(),
+                transaction.fee.to_string(),
+                transaction
+                    .external_id
+                    .clone()
+                    .unwrap_or_else(|| transaction.id.to_string()),
+                transaction.description.clone().unwrap_or_default(),
+                transaction
+                    .transfer_id
+                    .as_ref()
+                    .map(|it| it.to_string())
+                    .unwrap_or_default(),
+                transaction
+                    .category_id
+                    .as_ref()
+                    .map(|it| it.to_string())
+                    .unwrap_or_default(),
+                transaction.tags.join(";"),
+            ])?;
+        }
+
+        let mut rates = csv::Writer::from_writer(vec![]);
+        rates.write_record(["date", "quote", "base", "rate"])?;
+
+        for rate in &export.rates {
+            rates.write_record([
+                rate.date.to_string(),
+                rate.quote.clone(),
+                rate.base.clone(),
+                rate.rate.to_string(),
+            ])?;
+        }
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let files = vec![
+            ("accounts.csv", accounts.into_inner()?),
+            ("transactions.csv", transactions.into_inner()?),
+            ("rates.csv", rates.into_inner()?),
+        ];
+
+        for (name, contents) in files {
+            zip.start_file(name, FileOptions::default())?;
+            zip.write_all(&contents)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn to_text(export: &Export, dialect: Dialect) -> String {
+        let names = Self::account_names(&export.accounts);
+        let mut out = String::from("; Exported from pfd\n\n");
+
+        let first_date = export
+            .accounts
+            .iter()
+            .map(|it| it.opened_at)
+            .chain(
+                export
+                    .transactions
+                    .iter()
+                    .map(|it| it.time.date().naive_utc()),
+            )
+            .min();
+
+        let counter_accounts = vec![
+            "Equity:Transfers",
+            "Expenses:Fees",
+            "Expenses:Taxes",
+            "Income:Interest",
+            "Income:Dividends",
+            "Income:CapitalGains",
+        ];
+
+        match dialect {
+            Dialect::Beancount => {
+                for account in &export.accounts {
+                    // Transactions might predate the day the account was opened on
+                    let opened_at = export
+                        .transactions
+                        .iter()
+                        .filter(|it| it.account_id == account.id)
+                        .map(|it| it.time.date().naive_utc())
+                        .chain(std::iter::once(account.opened_at))
+                        .min()
+                        .unwrap();
+                    out += &format!("{} open {}\n", opened_at, names[&account.id.to_string()]);
+
+                    if let Some(closed_at) = account.closed_at {
+                        out += &format!("{} close {}\n", closed_at, names[&account.id.to_string()]);
+                    }
+                }
+
+                if let Some(first_date) = first_date {
+                    for account in &counter_accounts {
+                        out += &format!("{} open {}\n", first_date, account);
+                    }
+                }
+            }
+            Dialect::Ledger => {
+                for account in &export.accounts {
+                    out += &format!("account {}\n", names[&account.id.to_string()]);
+                }
+
+                for account in &counter_accounts {
+                    out += &format!("account {}\n", account);
+                }
+            }
+        }
+
+        out += "\n";
+
+        for rate in &export.rates {
+            out += &match dialect {
+                Dialect::Beancount => format!(
+                    "{} price {} {} {}\n",
+                    rate.date,
+                    Self::commodity(&rate.quote, dialect),
+                    Self::number(rate.rate),
+                    Self::commodity(&rate.base, dialect)
+                ),
+                Dialect::Ledger => format!(
+                    "P {} {} {} {}\n",
+                    rate.date.format("%Y/%m/%d"),
+                    Self::commodity(&rate.quote, dialect),
+                    Self::number(rate.rate),
+                    Self::commodity(&rate.base, dialect)
+                ),
+            };
+        }
+
+        for transaction in &export.transactions {
+            let account = match names.get(&transaction.account_id.to_string()) {
+                Some(account) => account,
+                None => continue,
+            };
+
+            // Neither tool can restate the lots of a corporate action without their balances,
+            // so it's left as a note to be handled by hand
+            if transaction.is_corporate_action() {
+                let note = Self::corporate_action_note(transaction);
+                let date = transaction.time.date().naive_utc();
+
+                out += &match dialect {
+                    Dialect::Beancount => format!("\n{} note {} \"{}\"\n", date, account, note),
+                    Dialect::Ledger => format!("\n; {} {}\n", date.format("%Y/%m/%d"), note),
+                };
+
+                continue;
+            }
+
+            let entry = Self::entry(transaction, account, dialect);
+
+            out += "\n";
+            out += &match dialect {
+                Dialect::Beancount => format!("{} * \"{}\"\n", entry.date, entry.description),
+                Dialect::Ledger => format!("{} * {}\n", entry.date, entry.description),
+            };
+
+            for posting in entry.postings {
+                out += &format!("  {}", posting.account);
+
+                if let Some((amount, commodity)) = posting.amount {
+                    out += &format!(
+                        "  {} {}",
+                        Self::number(amount),
+                        Self::commodity(&commodity, dialect)
+                    );
+                }
+
+                out += &match (posting.cost, dialect) {
+                    (Cost::None, _) => String::new(),
+                    (Cost::Unit(cost, currency), Dialect::Beancount) => format!(
+                        " {{{} {}}}",
+                        Self::number(cost),
+                        Self::commodity(&currency, dialect)
+                    ),
+                    (Cost::Sale(price, currency), Dialect::Beancount) => format!(
+                        " {{}} @ {} {}",
+                        Self::number(price),
+                        Self::commodity(&currency, dialect)
+                    ),
+                    (Cost::Unit(price, currency), Dialect::Ledger)
+                    | (Cost::Sale(price, currency), Dialect::Ledger) => format!(
+                        " @ {} {}",
+                        Self::number(price),
+                        Self::commodity(&currency, dialect)
+                    ),
+                };
+
+                out += "\n";
+            }
+        }
+
+        out
+    }
+
+    fn entry(transaction: &Transaction, account: &str, dialect: Dialect) -> Entry {
+        let currency = transaction.currency.clone();
+        let asset = transaction.asset.clone().unwrap_or_default();
+        let quantity = transaction.quantity;
+        let price = transaction.price.unwrap_or(0.0);
+
+        let posting = |amount: f64, commodity: &str, cost: Cost| Posting {
+            account: account.into(),
+            amount: Some((amount, commodity.into())),
+            cost,
+        };
+        let counter = |account: &str| Posting {
+            account: account.into(),
+            amount: None,
+            cost: Cost::None,
+        };
+
+        let (description, mut postings) = match (transaction.transaction_type, &transaction.asset) {
+            (TransactionType::Buy, _) => (
+                format!("Buy {}", asset),
+                vec![
+                    posting(quantity, &asset, Cost::Unit(price, currency.clone())),
+                    posting(-quantity * price, &currency, Cost::None),
+                ],
+            ),
+            (TransactionType::Sell, _) => {
+                let mut postings = vec![
+                    posting(-quantity, &asset, Cost::Sale(price, currency.clone())),
+                    posting(quantity * price, &currency, Cost::None),
+                ];
+
+                // Ledger values both sides at the sale price, so there's nothing to book
+                if dialect == Dialect::Beancount {
+                    postings.push(counter("Income:CapitalGains"));
+                }
+
+                (format!("Sell {}", asset), postings)
+            }
+            (TransactionType::Deposit, _) => (
+                "Deposit".into(),
+                vec![
+                    posting(quantity, &currency, Cost::None),
+                    counter("Equity:Transfers"),
+                ],
+            ),
+            (TransactionType::Withdrawal, _) => (
+                "Withdrawal".into(),
+                vec![
+                    posting(-quantity, &currency, Cost::None),
+                    counter("Equity:Transfers"),
+                ],
+            ),
+            (TransactionType::Fee, _) => (
+                "Fee".into(),
+                vec![
+                    posting(-quantity, &currency, Cost::None),
+                    counter("Expenses:Fees"),
+                ],
+            ),
+            (TransactionType::Interest, _) => (
+                "Interest".into(),
+                vec![
+                    posting(quantity, &currency, Cost::None),
+                    counter("Income:Interest"),
+                ],
+            ),
+            (TransactionType::Dividend, _) => (
+                format!("Dividend {}", asset),
+                vec![
+                    posting(quantity, &currency, Cost::None),
+                    counter("Income:Dividends"),
+                ],
+            ),
+            (TransactionType::DividendReinvestment, _) => (
+                format!("Reinvested dividend {}", asset),
+                vec![
+                    posting(quantity, &asset, Cost::Unit(price, currency.clone())),
+                    counter("Income:Dividends"),
+                ],
+            ),
+            (TransactionType::Tax, _) => (
+                match &transaction.asset {
+                    Some(asset) => format!("Tax {}", asset),
+                    None => "Tax".into(),
+                },
+                vec![
+                    posting(-quantity, &currency, Cost::None),
+                    counter("Expenses:Taxes"),
+                ],
+            ),
+            (TransactionType::Transfer, Some(asset)) => (
+                format!("Transfer {}", asset),
+                vec![
+                    posting(quantity, asset, Cost::None),
+                    counter("Equity:Transfers"),
+                ],
+            ),
+            (TransactionType::Transfer, None) => (
+                "Transfer".into(),
+                vec![
+                    posting(quantity, &currency, Cost::None),
+                    counter("Equity:Transfers"),
+                ],
+            ),
+            (TransactionType::Split, _)
+            | (TransactionType::SpinOff, _)
+            | (TransactionType::TickerChange, _) => {
+                (Self::corporate_action_note(transaction), vec![])
+            }
+        };
+
+        if transaction.fee != 0.0 {
+            let fee = transaction.fee;
+            let mut fee_posting = posting(fee, &currency, Cost::None);
+            fee_posting.account = "Expenses:Fees".into();
+            postings.push(posting(-fee, &currency, Cost::None));
+            postings.push(fee_posting);
+        }
+
+        let date = transaction.time.date().naive_utc();
+
+        Entry {
+            date: match dialect {
+                Dialect::Beancount => date.format("%Y-%m-%d").to_string(),
+                Dialect::Ledger => date.format("%Y/%m/%d").to_string(),
+            },
+            description,
+            postings,
+        }
+    }
+
+    fn corporate_action_note(transaction: &Transaction) -> String {
+        let asset = transaction.asset.clone().unwrap_or_default();
+        let new_asset = transaction.new_asset.clone().unwrap_or_default();
+        let ratio = Self::number(transaction.quantity);
+
+        match transaction.transaction_type {
+            TransactionType::Split => format!("Split {} {} for 1", asset, ratio),
+            TransactionType::SpinOff => format!(
+                "Spin-off of {} {} per {}, {} of the cost basis",
+                ratio,
+                new_asset,
+                asset,
+                Self::number(transaction.price.unwrap_or(0.0))
+            ),
+            _ => format!("Ticker change {} to {}, {} for 1", asset, new_asset, ratio),
+        }
+    }
+
+    /// Account names such as Assets:Brokerage:MyBroker, made unique by a numeric suffix
+    fn account_names(accounts: &[Account]) -> HashMap<String, String> {
+        let mut names = HashMap::new();
+        let mut taken: HashMap<String, usize> = HashMap::new();
+
+        for account in accounts {
+            let kind = account.account_type.to_string();
+            let mut name: String = account
+                .name
+                .split(|it: char| !it.is_ascii_alphanumeric())
+                .filter(|it| !it.is_empty())
+                .map(|it| it[..1].to_uppercase() + &it[1..])
+                .collect();
+
+            if name.is_empty() {
+                name = "Account".into();
+            }
+
+            let name = format!("Assets:{}:{}", kind[..1].to_uppercase() + &kind[1..], name);
+            let count = taken.entry(name.clone()).or_insert(0);
+            *count += 1;
+
+            let name = match count {
+                1 => name,
+                n => format!("{}{}", name, n),
+            };
+
+            names.insert(account.id.to_string(), name);
+        }
+
+        names
+    }
+
+    /// Ledger needs quotes around commodities which aren't purely alphabetic
+    fn commodity(code: &str, dialect: Dialect) -> String {
+        match dialect {
+            Dialect::Ledger if !code.chars().all(|it| it.is_alphabetic()) => {
+                format!("\"{}\"", code)
+            }
+            _ => code.to_string(),
+        }
+    }
+
+    /// Drops the noise of binary floats, such as 0.30000000000000004
+    fn number(value: f64) -> String {
+        let number = format!("{:.10}", value);
+        let number = number.trim_end_matches('0').trim_end_matches('.');
+
+        match number {
+            "-0" => "0".into(),
+            number => number.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, CostBasisMethod, Export, ExportFormat, HistoricalRate, Id,
+            Transaction, TransactionType,
+        },
+        service::ExportService,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn render_beancount() -> Result<()> {
+        let text = String::from_utf8(ExportService::render(&export(), ExportFormat::Beancount)?)?;
+        let expected = r#"; Exported from pfd
+
+2021-01-01 open Assets:Brokerage:MyBroker
+2021-01-01 open Equity:Transfers
+2021-01-01 open Expenses:Fees
+2021-01-01 open Expenses:Taxes
+2021-01-01 open Income:Interest
+2021-01-01 open Income:Dividends
+2021-01-01 open Income:CapitalGains
+
+2021-08-03 price BTC 160 USD
+
+2021-08-01 * "Deposit"
+  Assets:Brokerage:MyBroker  1000 USD
+  Equity:Transfers
+
+2021-08-02 * "Buy BTC"
+  Assets:Brokerage:MyBroker  2 BTC {100.1 USD}
+  Assets:Brokerage:MyBroker  -200.2 USD
+  Assets:Brokerage:MyBroker  -1 USD
+  Expenses:Fees  1 USD
+
+2021-08-03 * "Sell BTC"
+  Assets:Brokerage:MyBroker  -1 BTC {} @ 150 USD
+  Assets:Brokerage:MyBroker  150 USD
+  Income:CapitalGains
+"#;
+        assert_eq!(expected, text);
+        Ok(())
+    }
+
+    #[test]
+    fn render_ledger() -> Result<()> {
+        let text = String::from_utf8(ExportService::render(&export(), ExportFormat::Ledger)?)?;
+        assert!(text.contains("account Assets:Brokerage:MyBroker\n"));
+        assert!(text.contains("P 2021/08/03 BTC 160 USD\n"));
+        assert!(text.contains(
+            "2021/08/03 * Sell BTC\n  Assets:Brokerage:MyBroker  -1 BTC @ 150 USD\n  Assets:Brokerage:MyBroker  150 USD\n"
+        ));
+        assert!(text.ends_with("150 USD\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn render_csv() -> Result<()> {
+        let export = export();
+        let zip = ExportService::render(&export, ExportFormat::Csv)?;
+        let mut zip = ZipArchive::new(Cursor::new(zip))?;
+        let mut transactions = String::new();
+        zip.by_name("transactions.csv")?
+            .read_to_string(&mut transactions)?;
+        let lines: Vec<&str> = transactions.lines().collect();
+        assert_eq!(4, lines.len());
+        assert_eq!(
+            "account_id,time,type,asset,new_asset,quantity,price,currency,fee,external_id,description,transfer_id,category_id,tags",
+            lines[0]
+        );
+        assert!(lines[1].ends_with(&format!(
+            ",2021-08-01T12:00:00+00:00,deposit,,,1000,,USD,0,{},,,,",
+            export.transactions[0].id
+        )));
+        assert!(zip.by_name("accounts.csv").is_ok());
+        assert!(zip.by_name("rates.csv").is_ok());
+        Ok(())
+    }
+
+    fn export() -> Export {
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "my broker".into(),
+            account_type: AccountType::Brokerage,
+            currency: "USD".into(),
+            opened_at: NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        let transaction =
+            |transaction_type, asset: Option<&str>, quantity, price, fee, day| Transaction {
+                id: Id::new(),
+                account_id: account.id.clone(),
+                transaction_type,
+                asset: asset.map(|it| it.into()),
+                quantity,
+                price,
+                currency: "USD".into(),
+                fee,
+                time: Utc.ymd(2021, 8, day).and_hms(12, 0, 0),
+                lot_id: None,
+                external_id: None,
+                new_asset: None,
+                transfer_id: None,
+                description: None,
+                category_id: None,
+                tags: vec![],
+            };
+        let transactions = vec![
+            transaction(TransactionType::Deposit, None, 1000.0, None, 0.0, 1),
+            transaction(TransactionType::Buy, Some("BTC"), 2.0, Some(100.1), 1.0, 2),
+            transaction(TransactionType::Sell, Some("BTC"), 1.0, Some(150.0), 0.0, 3),
+        ];
+
+        Export {
+            accounts: vec![account.clone()],
+            transactions,
+            rates: vec![HistoricalRate {
+                date: NaiveDate::from_ymd(2021, 8, 3),
+                quote: "BTC".into(),
+                base: "USD".into(),
+                rate: 160.0,
+            }],
+        }
+    }
+}
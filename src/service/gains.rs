@@ -0,0 +1,200 @@
+use crate::{
+    model::{ExchangeRate, GainsReport, Lot, LotMatch, RealizedGain, Transaction, UnrealizedGain},
+    repository::{AccountRepository, LotMatchRepository, TransactionRepository},
+    service::{holding::DUST, HoldingService, LotService, ValuationService},
+};
+use anyhow::Result;
+use chrono::{Date, NaiveDate, Utc};
+
+#[derive(Clone)]
+pub struct GainsService {
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    lot_match_repo: LotMatchRepository,
+    valuation_service: ValuationService,
+}
+
+impl GainsService {
+    pub fn new(
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        lot_match_repo: &LotMatchRepository,
+        valuation_service: &ValuationService,
+    ) -> GainsService {
+        GainsService {
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            lot_match_repo: lot_match_repo.clone(),
+            valuation_service: valuation_service.clone(),
+        }
+    }
+
+    /// Realized gains of the sells made within the period and unrealized gains of the lots
+    /// which are open at its end. Open end means now and uses the latest rates.
+    pub fn report(
+        &self,
+        username: &str,
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<GainsReport> {
+        let start = from.map(|it| Date::<Utc>::from_utc(it, Utc).and_hms(0, 0, 0));
+        let end = to.map(|it| HoldingService::end_of_day(&it));
+
+        let mut report = GainsReport {
+            currency: currency.into(),
+            from,
+            to,
+            realized: vec![],
+            unrealized: vec![],
+            total_realized: 0.0,
+            total_unrealized: 0.0,
+            unpriced_lots: vec![],
+            rates: vec![],
+        };
+
+        let accounts = self.account_repo.select_by_username(username)?;
+        // Transfers move lots between accounts, so all of them are replayed together
+        let transactions: Vec<Transaction> = self
+            .transaction_repo
+            .select_by_username(username)?
+            .into_iter()
+            .filter(|it| end.map(|end| it.time < end).unwrap_or(true))
+            .collect();
+        let (lots, _) = LotService::match_accounts(&accounts, &transactions);
+
+        for account in &accounts {
+            for lot_match in self.lot_match_repo.select_by_account_id(&account.id)? {
+                if start.map(|it| lot_match.sold_at < it).unwrap_or(false)
+                    || end.map(|it| lot_match.sold_at >= it).unwrap_or(false)
+                {
+                    continue;
+                }
+
+                match self.realized(&lot_match, currency, &mut report.rates)? {
+                    Some(gain) => report.realized.push(gain),
+                    None => report.unpriced_lots.push(lot_match.lot_id),
+                }
+            }
+
+            let transactions: Vec<Transaction> = transactions
+                .iter()
+                .filter(|it| it.account_id == account.id)
+                .cloned()
+                .collect();
+
+            for lot in lots
+                .iter()
+                .filter(|it| it.account_id == account.id && it.remaining > DUST)
+            {
+                match self.unrealized(lot, &transactions, currency, to, &mut report.rates)? {
+                    Some(gain) => report.unrealized.push(gain),
+                    None => report.unpriced_lots.push(lot.id.clone()),
+                }
+            }
+        }
+
+        report.total_realized = report.realized.iter().map(|it| it.gain).sum();
+        report.total_unrealized = report.unrealized.iter().map(|it| it.gain).sum();
+        Ok(report)
+    }
+
+    /// Cost is converted into the sale currency first, so the sale currency is treated as
+    /// the asset currency
+    pub fn realized(
+        &self,
+        lot_match: &LotMatch,
+        currency: &str,
+        rates: &mut Vec<ExchangeRate>,
+    ) -> Result<Option<RealizedGain>> {
+        let acquired = Some(lot_match.acquired_at.date().naive_utc());
+        let sold = Some(lot_match.sold_at.date().naive_utc());
+        let asset_currency = &lot_match.proceeds_currency;
+
+        let cost = self.valuation_service.rate(
+            &lot_match.cost_currency,
+            asset_currency,
+            acquired,
+            rates,
+        )?;
+        let fx_acquired = self
+            .valuation_service
+            .rate(asset_currency, currency, acquired, rates)?;
+        let fx_sold = self
+            .valuation_service
+            .rate(asset_currency, currency, sold, rates)?;
+
+        let (cost, fx_acquired, fx_sold) = match (cost, fx_acquired, fx_sold) {
+            (Some(rate), Some(fx_acquired), Some(fx_sold)) => {
+                (lot_match.cost * rate, fx_acquired, fx_sold)
+            }
+            _ => return Ok(None),
+        };
+
+        let (price_gain, fx_gain) = split(cost, lot_match.proceeds, fx_acquired, fx_sold);
+
+        Ok(Some(RealizedGain {
+            account_id: lot_match.account_id.clone(),
+            lot_id: lot_match.lot_id.clone(),
+            sell_id: lot_match.sell_id.clone(),
+            asset: lot_match.asset.clone(),
+            quantity: lot_match.quantity,
+            acquired_at: lot_match.acquired_at,
+            sold_at: lot_match.sold_at,
+            cost: cost * fx_acquired,
+            proceeds: lot_match.proceeds * fx_sold,
+            price_gain,
+            fx_gain,
+            gain: price_gain + fx_gain,
+        }))
+    }
+
+    fn unrealized(
+        &self,
+        lot: &Lot,
+        transactions: &[Transaction],
+        currency: &str,
+        date: Option<NaiveDate>,
+        rates: &mut Vec<ExchangeRate>,
+    ) -> Result<Option<UnrealizedGain>> {
+        let acquired = Some(lot.acquired_at.date().naive_utc());
+
+        let price =
+            self.valuation_service
+                .price(&lot.asset, transactions, &lot.currency, date, rates)?;
+        let fx_acquired = self
+            .valuation_service
+            .rate(&lot.currency, currency, acquired, rates)?;
+        let fx_now = self
+            .valuation_service
+            .rate(&lot.currency, currency, date, rates)?;
+
+        let (price, fx_acquired, fx_now) = match (price, fx_acquired, fx_now) {
+            (Some(price), Some(fx_acquired), Some(fx_now)) => (price, fx_acquired, fx_now),
+            _ => return Ok(None),
+        };
+
+        let cost = lot.cost * lot.remaining / lot.quantity;
+        let value = price * lot.remaining;
+        let (price_gain, fx_gain) = split(cost, value, fx_acquired, fx_now);
+
+        Ok(Some(UnrealizedGain {
+            account_id: lot.account_id.clone(),
+            lot_id: lot.id.clone(),
+            asset: lot.asset.clone(),
+            quantity: lot.remaining,
+            acquired_at: lot.acquired_at,
+            cost: cost * fx_acquired,
+            value: value * fx_now,
+            price_gain,
+            fx_gain,
+            gain: price_gain + fx_gain,
+        }))
+    }
+}
+
+/// Splits a gain in the reporting currency into the price and FX parts. Cost and value are
+/// in the asset currency, rates convert it to the reporting currency at both ends.
+fn split(cost: f64, value: f64, fx_start: f64, fx_end: f64) -> (f64, f64) {
+    ((value - cost) * fx_end, cost * (fx_end - fx_start))
+}
@@ -0,0 +1,206 @@
+use crate::{
+    model::{Goal, GoalProgress, Id, Period},
+    repository::{AccountRepository, GoalRepository, TransactionRepository},
+    service::{AssetService, PerformanceService},
+};
+use anyhow::{ensure, Result};
+use chrono::{Datelike, NaiveDate};
+
+/// Projections stop after a century
+const MAX_MONTHS: u32 = 1200;
+
+/// Shorter histories don't extrapolate into contributions and returns
+const MIN_HISTORY_DAYS: i64 = 90;
+
+#[derive(Clone)]
+pub struct GoalService {
+    repo: GoalRepository,
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    asset_service: AssetService,
+    performance_service: PerformanceService,
+}
+
+impl GoalService {
+    pub fn new(
+        repo: &GoalRepository,
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        asset_service: &AssetService,
+        performance_service: &PerformanceService,
+    ) -> GoalService {
+        GoalService {
+            repo: repo.clone(),
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            asset_service: asset_service.clone(),
+            performance_service: performance_service.clone(),
+        }
+    }
+
+    pub fn insert(&self, goal: &Goal) -> Result<()> {
+        self.repo.insert(goal)
+    }
+
+    pub fn update(&self, goal: &Goal) -> Result<()> {
+        self.repo.update(goal)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.repo.delete(id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Goal>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Other users' goals are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Goal>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, goal: &Goal) -> Result<()> {
+        ensure!(!goal.name.trim().is_empty(), "Goal name can't be empty");
+        ensure!(goal.target > 0.0, "Target must be positive");
+        ensure!(
+            self.asset_service.exists(&goal.currency)?,
+            "Unknown asset: {}",
+            goal.currency
+        );
+        ensure!(!goal.account_ids.is_empty(), "Goal needs an account");
+
+        for (i, account_id) in goal.account_ids.iter().enumerate() {
+            ensure!(
+                !goal.account_ids[..i].contains(account_id),
+                "Account {} is linked twice",
+                account_id
+            );
+
+            let account = self.account_repo.select_by_id(account_id)?;
+            ensure!(
+                account
+                    .map(|it| it.username == goal.username)
+                    .unwrap_or(false),
+                "Unknown account: {}",
+                account_id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Projects the value of the linked accounts month by month, assuming they keep
+    /// getting the net contributions and the return of the last year
+    pub fn progress(&self, goal: &Goal, date: &NaiveDate) -> Result<GoalProgress> {
+        let mut transactions = vec![];
+
+        for account_id in &goal.account_ids {
+            transactions.extend(self.transaction_repo.select_by_account_id(account_id)?);
+        }
+
+        transactions.sort_by_key(|it| it.time);
+
+        let from = match transactions.first() {
+            Some(first) => Period::OneYear
+                .start(date)
+                .unwrap()
+                .max(first.time.date().naive_utc())
+                .min(*date),
+            None => *date,
+        };
+
+        let performance = self.performance_service.performance(
+            &transactions,
+            &goal.currency,
+            Some(from),
+            *date,
+        )?;
+
+        let days = (*date - from).num_days() + 1;
+        // Short periods extrapolate into noise
+        let monthly_contribution = Some(performance.net_flows / (days as f64 * 12.0 / 365.0))
+            .filter(|_| days >= MIN_HISTORY_DAYS);
+        let annual_return = performance.xirr.filter(|_| days >= MIN_HISTORY_DAYS);
+        let growth = (1.0 + annual_return.unwrap_or(0.0)).powf(1.0 / 12.0);
+
+        let value = performance.end_value;
+        let mut projected = value;
+        let mut projected_completion = None;
+
+        for months in 0..=MAX_MONTHS {
+            if projected >= goal.target - 0.005 {
+                projected_completion = Some(add_months(date, months));
+                break;
+            }
+
+            projected = projected * growth + monthly_contribution.unwrap_or(0.0);
+        }
+
+        let required_contribution = goal.deadline.filter(|it| it > date).map(|deadline| {
+            let mut months = 1;
+
+            while months < MAX_MONTHS && add_months(date, months + 1) <= deadline {
+                months += 1;
+            }
+
+            let compounded = growth.powi(months as i32);
+            let annuity = match (growth - 1.0).abs() < 1e-12 {
+                true => months as f64,
+                false => (compounded - 1.0) / (growth - 1.0),
+            };
+
+            ((goal.target - value * compounded) / annuity).max(0.0)
+        });
+
+        Ok(GoalProgress {
+            goal_id: goal.id.clone(),
+            currency: goal.currency.clone(),
+            date: *date,
+            value,
+            target: goal.target,
+            progress: value / goal.target,
+            monthly_contribution,
+            annual_return,
+            projected_completion,
+            required_contribution,
+            on_track: goal.deadline.map(|deadline| {
+                projected_completion
+                    .map(|it| it <= deadline)
+                    .unwrap_or(false)
+            }),
+        })
+    }
+}
+
+/// Ends of months carry over, so Jan 31 plus a month is the last day of February
+fn add_months(date: &NaiveDate, months: u32) -> NaiveDate {
+    let month = date.month0() + months;
+    let year = date.year() + (month / 12) as i32;
+    let month = month % 12 + 1;
+    let mut day = date.day();
+
+    loop {
+        match NaiveDate::from_ymd_opt(year, month, day) {
+            Some(it) => return it,
+            None => day -= 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::service::goal::add_months;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn add_months_clamps() {
+        let date = NaiveDate::from_ymd(2021, 12, 31);
+        assert_eq!(NaiveDate::from_ymd(2022, 2, 28), add_months(&date, 2));
+        assert_eq!(NaiveDate::from_ymd(2022, 12, 31), add_months(&date, 12));
+        assert_eq!(date, add_months(&date, 0));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    model::{Holding, Id, Transaction, TransactionType},
+    repository::TransactionRepository,
+};
+use anyhow::Result;
+use chrono::{Date, DateTime, NaiveDate, Utc};
+use std::collections::BTreeMap;
+
+/// Quantities below this threshold are leftovers of floating point math
+pub const DUST: f64 = 1e-9;
+
+#[derive(Clone)]
+pub struct HoldingService {
+    transaction_repo: TransactionRepository,
+}
+
+impl HoldingService {
+    pub fn new(transaction_repo: &TransactionRepository) -> HoldingService {
+        HoldingService {
+            transaction_repo: transaction_repo.clone(),
+        }
+    }
+
+    pub fn select_by_account_id(
+        &self,
+        account_id: &Id,
+        as_of: Option<NaiveDate>,
+    ) -> Result<Vec<Holding>> {
+        let transactions = self.transaction_repo.select_by_account_id(account_id)?;
+        Ok(Self::replay(&transactions, as_of))
+    }
+
+    /// Combined positions of all the user's accounts
+    pub fn select_by_username(
+        &self,
+        username: &str,
+        as_of: Option<NaiveDate>,
+    ) -> Result<Vec<Holding>> {
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        Ok(Self::replay(&transactions, as_of))
+    }
+
+    /// Sums up the ledger up to the end of a given day, or all of it if there is no date.
+    /// Balances are kept per account since corporate actions apply to a single account.
+    pub fn replay(transactions: &[Transaction], as_of: Option<NaiveDate>) -> Vec<Holding> {
+        let mut balances: BTreeMap<(String, &str), f64> = BTreeMap::new();
+
+        let end = as_of.map(|it| Self::end_of_day(&it));
+
+        for transaction in transactions
+            .iter()
+            .filter(|it| end.map(|end| it.time < end).unwrap_or(true))
+        {
+            let account_id = transaction.account_id.to_string();
+
+            *balances
+                .entry((account_id.clone(), &transaction.currency))
+                .or_insert(0.0) += transaction.cash_amount();
+
+            let asset = match &transaction.asset {
+                Some(asset) => asset,
+                None => continue,
+            };
+
+            let balance = balances.entry((account_id.clone(), asset)).or_insert(0.0);
+
+            match (transaction.transaction_type, &transaction.new_asset) {
+                (TransactionType::Split, _) => *balance *= transaction.quantity,
+                (TransactionType::TickerChange, Some(new_asset)) => {
+                    let converted = std::mem::take(balance) * transaction.quantity;
+                    *balances.entry((account_id, new_asset)).or_insert(0.0) += converted;
+                }
+                (TransactionType::SpinOff, Some(new_asset)) => {
+                    let received = *balance * transaction.quantity;
+                    *balances.entry((account_id, new_asset)).or_insert(0.0) += received;
+                }
+                _ => *balance += transaction.asset_quantity(),
+            }
+        }
+
+        let mut quantities: BTreeMap<&str, f64> = BTreeMap::new();
+
+        for ((_, asset), balance) in balances {
+            *quantities.entry(asset).or_insert(0.0) += balance;
+        }
+
+        quantities
+            .into_iter()
+            .filter(|(_, quantity)| quantity.abs() > DUST)
+            .map(|(asset, quantity)| Holding {
+                asset: asset.into(),
+                quantity,
+            })
+            .collect()
+    }
+
+    /// Transactions made before this time are included in the positions as of a given day
+    pub fn end_of_day(date: &NaiveDate) -> DateTime<Utc> {
+        Date::<Utc>::from_utc(date.succ(), Utc).and_hms(0, 0, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Holding, Id, Transaction, TransactionType},
+        service::HoldingService,
+    };
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn replay() {
+        let transactions = vec![
+            transaction(TransactionType::Deposit, None, 30000.0, None, 1),
+            transaction(TransactionType::Buy, Some("BTC"), 0.5, Some(40000.0), 2),
+            transaction(TransactionType::Sell, Some("BTC"), 0.25, Some(50000.0), 3),
+            transaction(TransactionType::Dividend, Some("BTC"), 5.0, None, 3),
+            transaction(TransactionType::Withdrawal, None, 1000.0, None, 4),
+        ];
+
+        assert_eq!(
+            vec![holding("BTC", 0.25), holding("USD", 21503.0)],
+            HoldingService::replay(&transactions, None)
+        );
+    }
+
+    #[test]
+    fn replay_as_of() {
+        let transactions = vec![
+            transaction(TransactionType::Deposit, None, 10000.0, None, 1),
+            transaction(TransactionType::Buy, Some("BTC"), 0.2, Some(40000.0), 2),
+            transaction(TransactionType::Sell, Some("BTC"), 0.2, Some(50000.0), 3),
+        ];
+
+        assert_eq!(
+            vec![holding("USD", 10000.0)],
+            HoldingService::replay(&transactions, Some(NaiveDate::from_ymd(2021, 8, 1)))
+        );
+
+        assert_eq!(
+            vec![holding("BTC", 0.2), holding("USD", 2000.0 - 1.0)],
+            HoldingService::replay(&transactions, Some(NaiveDate::from_ymd(2021, 8, 2)))
+        );
+
+        assert_eq!(
+            vec![holding("USD", 12000.0 - 2.0)],
+            HoldingService::replay(&transactions, None)
+        );
+    }
+
+    #[test]
+    fn replay_corporate_actions() {
+        let account_id = Id::new();
+        let mut transactions = vec![
+            transaction(TransactionType::Buy, Some("AAPL"), 10.0, Some(100.0), 1),
+            transaction(TransactionType::Split, Some("AAPL"), 4.0, None, 2),
+            transaction(TransactionType::SpinOff, Some("AAPL"), 0.5, Some(0.1), 3),
+            transaction(TransactionType::TickerChange, Some("XYZ"), 2.0, None, 4),
+        ];
+        transactions[2].new_asset = Some("XYZ".into());
+        transactions[3].new_asset = Some("ABC".into());
+
+        for transaction in &mut transactions {
+            transaction.account_id = account_id.clone();
+        }
+
+        // Other accounts keep their balances
+        transactions.insert(
+            0,
+            transaction(TransactionType::Buy, Some("AAPL"), 1.0, Some(100.0), 1),
+        );
+
+        assert_eq!(
+            vec![
+                holding("AAPL", 41.0),
+                holding("ABC", 40.0),
+                holding("USD", -1100.0 - 3.0)
+            ],
+            HoldingService::replay(&transactions, None)
+        );
+    }
+
+    fn transaction(
+        transaction_type: TransactionType,
+        asset: Option<&str>,
+        quantity: f64,
+        price: Option<f64>,
+        day: u32,
+    ) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: Id::new(),
+            transaction_type,
+            asset: asset.map(|it| it.into()),
+            quantity,
+            price,
+            currency: "USD".into(),
+            fee: if price.is_some() { 1.0 } else { 0.0 },
+            time: Utc.ymd(2021, 8, day).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+
+    fn holding(asset: &str, quantity: f64) -> Holding {
+        Holding {
+            asset: asset.into(),
+            quantity,
+        }
+    }
+}
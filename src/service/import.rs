@@ -0,0 +1,1064 @@
+use crate::{
+    model::{
+        Account, CsvMapping, Id, ImportReport, ImportRow, ImportStatus, Transaction,
+        TransactionType,
+    },
+    repository::TransactionRepository,
+    service::{CategoryService, TransactionService},
+};
+use anyhow::{ensure, Error, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Parsed row along with its line number, errors are safe to show to the user
+pub type ParsedRow = (u64, std::result::Result<Transaction, String>);
+
+#[derive(Clone)]
+pub struct ImportService {
+    transaction_repo: TransactionRepository,
+    transaction_service: TransactionService,
+    category_service: CategoryService,
+}
+
+impl ImportService {
+    pub fn new(
+        transaction_repo: &TransactionRepository,
+        transaction_service: &TransactionService,
+        category_service: &CategoryService,
+    ) -> ImportService {
+        ImportService {
+            transaction_repo: transaction_repo.clone(),
+            transaction_service: transaction_service.clone(),
+            category_service: category_service.clone(),
+        }
+    }
+
+    /// Built-in mappings for common sources, `pfd` is the format of our own export
+    pub fn presets() -> BTreeMap<String, CsvMapping> {
+        let mut presets = BTreeMap::new();
+
+        let types = vec![
+            TransactionType::Buy,
+            TransactionType::Sell,
+            TransactionType::Deposit,
+            TransactionType::Withdrawal,
+            TransactionType::Fee,
+            TransactionType::Interest,
+            TransactionType::Dividend,
+            TransactionType::DividendReinvestment,
+            TransactionType::Tax,
+            TransactionType::Transfer,
+            TransactionType::Split,
+            TransactionType::SpinOff,
+            TransactionType::TickerChange,
+        ];
+
+        presets.insert(
+            "pfd".to_string(),
+            CsvMapping {
+                delimiter: ',',
+                time: "time".into(),
+                time_format: None,
+                transaction_type: "type".into(),
+                types: types.into_iter().map(|it| (it.to_string(), it)).collect(),
+                asset: Some("asset".into()),
+                new_asset: Some("new_asset".into()),
+                description: Some("description".into()),
+                quantity: "quantity".into(),
+                amount: None,
+                price: Some("price".into()),
+                currency: Some("currency".into()),
+                fee: Some("fee".into()),
+                external_id: Some("external_id".into()),
+                decimal_comma: false,
+            },
+        );
+
+        let types = vec![
+            ("Buy", TransactionType::Buy),
+            ("Advanced Trade Buy", TransactionType::Buy),
+            ("Sell", TransactionType::Sell),
+            ("Advanced Trade Sell", TransactionType::Sell),
+            ("Deposit", TransactionType::Deposit),
+            ("Withdrawal", TransactionType::Withdrawal),
+            ("Send", TransactionType::Transfer),
+            ("Receive", TransactionType::Transfer),
+            ("Rewards Income", TransactionType::Interest),
+            ("Staking Income", TransactionType::Interest),
+            ("Learning Reward", TransactionType::Interest),
+            ("Coinbase Earn", TransactionType::Interest),
+        ];
+
+        presets.insert(
+            "coinbase".to_string(),
+            CsvMapping {
+                delimiter: ',',
+                time: "Timestamp".into(),
+                time_format: None,
+                transaction_type: "Transaction Type".into(),
+                types: types.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+                asset: Some("Asset".into()),
+                new_asset: None,
+                description: None,
+                quantity: "Quantity Transacted".into(),
+                amount: None,
+                price: Some("Spot Price at Transaction".into()),
+                currency: Some("Spot Price Currency".into()),
+                fee: Some("Fees and/or Spread".into()),
+                external_id: None,
+                decimal_comma: false,
+            },
+        );
+
+        let types = vec![
+            ("BUY - MARKET", TransactionType::Buy),
+            ("BUY - LIMIT", TransactionType::Buy),
+            ("SELL - MARKET", TransactionType::Sell),
+            ("SELL - LIMIT", TransactionType::Sell),
+            ("DIVIDEND", TransactionType::Dividend),
+            ("CASH TOP-UP", TransactionType::Deposit),
+            ("CASH WITHDRAWAL", TransactionType::Withdrawal),
+            ("CUSTODY FEE", TransactionType::Fee),
+        ];
+
+        presets.insert(
+            "revolut".to_string(),
+            CsvMapping {
+                delimiter: ',',
+                time: "Date".into(),
+                time_format: None,
+                transaction_type: "Type".into(),
+                types: types.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+                asset: Some("Ticker".into()),
+                new_asset: None,
+                description: None,
+                quantity: "Quantity".into(),
+                amount: Some("Total Amount".into()),
+                price: Some("Price per share".into()),
+                currency: Some("Currency".into()),
+                fee: None,
+                external_id: None,
+                decimal_comma: false,
+            },
+        );
+
+        presets
+    }
+
+    /// Reads all the rows into transactions of a given account. Rows which can't be read
+    /// are reported individually, returned errors mean the file as a whole is unusable and
+    /// are safe to show to the user.
+    pub fn parse_csv(
+        &self,
+        csv: &str,
+        mapping: &CsvMapping,
+        account: &Account,
+    ) -> Result<Vec<ParsedRow>> {
+        ensure!(
+            mapping.delimiter.is_ascii(),
+            "Delimiter must be an ASCII character"
+        );
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(mapping.delimiter as u8)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv.trim_start_matches('\u{feff}').as_bytes());
+
+        let required: Vec<&String> = vec![
+            Some(&mapping.time),
+            Some(&mapping.transaction_type),
+            Some(&mapping.quantity),
+            mapping.asset.as_ref(),
+            mapping.amount.as_ref(),
+            mapping.price.as_ref(),
+            mapping.currency.as_ref(),
+            mapping.fee.as_ref(),
+            mapping.external_id.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let mut columns: Option<HashMap<String, usize>> = None;
+        let mut rows = vec![];
+
+        for record in reader.records() {
+            let record = record.map_err(|e| Error::msg(format!("Invalid CSV: {}", e)))?;
+            let line = record.position().map(|it| it.line()).unwrap_or(0);
+
+            let columns = match &columns {
+                Some(columns) => columns,
+                // Some sources put a preamble before the header
+                None => {
+                    let header: HashMap<String, usize> = record
+                        .iter()
+                        .enumerate()
+                        .map(|(i, it)| (it.trim().to_string(), i))
+                        .collect();
+
+                    if required.iter().all(|it| header.contains_key(it.as_str())) {
+                        columns = Some(header);
+                    }
+
+                    continue;
+                }
+            };
+
+            if record.iter().all(|it| it.trim().is_empty()) {
+                continue;
+            }
+
+            let field = |column: Option<&String>| -> Option<&str> {
+                column
+                    .and_then(|it| columns.get(it))
+                    .and_then(|it| record.get(*it))
+                    .map(|it| it.trim())
+                    .filter(|it| !it.is_empty())
+            };
+
+            let transaction = Self::parse_row(&field, mapping, account);
+            rows.push((line, transaction));
+        }
+
+        if columns.is_none() {
+            let required: Vec<&str> = required.iter().map(|it| it.as_str()).collect();
+            return Err(Error::msg(format!(
+                "Couldn't find a header with columns: {}",
+                required.join(", ")
+            )));
+        }
+
+        Ok(Self::identify(rows))
+    }
+
+    /// Reads the transactions of OFX and QFX bank statements, both SGML and XML flavours.
+    /// Returned errors are safe to show to the user.
+    pub fn parse_ofx(&self, ofx: &str, account: &Account) -> Result<Vec<ParsedRow>> {
+        let currency = Self::ofx_value(ofx, "CURDEF").unwrap_or_else(|| account.currency.clone());
+        let mut rows = vec![];
+        let mut rest = ofx;
+
+        while let Some(start) = rest.find("<STMTTRN>") {
+            let block = &rest[start + "<STMTTRN>".len()..];
+            let end = block.find("</STMTTRN>").unwrap_or(block.len());
+            let line = ofx[..ofx.len() - rest.len() + start].matches('\n').count() as u64 + 1;
+
+            rows.push((
+                line,
+                Self::parse_ofx_transaction(&block[..end], &currency, account),
+            ));
+            rest = &block[end..];
+        }
+
+        if rows.is_empty() && !ofx.contains("<OFX>") {
+            return Err(Error::msg("Not an OFX statement"));
+        }
+
+        Ok(Self::identify(rows))
+    }
+
+    /// Reads the transactions of QIF bank statements. QIF dates don't have a fixed format,
+    /// US style month/day/year is assumed if it's not given. Returned errors are safe to
+    /// show to the user.
+    pub fn parse_qif(
+        &self,
+        qif: &str,
+        date_format: Option<&str>,
+        account: &Account,
+    ) -> Result<Vec<ParsedRow>> {
+        let mut rows = vec![];
+        let mut fields: Vec<(char, &str)> = vec![];
+        let mut start = 1;
+
+        for (i, line) in qif.lines().enumerate() {
+            let line = line.trim();
+
+            if let Some(header) = line.strip_prefix("!Type:") {
+                if !["bank", "cash", "ccard"].contains(&header.trim().to_lowercase().as_str()) {
+                    return Err(Error::msg(format!(
+                        "Unsupported QIF statement type: {}",
+                        header
+                    )));
+                }
+
+                continue;
+            }
+
+            if line.starts_with('!') {
+                continue;
+            }
+
+            if line == "^" {
+                if !fields.is_empty() {
+                    rows.push((
+                        start,
+                        Self::parse_qif_transaction(&fields, date_format, account),
+                    ));
+                }
+
+                fields.clear();
+                start = i as u64 + 2;
+                continue;
+            }
+
+            if let Some(code) = line.chars().next() {
+                fields.push((code, &line[code.len_utf8()..]));
+            }
+        }
+
+        if rows.is_empty() && !qif.trim_start().starts_with('!') {
+            return Err(Error::msg("Not a QIF statement"));
+        }
+
+        Ok(Self::identify(rows))
+    }
+
+    /// Skips the rows which are already in the account. Rows matching a transaction which
+    /// wasn't imported reconcile it by giving it their external ID. New rows are
+    /// categorized by the rules of the account owner. Nothing is written on a dry run.
+    pub fn import(
+        &self,
+        account: &Account,
+        mut rows: Vec<ParsedRow>,
+        dry_run: bool,
+    ) -> Result<ImportReport> {
+        let mut parsed: Vec<&mut Transaction> = rows
+            .iter_mut()
+            .filter_map(|(_, it)| it.as_mut().ok())
+            .collect();
+        self.category_service
+            .categorize(&account.username, &mut parsed)?;
+
+        let (identified, mut unidentified): (Vec<Transaction>, Vec<Transaction>) = self
+            .transaction_repo
+            .select_by_account_id(&account.id)?
+            .into_iter()
+            .partition(|it| it.external_id.is_some());
+        let mut existing: HashSet<String> = identified
+            .into_iter()
+            .filter_map(|it| it.external_id)
+            .collect();
+
+        let mut report = ImportReport {
+            dry_run,
+            imported: 0,
+            duplicates: 0,
+            reconciled: 0,
+            errors: 0,
+            rows: vec![],
+        };
+        let mut transactions = vec![];
+        let mut reconciled = vec![];
+
+        for (line, transaction) in rows {
+            let (status, transaction, error) = match transaction {
+                Err(e) => (ImportStatus::Error, None, Some(e)),
+                Ok(transaction) => {
+                    let external_id = transaction.external_id.clone().unwrap_or_default();
+
+                    let matched = unidentified
+                        .iter()
+                        .position(|it| Self::is_same(it, &transaction));
+
+                    if existing.contains(&external_id) {
+                        (ImportStatus::Duplicate, Some(transaction), None)
+                    } else if let Some(matched) = matched {
+                        let matched = Transaction {
+                            external_id: transaction.external_id,
+                            ..unidentified.remove(matched)
+                        };
+                        existing.insert(external_id);
+                        reconciled.push(matched.clone());
+                        (ImportStatus::Reconciled, Some(matched), None)
+                    } else if let Err(e) = self.transaction_service.validate(&transaction) {
+                        (ImportStatus::Error, Some(transaction), Some(e.to_string()))
+                    } else {
+                        existing.insert(external_id);
+                        transactions.push(transaction.clone());
+                        (ImportStatus::Ok, Some(transaction), None)
+                    }
+                }
+            };
+
+            match status {
+                ImportStatus::Ok => report.imported += 1,
+                ImportStatus::Duplicate => report.duplicates += 1,
+                ImportStatus::Reconciled => report.reconciled += 1,
+                ImportStatus::Error => report.errors += 1,
+            }
+
+            report.rows.push(ImportRow {
+                line,
+                status,
+                transaction,
+                error,
+            });
+        }
+
+        if !dry_run {
+            self.transaction_service.insert_all(&transactions)?;
+
+            for transaction in reconciled {
+                self.transaction_repo.update(&transaction)?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn parse_row<'a, F>(
+        field: &F,
+        mapping: &CsvMapping,
+        account: &Account,
+    ) -> std::result::Result<Transaction, String>
+    where
+        F: Fn(Option<&String>) -> Option<&'a str>,
+    {
+        let type_value = field(Some(&mapping.transaction_type))
+            .ok_or_else(|| "Missing transaction type".to_string())?;
+        let transaction_type = mapping
+            .types
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(type_value))
+            .map(|(_, v)| *v)
+            .ok_or_else(|| format!("Unknown transaction type: {}", type_value))?;
+
+        // Both legs of a transfer have to be linked, which single rows can't do
+        if transaction_type == TransactionType::Transfer {
+            return Err("Transfers can't be imported, add them through /transfers".into());
+        }
+
+        let time = field(Some(&mapping.time)).ok_or_else(|| "Missing time".to_string())?;
+        let time = Self::parse_time(time, mapping.time_format.as_deref())?;
+
+        let is_cash = matches!(
+            transaction_type,
+            TransactionType::Deposit
+                | TransactionType::Withdrawal
+                | TransactionType::Fee
+                | TransactionType::Interest
+                | TransactionType::Dividend
+                | TransactionType::Tax
+        );
+
+        let quantity = match (is_cash, field(mapping.amount.as_ref())) {
+            (true, Some(amount)) => Some(amount),
+            _ => field(Some(&mapping.quantity)),
+        }
+        .ok_or_else(|| "Missing quantity".to_string())?;
+        let quantity = Self::parse_number(quantity, mapping.decimal_comma)?;
+
+        let quantity = quantity.abs();
+
+        let price = field(mapping.price.as_ref())
+            .map(|it| Self::parse_number(it, mapping.decimal_comma))
+            .transpose()?;
+
+        let fee = field(mapping.fee.as_ref())
+            .map(|it| Self::parse_number(it, mapping.decimal_comma))
+            .transpose()?
+            .map(f64::abs)
+            .unwrap_or(0.0);
+
+        let mut asset = field(mapping.asset.as_ref()).map(|it| it.to_string());
+        let mut currency = field(mapping.currency.as_ref())
+            .map(|it| it.to_string())
+            .unwrap_or_else(|| account.currency.clone());
+
+        // Sources tend to put the currency of cash movements into the asset column
+        if matches!(
+            transaction_type,
+            TransactionType::Deposit | TransactionType::Withdrawal | TransactionType::Interest
+        ) {
+            if let Some(cash) = asset.take() {
+                currency = cash;
+            }
+        }
+
+        Ok(Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type,
+            asset,
+            quantity,
+            price: if is_cash { None } else { price },
+            currency,
+            fee,
+            time,
+            lot_id: None,
+            external_id: field(mapping.external_id.as_ref()).map(|it| it.to_string()),
+            new_asset: field(mapping.new_asset.as_ref()).map(|it| it.to_string()),
+            transfer_id: None,
+            description: field(mapping.description.as_ref()).map(|it| it.to_string()),
+            category_id: None,
+            tags: vec![],
+        })
+    }
+
+    /// Accepts currency symbols and codes around the number, as well as thousands
+    /// separators
+    pub fn parse_number(value: &str, decimal_comma: bool) -> std::result::Result<f64, String> {
+        let number: String = value
+            .chars()
+            .filter(|it| !"$€£¥ \u{a0}".contains(*it))
+            .collect();
+        let number = number.trim_matches(|it: char| it.is_alphabetic());
+        let (number, negative) = match number.strip_prefix('(').and_then(|it| it.strip_suffix(')'))
+        {
+            Some(number) => (number, true),
+            None => (number, false),
+        };
+        let number = if decimal_comma {
+            number.replace('.', "").replace(',', ".")
+        } else {
+            number.replace(',', "")
+        };
+
+        number
+            .parse::<f64>()
+            .map(|it| if negative { -it } else { it })
+            .map_err(|_| format!("Invalid number: {}", value))
+    }
+
+    /// Times without a timezone are assumed to be in UTC and dates start at midnight
+    pub fn parse_time(
+        value: &str,
+        format: Option<&str>,
+    ) -> std::result::Result<DateTime<Utc>, String> {
+        let formats = match format {
+            Some(format) => {
+                if let Ok(time) = DateTime::parse_from_str(value, format) {
+                    return Ok(time.with_timezone(&Utc));
+                }
+
+                vec![format]
+            }
+            None => {
+                if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+                    return Ok(time.with_timezone(&Utc));
+                }
+
+                vec![
+                    "%Y-%m-%d %H:%M:%S",
+                    "%Y-%m-%d %H:%M:%S UTC",
+                    "%Y-%m-%dT%H:%M:%S",
+                    "%Y-%m-%d",
+                ]
+            }
+        };
+
+        for format in formats {
+            if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+                return Ok(Utc.from_utc_datetime(&time));
+            }
+
+            if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+                return Ok(Utc.from_utc_datetime(&date.and_hms(0, 0, 0)));
+            }
+        }
+
+        Err(format!("Invalid time: {}", value))
+    }
+
+    fn parse_ofx_transaction(
+        block: &str,
+        currency: &str,
+        account: &Account,
+    ) -> std::result::Result<Transaction, String> {
+        let trntype = Self::ofx_value(block, "TRNTYPE").unwrap_or_default();
+        let time =
+            Self::ofx_value(block, "DTPOSTED").ok_or_else(|| "Missing DTPOSTED".to_string())?;
+        let amount =
+            Self::ofx_value(block, "TRNAMT").ok_or_else(|| "Missing TRNAMT".to_string())?;
+        let amount = Self::parse_number(&amount, false)?;
+
+        // Dividends without a security are booked as interest since dividends need an asset
+        let transaction_type = match (trntype.to_uppercase().as_str(), amount < 0.0) {
+            ("INT", _) | ("DIV", _) => TransactionType::Interest,
+            ("FEE", _) | ("SRVCHG", _) => TransactionType::Fee,
+            (_, true) => TransactionType::Withdrawal,
+            (_, false) => TransactionType::Deposit,
+        };
+
+        Ok(Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type,
+            asset: None,
+            quantity: amount.abs(),
+            price: None,
+            currency: currency.into(),
+            fee: 0.0,
+            time: Self::parse_ofx_time(&time)?,
+            lot_id: None,
+            external_id: Self::ofx_value(block, "FITID"),
+            new_asset: None,
+            transfer_id: None,
+            description: Self::ofx_value(block, "NAME").or_else(|| Self::ofx_value(block, "MEMO")),
+            category_id: None,
+            tags: vec![],
+        })
+    }
+
+    /// Value of the first element with a given tag, closing tags are optional in OFX 1.x
+    fn ofx_value(ofx: &str, tag: &str) -> Option<String> {
+        let open = format!("<{}>", tag);
+        let start = ofx.find(&open)? + open.len();
+        let value = &ofx[start..];
+        let end = value.find('<').unwrap_or(value.len());
+        Some(value[..end].trim().to_string()).filter(|it| !it.is_empty())
+    }
+
+    /// OFX times look like 20210801120000.000[-5:EST], everything but the date is optional
+    fn parse_ofx_time(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+        let (time, offset) = match value.find('[') {
+            Some(i) => (&value[..i], Some(&value[i + 1..])),
+            None => (value, None),
+        };
+        let digits: String = time.chars().take_while(|it| it.is_ascii_digit()).collect();
+        let invalid = || format!("Invalid time: {}", value);
+
+        let date = NaiveDate::parse_from_str(digits.get(..8).ok_or_else(invalid)?, "%Y%m%d")
+            .map_err(|_| invalid())?;
+        let time = match digits.get(8..14) {
+            Some(time) => {
+                chrono::NaiveTime::parse_from_str(time, "%H%M%S").map_err(|_| invalid())?
+            }
+            None => chrono::NaiveTime::from_hms(0, 0, 0),
+        };
+        let offset_hours: f64 = match offset {
+            Some(offset) => offset
+                .split([':', ']'])
+                .next()
+                .and_then(|it| it.parse().ok())
+                .ok_or_else(invalid)?,
+            None => 0.0,
+        };
+
+        let time = Utc.from_utc_datetime(&date.and_time(time));
+        Ok(time - chrono::Duration::minutes((offset_hours * 60.0) as i64))
+    }
+
+    fn parse_qif_transaction(
+        fields: &[(char, &str)],
+        date_format: Option<&str>,
+        account: &Account,
+    ) -> std::result::Result<Transaction, String> {
+        let field = |code: char| {
+            fields
+                .iter()
+                .find(|(it, _)| *it == code)
+                .map(|(_, value)| value.trim())
+        };
+
+        let date = field('D').ok_or_else(|| "Missing date".to_string())?;
+        // Quicken writes years after 1999 as 1'21
+        let normalized = date.replace('\'', "/").replace(' ', "");
+        let time = match date_format {
+            Some(format) => Self::parse_time(&normalized, Some(format)),
+            None => Self::parse_time(&normalized, Some("%m/%d/%y"))
+                .or_else(|_| Self::parse_time(&normalized, Some("%m/%d/%Y"))),
+        }
+        .map_err(|_| format!("Invalid date: {}", date))?;
+
+        let amount = field('T')
+            .or_else(|| field('U'))
+            .ok_or_else(|| "Missing amount".to_string())?;
+        let amount = Self::parse_number(amount, false)?;
+
+        Ok(Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type: if amount < 0.0 {
+                TransactionType::Withdrawal
+            } else {
+                TransactionType::Deposit
+            },
+            asset: None,
+            quantity: amount.abs(),
+            price: None,
+            currency: account.currency.clone(),
+            fee: 0.0,
+            time,
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: field('P')
+                .or_else(|| field('M'))
+                .filter(|it| !it.is_empty())
+                .map(|it| it.to_string()),
+            category_id: None,
+            tags: vec![],
+        })
+    }
+
+    /// Rows without an external ID are identified by a hash of their contents
+    fn identify(rows: Vec<ParsedRow>) -> Vec<ParsedRow> {
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+        rows.into_iter()
+            .map(|(line, transaction)| {
+                let transaction = transaction.map(|mut transaction| {
+                    if transaction.external_id.is_none() {
+                        let contents = Self::contents(&transaction);
+                        let occurrence = occurrences.entry(contents.clone()).or_insert(0);
+                        *occurrence += 1;
+                        transaction.external_id = Some(Self::hash(&contents, *occurrence));
+                    }
+
+                    transaction
+                });
+
+                (line, transaction)
+            })
+            .collect()
+    }
+
+    /// Same entries made outside of an import, such as manual ones
+    fn is_same(existing: &Transaction, imported: &Transaction) -> bool {
+        existing.transaction_type == imported.transaction_type
+            && existing.asset == imported.asset
+            && existing.currency == imported.currency
+            && (existing.quantity - imported.quantity).abs() < 1e-9
+            && existing.time.date() == imported.time.date()
+    }
+
+    fn contents(transaction: &Transaction) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            transaction.transaction_type,
+            transaction.asset.as_deref().unwrap_or(""),
+            transaction.quantity,
+            transaction
+                .price
+                .map(|it| it.to_string())
+                .unwrap_or_default(),
+            transaction.currency,
+            transaction.fee,
+            transaction.time.to_rfc3339(),
+        )
+    }
+
+    /// Identical rows within a file are told apart by their occurrence, so importing the
+    /// same file twice doesn't duplicate them but they still get imported once each
+    fn hash(contents: &str, occurrence: usize) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}|{}", contents, occurrence).as_bytes());
+        format!("sha256:{}", hex::encode(hasher.finalize()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, CostBasisMethod, CsvMapping, Export, ExportFormat, Id,
+            ImportStatus, Transaction, TransactionType,
+        },
+        repository::{
+            AccountRepository, AssetRepository, BudgetRepository, CategoryRepository,
+            CategoryRuleRepository, LotMatchRepository, LotRepository, PortfolioSnapshotRepository,
+            RecurringTransactionRepository, TransactionRepository,
+        },
+        service::{
+            asset::AssetConf, AssetService, CategoryService, ExportService, ImportService,
+            LotService, TransactionService,
+        },
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{TimeZone, Utc};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn parse_number() {
+        assert_eq!(Ok(1234.5), ImportService::parse_number("1,234.5", false));
+        assert_eq!(Ok(1234.5), ImportService::parse_number("1.234,5", true));
+        assert_eq!(Ok(-12.5), ImportService::parse_number("-$12.5", false));
+        assert_eq!(Ok(12.5), ImportService::parse_number("USD 12.5", false));
+        assert_eq!(Ok(-12.5), ImportService::parse_number("(12.5)", false));
+        assert!(ImportService::parse_number("abc", false).is_err());
+    }
+
+    #[test]
+    fn parse_time() {
+        let time = Utc.ymd(2021, 8, 1).and_hms(12, 0, 0);
+        assert_eq!(
+            Ok(time),
+            ImportService::parse_time("2021-08-01T14:00:00+02:00", None)
+        );
+        assert_eq!(
+            Ok(time),
+            ImportService::parse_time("2021-08-01 12:00:00 UTC", None)
+        );
+        assert_eq!(
+            Ok(Utc.ymd(2021, 8, 1).and_hms(0, 0, 0)),
+            ImportService::parse_time("01.08.2021", Some("%d.%m.%Y"))
+        );
+        assert!(ImportService::parse_time("yesterday", None).is_err());
+    }
+
+    #[test]
+    fn parse_csv_coinbase() -> Result<()> {
+        let service = service();
+        let csv = "You can use this transaction report to inform your likely tax obligations.\n\
+            \n\
+            Timestamp,Transaction Type,Asset,Quantity Transacted,Spot Price Currency,Spot Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes\n\
+            2021-08-01T12:00:00Z,Deposit,USD,100,USD,1,100,100,0,\n\
+            2021-08-01T13:00:00Z,Buy,BTC,0.002,USD,40000,80,81,1,\n\
+            2021-08-02T12:00:00Z,Send,BTC,0.001,USD,41000,,,0,\n\
+            2021-08-03T12:00:00Z,Convert,BTC,0.001,USD,42000,,,0,\n";
+        let mapping = ImportService::presets().remove("coinbase").unwrap();
+        let rows = service.parse_csv(csv, &mapping, &account())?;
+        assert_eq!(4, rows.len());
+
+        let deposit = rows[0].1.as_ref().unwrap();
+        assert_eq!(4, rows[0].0);
+        assert_eq!(TransactionType::Deposit, deposit.transaction_type);
+        assert_eq!(None, deposit.asset);
+        assert_eq!("USD", deposit.currency);
+        assert!(deposit.external_id.as_ref().unwrap().starts_with("sha256:"));
+
+        let buy = rows[1].1.as_ref().unwrap();
+        assert_eq!(Some("BTC".to_string()), buy.asset);
+        assert_eq!(Some(40000.0), buy.price);
+        assert_eq!(1.0, buy.fee);
+
+        assert_eq!(
+            Err("Transfers can't be imported, add them through /transfers".to_string()),
+            rows[2].1
+        );
+        assert_eq!(
+            Err("Unknown transaction type: Convert".to_string()),
+            rows[3].1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_coinbase() -> Result<()> {
+        let service = service();
+        let csv = "Timestamp,Transaction Type,Asset,Quantity Transacted,Spot Price Currency,Spot Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes\n\
+            2021-08-01T12:00:00Z,Deposit,USD,100,USD,1,100,100,0,\n\
+            2021-08-01T13:00:00Z,Buy,BTC,0.002,USD,40000,80,81,1,\n\
+            2021-08-02T12:00:00Z,Send,BTC,0.001,USD,41000,,,0,\n\
+            2021-08-03T12:00:00Z,Receive,BTC,0.001,USD,42000,,,0,\n";
+        let mapping = ImportService::presets().remove("coinbase").unwrap();
+        let rows = service.parse_csv(csv, &mapping, &account())?;
+        let report = service.import(&account(), rows, false)?;
+        assert_eq!(2, report.imported);
+        assert_eq!(2, report.errors);
+        assert_eq!(
+            vec![
+                ImportStatus::Ok,
+                ImportStatus::Ok,
+                ImportStatus::Error,
+                ImportStatus::Error
+            ],
+            report.rows.iter().map(|it| it.status).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_pfd_export() -> Result<()> {
+        let service = service();
+        let account = account();
+        let transaction = |transaction_type, asset: Option<&str>, quantity, price| Transaction {
+            id: Id::new(),
+            account_id: account.id.clone(),
+            transaction_type,
+            asset: asset.map(|it| it.into()),
+            quantity,
+            price,
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: Some("Savings, monthly".into()),
+            category_id: None,
+            tags: vec![],
+        };
+        let transfer = Transaction {
+            transfer_id: Some(Id::new()),
+            ..transaction(TransactionType::Transfer, Some("BTC"), -1.0, None)
+        };
+        let export = Export {
+            accounts: vec![account.clone()],
+            transactions: vec![
+                transaction(TransactionType::Deposit, None, 1000.0, None),
+                transaction(TransactionType::Buy, Some("BTC"), 2.0, Some(100.0)),
+                transfer,
+            ],
+            rates: vec![],
+        };
+        let zip = ExportService::render(&export, ExportFormat::Csv)?;
+        let mut csv = String::new();
+        ZipArchive::new(Cursor::new(zip))?
+            .by_name("transactions.csv")?
+            .read_to_string(&mut csv)?;
+
+        let mapping = ImportService::presets().remove("pfd").unwrap();
+        let rows = service.parse_csv(&csv, &mapping, &account)?;
+        let report = service.import(&account, rows, false)?;
+        assert_eq!(2, report.imported);
+        assert_eq!(1, report.errors);
+        for (row, exported) in report.rows.iter().zip(&export.transactions).take(2) {
+            let imported = row.transaction.as_ref().unwrap();
+            assert_eq!(exported.transaction_type, imported.transaction_type);
+            assert_eq!(exported.asset, imported.asset);
+            assert_eq!(exported.quantity, imported.quantity);
+            assert_eq!(exported.description, imported.description);
+            assert_eq!(Some(exported.id.to_string()), imported.external_id);
+        }
+
+        let rows = service.parse_csv(&csv, &mapping, &account)?;
+        let report = service.import(&account, rows, false)?;
+        assert_eq!(0, report.imported);
+        assert_eq!(2, report.duplicates);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_csv_identical_rows() -> Result<()> {
+        let service = service();
+        let csv = "time,type,asset,quantity,price,currency,fee,external_id\n\
+            2021-08-01T12:00:00Z,buy,BTC,1,100,USD,0,\n\
+            2021-08-01T12:00:00Z,buy,BTC,1,100,USD,0,\n";
+        let mapping = ImportService::presets().remove("pfd").unwrap();
+        let rows = service.parse_csv(csv, &mapping, &account())?;
+        let first = rows[0].1.as_ref().unwrap().external_id.clone();
+        let second = rows[1].1.as_ref().unwrap().external_id.clone();
+        assert_ne!(first, second);
+        let again = service.parse_csv(csv, &mapping, &account())?;
+        assert_eq!(first, again[0].1.as_ref().unwrap().external_id);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_csv_non_ascii_delimiter() {
+        let mapping = CsvMapping {
+            delimiter: '§',
+            ..ImportService::presets().remove("pfd").unwrap()
+        };
+        let res = service().parse_csv("time§type\n", &mapping, &account());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn parse_csv_missing_header() {
+        let mapping = ImportService::presets().remove("pfd").unwrap();
+        let res = service().parse_csv("a,b,c\n1,2,3\n", &mapping, &account());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn parse_ofx() -> Result<()> {
+        let ofx = r#"<?xml version="1.0"?>
+<OFX>
+  <BANKMSGSRSV1><STMTTRNRS><STMTRS>
+    <CURDEF>EUR</CURDEF>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>INT</TRNTYPE>
+        <DTPOSTED>20210801120000.000[-5:EST]</DTPOSTED>
+        <TRNAMT>1.50</TRNAMT>
+        <FITID>2021080101</FITID>
+      </STMTTRN>
+      <STMTTRN>
+        <TRNTYPE>SRVCHG</TRNTYPE>
+        <DTPOSTED>20210802</DTPOSTED>
+        <TRNAMT>-3</TRNAMT>
+        <FITID>2021080201</FITID>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>"#;
+        let rows = service().parse_ofx(ofx, &account())?;
+        assert_eq!(2, rows.len());
+        assert_eq!(6, rows[0].0);
+
+        let interest = rows[0].1.as_ref().unwrap();
+        assert_eq!(TransactionType::Interest, interest.transaction_type);
+        assert_eq!(1.5, interest.quantity);
+        assert_eq!("EUR", interest.currency);
+        assert_eq!(Utc.ymd(2021, 8, 1).and_hms(17, 0, 0), interest.time);
+        assert_eq!(Some("2021080101".to_string()), interest.external_id);
+
+        let fee = rows[1].1.as_ref().unwrap();
+        assert_eq!(TransactionType::Fee, fee.transaction_type);
+        assert_eq!(3.0, fee.quantity);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_qif() -> Result<()> {
+        let qif = "!Type:Bank\nD8/ 1'21\nT1,000.00\nPSalary\n^\nD08/02/2021\nT-20.00\n^\nDtomorrow\nT1\n^\n";
+        let rows = service().parse_qif(qif, None, &account())?;
+        assert_eq!(3, rows.len());
+
+        let deposit = rows[0].1.as_ref().unwrap();
+        assert_eq!(TransactionType::Deposit, deposit.transaction_type);
+        assert_eq!(1000.0, deposit.quantity);
+        assert_eq!(Utc.ymd(2021, 8, 1).and_hms(0, 0, 0), deposit.time);
+
+        let withdrawal = rows[1].1.as_ref().unwrap();
+        assert_eq!(6, rows[1].0);
+        assert_eq!(TransactionType::Withdrawal, withdrawal.transaction_type);
+        assert_eq!(20.0, withdrawal.quantity);
+
+        assert_eq!(Err("Invalid date: tomorrow".to_string()), rows[2].1);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_qif_investment() {
+        let qif = "!Type:Invst\nD8/1/21\nNBuy\n^\n";
+        assert!(service().parse_qif(qif, None, &account()).is_err());
+    }
+
+    fn service() -> ImportService {
+        let pool = pool();
+        let transaction_repo = TransactionRepository::new(&pool);
+        let lot_service = LotService::new(
+            &LotRepository::new(&pool),
+            &LotMatchRepository::new(&pool),
+            &AccountRepository::new(&pool),
+            &transaction_repo,
+        );
+        let transaction_service = TransactionService::new(
+            &transaction_repo,
+            &PortfolioSnapshotRepository::new(&pool),
+            &AssetService::new(&AssetRepository::new(&pool), &AssetConf { admins: vec![] }),
+            &lot_service,
+        );
+        let category_service = CategoryService::new(
+            &CategoryRepository::new(&pool),
+            &CategoryRuleRepository::new(&pool),
+            &transaction_repo,
+            &BudgetRepository::new(&pool),
+            &RecurringTransactionRepository::new(&pool),
+        );
+        ImportService::new(&transaction_repo, &transaction_service, &category_service)
+    }
+
+    fn account() -> Account {
+        Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Exchange".into(),
+            account_type: AccountType::Wallet,
+            currency: "USD".into(),
+            opened_at: chrono::NaiveDate::from_ymd(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        }
+    }
+}
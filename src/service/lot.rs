@@ -0,0 +1,546 @@
+use crate::{
+    model::{Account, CostBasisMethod, Id, Lot, LotMatch, Transaction, TransactionType},
+    repository::{AccountRepository, LotMatchRepository, LotRepository, TransactionRepository},
+    service::holding::DUST,
+};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct LotService {
+    repo: LotRepository,
+    match_repo: LotMatchRepository,
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+}
+
+impl LotService {
+    pub fn new(
+        repo: &LotRepository,
+        match_repo: &LotMatchRepository,
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+    ) -> LotService {
+        LotService {
+            repo: repo.clone(),
+            match_repo: match_repo.clone(),
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+        }
+    }
+
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<Lot>> {
+        self.repo.select_by_account_id(account_id)
+    }
+
+    pub fn delete_by_account_id(&self, account_id: &Id) -> Result<()> {
+        self.match_repo.delete_by_account_id(account_id)?;
+        self.repo.delete_by_account_id(account_id)
+    }
+
+    /// Replays the ledgers of all the accounts of the owner and replaces their lots and
+    /// matches, so they stay consistent after earlier transactions get changed. Transfers
+    /// move lots between accounts, so the ledgers can't be replayed one by one. Matches are
+    /// recomputed from the whole history, so changing an old transaction can also change
+    /// the matches, and the realized gains, of periods which were already reported.
+    pub fn rebuild(&self, account_id: &Id) -> Result<()> {
+        self.rebuild_all(std::slice::from_ref(account_id))
+    }
+
+    /// Same as `rebuild` but replays the accounts of each owner only once
+    pub fn rebuild_all(&self, account_ids: &[Id]) -> Result<()> {
+        let mut usernames: Vec<String> = vec![];
+
+        for account_id in account_ids {
+            if let Some(account) = self.account_repo.select_by_id(account_id)? {
+                if !usernames.contains(&account.username) {
+                    usernames.push(account.username);
+                }
+            }
+        }
+
+        for username in usernames {
+            self.rebuild_by_username(&username)?;
+        }
+
+        Ok(())
+    }
+
+    /// Same as `rebuild` but for all the accounts of a user
+    pub fn rebuild_by_username(&self, username: &str) -> Result<()> {
+        let accounts = self.account_repo.select_by_username(username)?;
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        let (lots, matches) = Self::match_accounts(&accounts, &transactions);
+        let account_ids: Vec<Id> = accounts.into_iter().map(|it| it.id).collect();
+        self.repo
+            .replace_by_account_ids(&account_ids, &lots, &matches)
+    }
+
+    /// Same as `match_lots` but every account uses its own cost basis method
+    pub fn match_accounts(
+        accounts: &[Account],
+        transactions: &[Transaction],
+    ) -> (Vec<Lot>, Vec<LotMatch>) {
+        Self::replay(transactions, |account_id| {
+            accounts
+                .iter()
+                .find(|it| &it.id == account_id)
+                .map(|it| it.cost_basis_method)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Buys, reinvested dividends and incoming transfers open lots, sells and outgoing
+    /// transfers consume them. Only sells are recorded as matches since transfers don't
+    /// realize any gains. Corporate actions adjust the lots in place, spin-offs also open
+    /// lots of the new asset which inherit a share of the cost basis and the acquisition
+    /// time.
+    ///
+    /// Transactions may span several accounts of a user, ordered by time. Lots consumed by
+    /// an outgoing transfer move to the account of the incoming leg, along with their cost
+    /// and acquisition time. Incoming transfers which can't be traced back open lots
+    /// without a cost basis.
+    pub fn match_lots(
+        transactions: &[Transaction],
+        method: CostBasisMethod,
+    ) -> (Vec<Lot>, Vec<LotMatch>) {
+        Self::replay(transactions, |_| method)
+    }
+
+    fn replay<F>(transactions: &[Transaction], method: F) -> (Vec<Lot>, Vec<LotMatch>)
+    where
+        F: Fn(&Id) -> CostBasisMethod,
+    {
+        let mut lots: Vec<Lot> = vec![];
+        let mut matches = vec![];
+        // Lots which left an account, by transfer ID
+        let mut moving: HashMap<Id, Vec<Lot>> = HashMap::new();
+
+        for transaction in transactions {
+            let account_id = &transaction.account_id;
+            let asset = match &transaction.asset {
+                Some(asset) => asset,
+                None => continue,
+            };
+
+            match transaction.transaction_type {
+                TransactionType::Buy | TransactionType::DividendReinvestment => {
+                    lots.push(Self::open(transaction, asset))
+                }
+                TransactionType::Transfer if transaction.quantity > 0.0 => {
+                    let moved = transaction
+                        .transfer_id
+                        .as_ref()
+                        .and_then(|it| moving.remove(it))
+                        .unwrap_or_default();
+                    let uncovered =
+                        transaction.quantity - moved.iter().map(|it| it.remaining).sum::<f64>();
+
+                    for lot in moved {
+                        lots.push(Lot {
+                            id: Self::derive_id(&lot.id, &transaction.id),
+                            account_id: account_id.clone(),
+                            ..lot
+                        });
+                    }
+
+                    if uncovered > DUST {
+                        lots.push(Lot {
+                            quantity: uncovered,
+                            remaining: uncovered,
+                            ..Self::open(transaction, asset)
+                        });
+                    }
+                }
+                TransactionType::Transfer => {
+                    let parts = Self::consume(
+                        &mut lots,
+                        account_id,
+                        asset,
+                        -transaction.quantity,
+                        method(account_id),
+                        None,
+                    );
+
+                    if let Some(transfer_id) = &transaction.transfer_id {
+                        let moved = parts
+                            .into_iter()
+                            .map(|(index, quantity, cost)| Lot {
+                                quantity,
+                                remaining: quantity,
+                                cost,
+                                ..lots[index].clone()
+                            })
+                            .collect();
+                        moving.insert(transfer_id.clone(), moved);
+                    }
+                }
+                TransactionType::Sell => {
+                    let proceeds =
+                        transaction.quantity * transaction.price.unwrap_or(0.0) - transaction.fee;
+
+                    let parts = Self::consume(
+                        &mut lots,
+                        account_id,
+                        asset,
+                        transaction.quantity,
+                        method(account_id),
+                        transaction.lot_id.as_ref(),
+                    );
+
+                    for (index, quantity, cost) in parts {
+                        let lot = &lots[index];
+
+                        matches.push(LotMatch {
+                            id: Self::match_id(&lot.id, &transaction.id),
+                            account_id: account_id.clone(),
+                            sell_id: transaction.id.clone(),
+                            lot_id: lot.id.clone(),
+                            asset: asset.clone(),
+                            quantity,
+                            cost,
+                            cost_currency: lot.currency.clone(),
+                            proceeds: proceeds * quantity / transaction.quantity,
+                            proceeds_currency: transaction.currency.clone(),
+                            acquired_at: lot.acquired_at,
+                            sold_at: transaction.time,
+                        });
+                    }
+                }
+                TransactionType::Split => {
+                    for lot in lots
+                        .iter_mut()
+                        .filter(|it| &it.account_id == account_id && &it.asset == asset)
+                    {
+                        lot.quantity *= transaction.quantity;
+                        lot.remaining *= transaction.quantity;
+                    }
+                }
+                TransactionType::TickerChange => {
+                    let new_asset = match &transaction.new_asset {
+                        Some(new_asset) => new_asset,
+                        None => continue,
+                    };
+
+                    for lot in lots
+                        .iter_mut()
+                        .filter(|it| &it.account_id == account_id && &it.asset == asset)
+                    {
+                        lot.asset = new_asset.clone();
+                        lot.quantity *= transaction.quantity;
+                        lot.remaining *= transaction.quantity;
+                    }
+                }
+                TransactionType::SpinOff => {
+                    let new_asset = match &transaction.new_asset {
+                        Some(new_asset) => new_asset,
+                        None => continue,
+                    };
+                    let share = transaction.price.unwrap_or(0.0);
+                    let mut spun_off = vec![];
+
+                    for lot in lots.iter_mut().filter(|it| {
+                        &it.account_id == account_id && &it.asset == asset && it.remaining > DUST
+                    }) {
+                        let moved = lot.cost * lot.remaining / lot.quantity * share;
+                        lot.cost *= 1.0 - share;
+
+                        spun_off.push(Lot {
+                            id: Self::spun_off_id(&lot.id, &transaction.id),
+                            account_id: lot.account_id.clone(),
+                            asset: new_asset.clone(),
+                            quantity: lot.remaining * transaction.quantity,
+                            remaining: lot.remaining * transaction.quantity,
+                            cost: moved,
+                            currency: lot.currency.clone(),
+                            acquired_at: lot.acquired_at,
+                        });
+                    }
+
+                    lots.extend(spun_off);
+                }
+                _ => {}
+            }
+        }
+
+        (lots, matches)
+    }
+
+    /// Lots of a spun-off asset keep their IDs across rebuilds, so sells can point to them
+    fn spun_off_id(lot_id: &Id, spin_off_id: &Id) -> Id {
+        Self::derive_id(lot_id, spin_off_id)
+    }
+
+    /// Matches keep their IDs across rebuilds, so reports can refer to them
+    fn match_id(lot_id: &Id, sell_id: &Id) -> Id {
+        Self::derive_id(lot_id, sell_id)
+    }
+
+    fn derive_id(first: &Id, second: &Id) -> Id {
+        let digest = Sha256::digest(format!("{}{}", first, second).as_bytes());
+        Uuid::from_slice(&digest[..16]).unwrap().into()
+    }
+
+    fn open(transaction: &Transaction, asset: &str) -> Lot {
+        Lot {
+            id: transaction.id.clone(),
+            account_id: transaction.account_id.clone(),
+            asset: asset.into(),
+            quantity: transaction.quantity,
+            remaining: transaction.quantity,
+            cost: transaction.quantity * transaction.price.unwrap_or(0.0) + transaction.fee,
+            currency: transaction.currency.clone(),
+            acquired_at: transaction.time,
+        }
+    }
+
+    /// Takes a quantity out of the open lots of an asset. Returns lot index, quantity and
+    /// cost of every consumed part.
+    fn consume(
+        lots: &mut [Lot],
+        account_id: &Id,
+        asset: &str,
+        quantity: f64,
+        method: CostBasisMethod,
+        lot_id: Option<&Id>,
+    ) -> Vec<(usize, f64, f64)> {
+        let mut open: Vec<usize> = lots
+            .iter()
+            .enumerate()
+            .filter(|(_, it)| {
+                &it.account_id == account_id && it.asset == asset && it.remaining > DUST
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        match method {
+            CostBasisMethod::Fifo => {}
+            CostBasisMethod::Lifo => open.reverse(),
+            CostBasisMethod::Specific => {
+                let position = lot_id.and_then(|id| open.iter().position(|&it| &lots[it].id == id));
+
+                if let Some(position) = position {
+                    let index = open.remove(position);
+                    open.insert(0, index);
+                }
+            }
+            CostBasisMethod::Average => {
+                let total: f64 = open.iter().map(|&it| lots[it].remaining).sum();
+
+                if total <= DUST {
+                    warn!(asset, %quantity, "No lots to cover a disposal");
+                    return vec![];
+                }
+
+                let share = (quantity / total).min(1.0);
+
+                return open
+                    .into_iter()
+                    .map(|index| {
+                        let lot = &mut lots[index];
+                        let quantity = lot.remaining * share;
+                        lot.remaining -= quantity;
+                        (index, quantity, lot.cost * quantity / lot.quantity)
+                    })
+                    .collect();
+            }
+        }
+
+        let mut left = quantity;
+        let mut parts = vec![];
+
+        for index in open {
+            if left <= DUST {
+                break;
+            }
+
+            let lot = &mut lots[index];
+            let quantity = lot.remaining.min(left);
+            lot.remaining -= quantity;
+            left -= quantity;
+            parts.push((index, quantity, lot.cost * quantity / lot.quantity));
+        }
+
+        if left > DUST {
+            warn!(asset, quantity = %left, "Not enough lots to cover a disposal");
+        }
+
+        parts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{CostBasisMethod, Id, Lot, LotMatch, Transaction, TransactionType},
+        service::LotService,
+    };
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn match_lots_fifo() {
+        let transactions = transactions();
+        let (lots, matches) = LotService::match_lots(&transactions, CostBasisMethod::Fifo);
+        assert_eq!(vec![0.0, 1.0], remaining(&lots));
+        assert_eq!(1, matches.len());
+        assert_eq!(transactions[0].id, matches[0].lot_id);
+        assert_eq!(101.0, matches[0].cost);
+        assert_eq!(299.0, matches[0].proceeds);
+    }
+
+    #[test]
+    fn match_lots_lifo() {
+        let transactions = transactions();
+        let (lots, matches) = LotService::match_lots(&transactions, CostBasisMethod::Lifo);
+        assert_eq!(vec![1.0, 0.0], remaining(&lots));
+        assert_eq!(transactions[1].id, matches[0].lot_id);
+        assert_eq!(201.0, matches[0].cost);
+    }
+
+    #[test]
+    fn match_lots_average() {
+        let transactions = transactions();
+        let (lots, matches) = LotService::match_lots(&transactions, CostBasisMethod::Average);
+        assert_eq!(vec![0.5, 0.5], remaining(&lots));
+        assert_eq!(2, matches.len());
+        assert_eq!(151.0, matches.iter().map(|it| it.cost).sum::<f64>());
+        assert_eq!(299.0, matches.iter().map(|it| it.proceeds).sum::<f64>());
+    }
+
+    #[test]
+    fn match_lots_stable_ids() {
+        let transactions = transactions();
+        let (_, first) = LotService::match_lots(&transactions, CostBasisMethod::Average);
+        let (_, second) = LotService::match_lots(&transactions, CostBasisMethod::Average);
+        let ids = |matches: &[LotMatch]| matches.iter().map(|it| it.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&second));
+        assert_ne!(first[0].id, first[1].id);
+    }
+
+    #[test]
+    fn match_lots_specific() {
+        let mut transactions = transactions();
+        transactions[2].lot_id = Some(transactions[1].id.clone());
+        let (lots, matches) = LotService::match_lots(&transactions, CostBasisMethod::Specific);
+        assert_eq!(vec![1.0, 0.0], remaining(&lots));
+        assert_eq!(transactions[1].id, matches[0].lot_id);
+    }
+
+    #[test]
+    fn match_lots_transfer_out() {
+        let mut transactions = transactions();
+        transactions[2].transaction_type = TransactionType::Transfer;
+        transactions[2].quantity = -1.5;
+        transactions[2].price = None;
+        let (lots, matches) = LotService::match_lots(&transactions, CostBasisMethod::Fifo);
+        assert_eq!(vec![0.0, 0.5], remaining(&lots));
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn match_lots_transfer_between_accounts() {
+        let mut transactions = transactions();
+        transactions.truncate(1);
+        let transfer_id = Some(Id::new());
+        let outgoing = Transaction {
+            id: Id::new(),
+            transaction_type: TransactionType::Transfer,
+            quantity: -1.0,
+            price: None,
+            fee: 0.0,
+            transfer_id: transfer_id.clone(),
+            ..transactions[0].clone()
+        };
+        let incoming = Transaction {
+            id: Id::new(),
+            account_id: Id::new(),
+            quantity: 1.0,
+            time: Utc.ymd(2021, 9, 1).and_hms(12, 0, 0),
+            ..outgoing.clone()
+        };
+        transactions.extend(vec![outgoing, incoming.clone()]);
+
+        let (lots, _) = LotService::match_lots(&transactions, CostBasisMethod::Fifo);
+        assert_eq!(vec![0.0, 1.0], remaining(&lots));
+        assert_eq!(incoming.account_id, lots[1].account_id);
+        assert_eq!(101.0, lots[1].cost);
+        assert_eq!(transactions[0].time, lots[1].acquired_at);
+    }
+
+    #[test]
+    fn match_lots_corporate_actions() {
+        let mut transactions = transactions();
+        transactions.truncate(2);
+        let action =
+            |transaction_type, quantity, price: Option<f64>, new_asset: &str| Transaction {
+                id: Id::new(),
+                transaction_type,
+                quantity,
+                price,
+                fee: 0.0,
+                new_asset: Some(new_asset.into()),
+                ..transactions[0].clone()
+            };
+        let split = Transaction {
+            new_asset: None,
+            ..action(TransactionType::Split, 2.0, None, "")
+        };
+        let spin_off = action(TransactionType::SpinOff, 0.5, Some(0.25), "SPIN");
+        let ticker_change = Transaction {
+            asset: Some("SPIN".into()),
+            ..action(TransactionType::TickerChange, 1.0, None, "NEW")
+        };
+        transactions.extend(vec![split, spin_off, ticker_change.clone()]);
+
+        let (lots, _) = LotService::match_lots(&transactions, CostBasisMethod::Fifo);
+        assert_eq!(4, lots.len());
+        assert_eq!(vec![2.0, 2.0, 1.0, 1.0], remaining(&lots));
+        assert_eq!(101.0 * 0.75, lots[0].cost);
+        assert_eq!("NEW", lots[2].asset);
+        assert_eq!(101.0 * 0.25, lots[2].cost);
+        assert_eq!(lots[0].acquired_at, lots[2].acquired_at);
+        assert_eq!(
+            lots[2].id,
+            LotService::match_lots(&transactions, CostBasisMethod::Fifo).0[2].id
+        );
+    }
+
+    fn remaining(lots: &[Lot]) -> Vec<f64> {
+        lots.iter().map(|it| it.remaining).collect()
+    }
+
+    /// Two buys of one unit at 100 and 200, then a sell of one unit at 300
+    fn transactions() -> Vec<Transaction> {
+        let account_id = Id::new();
+
+        vec![
+            (TransactionType::Buy, 100.0),
+            (TransactionType::Buy, 200.0),
+            (TransactionType::Sell, 300.0),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(day, (transaction_type, price))| Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type,
+            asset: Some("BTC".into()),
+            quantity: 1.0,
+            price: Some(price),
+            currency: "USD".into(),
+            fee: 1.0,
+            time: Utc.ymd(2021, 8, day as u32 + 1).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        })
+        .collect()
+    }
+}
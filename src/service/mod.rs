@@ -1,6 +1,52 @@
+pub mod account;
+pub use account::AccountService;
+pub mod alert;
+pub use alert::AlertService;
+pub mod allocation;
+pub use allocation::AllocationService;
+pub mod asset;
+pub use asset::AssetService;
 pub mod auth_token;
 pub use auth_token::AuthTokenService;
+pub mod budget;
+pub use budget::BudgetService;
+pub mod cashflow;
+pub use cashflow::CashflowService;
+pub mod category;
+pub use category::CategoryService;
 pub mod exchange_rate;
 pub use exchange_rate::ExchangeRateService;
+pub mod export;
+pub use export::ExportService;
+pub mod gains;
+pub use gains::GainsService;
+pub mod goal;
+pub use goal::GoalService;
+pub mod holding;
+pub use holding::HoldingService;
+pub mod import;
+pub use import::ImportService;
+pub mod lot;
+pub use lot::LotService;
+pub mod performance;
+pub use performance::PerformanceService;
+pub mod recurring;
+pub use recurring::RecurringService;
+pub mod snapshot;
+pub use snapshot::SnapshotService;
+pub mod target;
+pub use target::TargetService;
+pub mod tax;
+pub use tax::TaxService;
+pub mod transaction;
+pub use transaction::TransactionService;
+pub mod transfer;
+pub use transfer::TransferService;
 pub mod user;
 pub use user::UserService;
+pub mod valuation;
+pub use valuation::ValuationService;
+pub mod watchlist;
+pub use watchlist::WatchlistService;
+pub mod webhook;
+pub use webhook::WebhookService;
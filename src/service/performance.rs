@@ -0,0 +1,273 @@
+use crate::{
+    model::{Id, Performance, Transaction, TransactionType},
+    repository::TransactionRepository,
+    service::{HoldingService, ValuationService},
+};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+#[derive(Clone)]
+pub struct PerformanceService {
+    transaction_repo: TransactionRepository,
+    valuation_service: ValuationService,
+}
+
+impl PerformanceService {
+    pub fn new(
+        transaction_repo: &TransactionRepository,
+        valuation_service: &ValuationService,
+    ) -> PerformanceService {
+        PerformanceService {
+            transaction_repo: transaction_repo.clone(),
+            valuation_service: valuation_service.clone(),
+        }
+    }
+
+    pub fn select_by_username(
+        &self,
+        username: &str,
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: NaiveDate,
+    ) -> Result<Performance> {
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        self.performance(&transactions, currency, from, to)
+    }
+
+    pub fn select_by_account_id(
+        &self,
+        account_id: &Id,
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: NaiveDate,
+    ) -> Result<Performance> {
+        let transactions = self.transaction_repo.select_by_account_id(account_id)?;
+        self.performance(&transactions, currency, from, to)
+    }
+
+    /// Period starts with the first transaction when there is no start date. Flows are
+    /// assumed to happen at the start of their days.
+    pub fn performance(
+        &self,
+        transactions: &[Transaction],
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: NaiveDate,
+    ) -> Result<Performance> {
+        let from = from
+            .or_else(|| transactions.first().map(|it| it.time.date().naive_utc()))
+            .unwrap_or(to);
+        let before = from - Duration::days(1);
+
+        let flows = self.flows(transactions, currency, &from, &to)?;
+
+        let start_value = self.value(transactions, currency, &before)?;
+        let mut values = vec![(before, start_value, 0.0)];
+
+        for (date, flow) in &flows {
+            if date != &to {
+                values.push((*date, self.value(transactions, currency, date)?, *flow));
+            }
+        }
+
+        let end_value = self.value(transactions, currency, &to)?;
+        values.push((to, end_value, flows.get(&to).copied().unwrap_or(0.0)));
+
+        let mut cash_flows = vec![(before, -start_value)];
+        cash_flows.extend(flows.iter().map(|(date, flow)| (*date, -flow)));
+        cash_flows.push((to, end_value));
+
+        Ok(Performance {
+            currency: currency.into(),
+            from,
+            to,
+            start_value,
+            end_value,
+            net_flows: flows.values().sum(),
+            twr: Self::twr(&values),
+            xirr: Self::xirr(&cash_flows),
+        })
+    }
+
+    /// External flows per day, positive when money comes into the portfolio. Transfers
+    /// between accounts of the portfolio aren't external.
+    fn flows(
+        &self,
+        transactions: &[Transaction],
+        currency: &str,
+        from: &NaiveDate,
+        to: &NaiveDate,
+    ) -> Result<BTreeMap<NaiveDate, f64>> {
+        let mut flows = BTreeMap::new();
+        let mut rates = vec![];
+
+        for transaction in transactions {
+            let date = transaction.time.date().naive_utc();
+
+            if &date < from || &date > to {
+                continue;
+            }
+
+            // Both legs of a transfer within the portfolio cancel out, apart from the fee and
+            // the conversion which show up in the value
+            let is_internal = transaction.transfer_id.is_some()
+                && transactions
+                    .iter()
+                    .any(|it| it.id != transaction.id && it.transfer_id == transaction.transfer_id);
+
+            if is_internal {
+                continue;
+            }
+
+            let (asset, quantity) = match (transaction.transaction_type, &transaction.asset) {
+                (TransactionType::Deposit, _) => (&transaction.currency, transaction.quantity),
+                (TransactionType::Withdrawal, _) => (&transaction.currency, -transaction.quantity),
+                (TransactionType::Transfer, Some(asset)) => (asset, transaction.quantity),
+                (TransactionType::Transfer, None) => (&transaction.currency, transaction.quantity),
+                _ => continue,
+            };
+
+            let price = self.valuation_service.price(
+                asset,
+                transactions,
+                currency,
+                Some(date),
+                &mut rates,
+            )?;
+
+            *flows.entry(date).or_insert(0.0) += quantity * price.unwrap_or(0.0);
+        }
+
+        Ok(flows)
+    }
+
+    fn value(&self, transactions: &[Transaction], currency: &str, date: &NaiveDate) -> Result<f64> {
+        let holdings = HoldingService::replay(transactions, Some(*date));
+        let value = self
+            .valuation_service
+            .value(&holdings, transactions, currency, Some(*date))?;
+        Ok(value.total)
+    }
+
+    /// Chains the returns of the sub-periods between flows. Takes date, value at the end of
+    /// the day and the flow at its start, the first entry is the starting point.
+    pub fn twr(values: &[(NaiveDate, f64, f64)]) -> Option<f64> {
+        let mut growth = 1.0;
+        let mut periods = 0;
+
+        for window in values.windows(2) {
+            let (_, previous, _) = window[0];
+            let (_, value, flow) = window[1];
+            let invested = previous + flow;
+
+            if invested.abs() < f64::EPSILON {
+                continue;
+            }
+
+            growth *= value / invested;
+            periods += 1;
+        }
+
+        if periods == 0 {
+            None
+        } else {
+            Some(growth - 1.0)
+        }
+    }
+
+    /// Annualized rate which brings the net present value of the flows to zero. Flows are
+    /// from the investor's point of view, payments into the portfolio are negative.
+    pub fn xirr(flows: &[(NaiveDate, f64)]) -> Option<f64> {
+        let flows: Vec<(NaiveDate, f64)> = flows
+            .iter()
+            .filter(|(_, amount)| amount.abs() > f64::EPSILON)
+            .copied()
+            .collect();
+
+        if !flows.iter().any(|(_, it)| *it > 0.0) || !flows.iter().any(|(_, it)| *it < 0.0) {
+            return None;
+        }
+
+        let start = flows.iter().map(|(date, _)| *date).min()?;
+
+        let npv = |rate: f64| -> f64 {
+            flows
+                .iter()
+                .map(|(date, amount)| {
+                    let years = (*date - start).num_days() as f64 / 365.0;
+                    amount / (1.0 + rate).powf(years)
+                })
+                .sum()
+        };
+
+        let mut low = -0.9999;
+        let mut high = 1.0;
+
+        while npv(low).signum() == npv(high).signum() {
+            high *= 2.0;
+
+            if high > 1e15 {
+                return None;
+            }
+        }
+
+        for _ in 0..200 {
+            let middle = (low + high) / 2.0;
+
+            if npv(middle).signum() == npv(low).signum() {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        Some((low + high) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::service::PerformanceService;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn twr() {
+        let values = vec![
+            (date(1), 1000.0, 0.0),
+            (date(2), 1100.0, 0.0),
+            (date(3), 3410.0, 2000.0),
+            (date(4), 3010.0, -400.0),
+        ];
+        let twr = PerformanceService::twr(&values).unwrap();
+        assert!((twr - (1.1 * 1.1 * 1.0 - 1.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn twr_no_value() {
+        assert_eq!(
+            None,
+            PerformanceService::twr(&[(date(1), 0.0, 0.0), (date(2), 0.0, 0.0)])
+        );
+    }
+
+    #[test]
+    fn xirr() {
+        let flows = vec![
+            (NaiveDate::from_ymd(2020, 1, 1), -1000.0),
+            (NaiveDate::from_ymd(2020, 12, 31), 1100.0),
+        ];
+        let xirr = PerformanceService::xirr(&flows).unwrap();
+        assert!((xirr - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn xirr_no_outflows() {
+        let flows = vec![(date(1), -1000.0), (date(2), -1000.0)];
+        assert_eq!(None, PerformanceService::xirr(&flows));
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2021, 8, day)
+    }
+}
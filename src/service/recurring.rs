@@ -0,0 +1,575 @@
+use crate::{
+    model::{Id, Occurrence, RecurringException, RecurringTransaction, TransactionType},
+    provider::run_on_schedule,
+    repository::{RecurringExceptionRepository, RecurringTransactionRepository},
+    service::TransactionService,
+};
+use anyhow::{ensure, Error, Result};
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+#[derive(Clone, Deserialize)]
+pub struct RecurringConf {
+    pub schedule: String,
+}
+
+#[derive(Clone)]
+pub struct RecurringService {
+    repo: RecurringTransactionRepository,
+    exception_repo: RecurringExceptionRepository,
+    transaction_service: TransactionService,
+    conf: RecurringConf,
+}
+
+impl RecurringService {
+    pub fn new(
+        repo: &RecurringTransactionRepository,
+        exception_repo: &RecurringExceptionRepository,
+        transaction_service: &TransactionService,
+        conf: &RecurringConf,
+    ) -> RecurringService {
+        RecurringService {
+            repo: repo.clone(),
+            exception_repo: exception_repo.clone(),
+            transaction_service: transaction_service.clone(),
+            conf: conf.clone(),
+        }
+    }
+
+    pub fn insert(&self, recurring: &RecurringTransaction) -> Result<()> {
+        self.repo.insert(recurring)
+    }
+
+    pub fn update(&self, recurring: &RecurringTransaction) -> Result<()> {
+        self.repo.update(recurring)
+    }
+
+    /// Transactions which were already materialized are kept
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.exception_repo.delete_by_recurring_id(id)?;
+        self.repo.delete(id)
+    }
+
+    pub fn delete_by_account_id(&self, account_id: &Id) -> Result<()> {
+        for recurring in self.repo.select_by_account_id(account_id)? {
+            self.delete(&recurring.id)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<RecurringTransaction>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Other users' templates are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<RecurringTransaction>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, recurring: &RecurringTransaction) -> Result<()> {
+        recurring.recurrence().map_err(Error::msg)?;
+
+        if let Some(end) = recurring.end {
+            ensure!(
+                end >= recurring.start.date().naive_utc(),
+                "End can't be before start"
+            );
+        }
+
+        ensure!(
+            !matches!(
+                recurring.transaction_type,
+                TransactionType::Transfer
+                    | TransactionType::Split
+                    | TransactionType::SpinOff
+                    | TransactionType::TickerChange
+            ),
+            "Transfers and corporate actions can't recur"
+        );
+
+        self.transaction_service
+            .validate(&recurring.transaction(recurring.start, None))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate_exception(
+        &self,
+        recurring: &RecurringTransaction,
+        exception: &RecurringException,
+    ) -> Result<()> {
+        let occurrence = self
+            .occurrences(recurring, &exception.date, &exception.date)?
+            .pop();
+
+        let occurrence = match occurrence {
+            Some(occurrence) => occurrence,
+            None => return Err(Error::msg(format!("No occurrence on {}", exception.date))),
+        };
+
+        ensure!(
+            !occurrence.materialized,
+            "Occurrence on {} is already a transaction, change the transaction instead",
+            exception.date
+        );
+
+        if let (Some(time), Some(materialized_until)) =
+            (exception.time, recurring.materialized_until)
+        {
+            ensure!(
+                time.date().naive_utc() > materialized_until,
+                "Occurrences can only be moved after {}, earlier ones are already transactions",
+                materialized_until
+            );
+        }
+
+        let time = exception.time.unwrap_or(occurrence.time);
+        self.transaction_service
+            .validate(&recurring.transaction(time, Some(exception)))
+    }
+
+    pub fn insert_or_replace_exception(&self, exception: &RecurringException) -> Result<()> {
+        self.exception_repo.insert_or_replace(exception)
+    }
+
+    pub fn delete_exception(&self, recurring_id: &Id, date: &NaiveDate) -> Result<()> {
+        self.exception_repo.delete(recurring_id, date)
+    }
+
+    /// Occurrences scheduled between two days, both included, with their exceptions applied
+    pub fn occurrences(
+        &self,
+        recurring: &RecurringTransaction,
+        from: &NaiveDate,
+        to: &NaiveDate,
+    ) -> Result<Vec<Occurrence>> {
+        let recurrence = recurring.recurrence().map_err(Error::msg)?;
+        let to = match recurring.end {
+            Some(end) => end.min(*to),
+            None => *to,
+        };
+        let until = Utc.from_utc_datetime(&to.and_hms(23, 59, 59));
+        let exceptions = self.exception_repo.select_by_recurring_id(&recurring.id)?;
+
+        Ok(recurrence
+            .occurrences(&recurring.start, &until)
+            .into_iter()
+            .filter(|it| &it.date().naive_utc() >= from)
+            .map(|time| {
+                let date = time.date().naive_utc();
+                let exception = exceptions.iter().find(|it| it.date == date);
+                let time = exception.and_then(|it| it.time).unwrap_or(time);
+                let transaction = recurring.transaction(time, exception);
+
+                Occurrence {
+                    date,
+                    time,
+                    quantity: transaction.quantity,
+                    price: transaction.price,
+                    skipped: exception.map(|it| it.skip).unwrap_or(false),
+                    materialized: recurring
+                        .materialized_until
+                        .map(|it| time.date().naive_utc() <= it)
+                        .unwrap_or(false),
+                }
+            })
+            .collect())
+    }
+
+    /// Turns the occurrences up to a given day into transactions, skipped ones are left
+    /// out. Moved occurrences are turned into transactions by the day they were moved to.
+    /// Returns the number of created transactions.
+    pub fn materialize(
+        &self,
+        recurring: &mut RecurringTransaction,
+        today: &NaiveDate,
+    ) -> Result<usize> {
+        let from = match recurring.materialized_until {
+            Some(date) => date.succ(),
+            None => recurring.start.date().naive_utc(),
+        };
+
+        if &from > today {
+            return Ok(0);
+        }
+
+        let exceptions = self.exception_repo.select_by_recurring_id(&recurring.id)?;
+        let due = |date: NaiveDate| date >= from && &date <= today;
+        let mut occurrences = self.occurrences(recurring, &from, today)?;
+
+        // Occurrences scheduled outside of the period which were moved into it
+        for exception in &exceptions {
+            let moved_in = exception
+                .time
+                .map(|it| due(it.date().naive_utc()))
+                .unwrap_or(false);
+
+            if moved_in && !due(exception.date) {
+                occurrences.extend(self.occurrences(
+                    recurring,
+                    &exception.date,
+                    &exception.date,
+                )?);
+            }
+        }
+
+        occurrences.sort_by_key(|it| it.time);
+
+        let transactions: Vec<_> = occurrences
+            .into_iter()
+            .filter(|it| !it.skipped && due(it.time.date().naive_utc()))
+            .map(|it| {
+                let exception = exceptions
+                    .iter()
+                    .find(|exception| exception.date == it.date);
+                recurring.transaction(it.time, exception)
+            })
+            .collect();
+
+        self.transaction_service.insert_all(&transactions)?;
+        self.repo.update_materialized_until(&recurring.id, today)?;
+        recurring.materialized_until = Some(*today);
+        Ok(transactions.len())
+    }
+
+    /// A failing template doesn't stop the others
+    pub fn materialize_all(&self, today: &NaiveDate) -> Result<()> {
+        for mut recurring in self.repo.select_all()? {
+            match self.materialize(&mut recurring, today) {
+                Ok(0) => {}
+                Ok(count) => info!(recurring = %recurring.id, count, "Materialized occurrences"),
+                Err(e) => warn!(recurring = %recurring.id, ?e, "Failed to materialize occurrences"),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn schedule(&self) -> Result<()> {
+        info!(schedule = %self.conf.schedule, "Scheduling recurring transactions...");
+
+        run_on_schedule("recurring transactions", &self.conf.schedule, || async {
+            if let Err(e) = self.materialize_all(&Utc::today().naive_utc()) {
+                warn!(?e, "Failed to materialize recurring transactions");
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, CostBasisMethod, Id, RecurringException, RecurringTransaction,
+            TransactionType,
+        },
+        repository::{
+            AccountRepository, AssetRepository, LotMatchRepository, LotRepository,
+            PortfolioSnapshotRepository, RecurringExceptionRepository,
+            RecurringTransactionRepository, TransactionRepository,
+        },
+        service::{
+            asset::AssetConf, recurring::RecurringConf, AssetService, LotService, RecurringService,
+            TransactionService,
+        },
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+    use r2d2::Pool;
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    #[test]
+    fn occurrences_cron() -> Result<()> {
+        let (service, _) = service();
+        let mut recurring = recurring(&Id::new(), "0 0 9 1 * * *", time(2021, 8, 15));
+        assert_eq!(
+            vec![time(2021, 9, 1), time(2021, 10, 1)],
+            times(&service, &recurring, date(2021, 8, 1), date(2021, 10, 15))?
+        );
+
+        recurring.end = Some(date(2021, 9, 30));
+        assert_eq!(
+            vec![time(2021, 9, 1)],
+            times(&service, &recurring, date(2021, 8, 1), date(2021, 10, 15))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn occurrences_monthly_rule() -> Result<()> {
+        let (service, _) = service();
+        let recurring = recurring(&Id::new(), "FREQ=MONTHLY;BYMONTHDAY=-1", time(2021, 1, 15));
+        let expected: Vec<DateTime<Utc>> = vec![(1, 31), (2, 28), (3, 31), (4, 30)]
+            .into_iter()
+            .map(|(month, day)| time(2021, month, day))
+            .collect();
+        assert_eq!(
+            expected,
+            times(&service, &recurring, date(2021, 1, 1), date(2021, 4, 30))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn occurrences_weekly_rule() -> Result<()> {
+        let (service, _) = service();
+        let recurring = recurring(
+            &Id::new(),
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=5",
+            time(2021, 8, 2),
+        );
+        let expected: Vec<DateTime<Utc>> = vec![2, 6, 16, 20, 30]
+            .into_iter()
+            .map(|day| time(2021, 8, day))
+            .collect();
+        assert_eq!(
+            expected,
+            times(&service, &recurring, date(2021, 8, 1), date(2021, 12, 31))?
+        );
+
+        let later = times(&service, &recurring, date(2021, 8, 10), date(2021, 12, 31))?;
+        assert_eq!(expected[2..].to_vec(), later);
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<()> {
+        let (service, pool) = service();
+        let account = account(&pool)?;
+        let valid = recurring(&account.id, "FREQ=MONTHLY", time(2021, 8, 1));
+        assert!(service.validate(&valid).is_ok());
+
+        let invalid = vec![
+            RecurringTransaction {
+                schedule: "0 0 * * * * *".into(),
+                ..valid.clone()
+            },
+            RecurringTransaction {
+                schedule: "FREQ=HOURLY".into(),
+                ..valid.clone()
+            },
+            RecurringTransaction {
+                schedule: "FREQ=DAILY;BYDAY=MO".into(),
+                ..valid.clone()
+            },
+            RecurringTransaction {
+                end: Some(date(2021, 7, 31)),
+                ..valid.clone()
+            },
+            RecurringTransaction {
+                transaction_type: TransactionType::Transfer,
+                ..valid.clone()
+            },
+            RecurringTransaction {
+                quantity: 0.0,
+                ..valid
+            },
+        ];
+        for recurring in invalid {
+            assert!(service.validate(&recurring).is_err(), "{:?}", recurring);
+        }
+        Ok(())
+    }
+
+    /// Skips the September salary and raises the October one
+    #[test]
+    fn materialize() -> Result<()> {
+        let (service, pool) = service();
+        let account = account(&pool)?;
+        let mut recurring = recurring(&account.id, "0 0 9 25 * * *", time(2021, 8, 1));
+        service.insert(&recurring)?;
+        service.insert_or_replace_exception(&exception(&recurring, 9, true, None))?;
+        service.insert_or_replace_exception(&exception(&recurring, 10, false, Some(2100.0)))?;
+
+        assert_eq!(2, service.materialize(&mut recurring, &date(2021, 10, 31))?);
+        assert_eq!(0, service.materialize(&mut recurring, &date(2021, 10, 31))?);
+        assert_eq!(1, service.materialize(&mut recurring, &date(2021, 11, 30))?);
+        assert_eq!(Some(date(2021, 11, 30)), recurring.materialized_until);
+
+        let transactions = TransactionRepository::new(&pool).select_by_account_id(&account.id)?;
+        let quantities: Vec<(DateTime<Utc>, f64)> = transactions
+            .iter()
+            .map(|it| (it.time, it.quantity))
+            .collect();
+        assert_eq!(
+            vec![
+                (time(2021, 8, 25), 2000.0),
+                (time(2021, 10, 25), 2100.0),
+                (time(2021, 11, 25), 2000.0),
+            ],
+            quantities
+        );
+        assert!(transactions.iter().all(|it| it.external_id.is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn materialize_moved() -> Result<()> {
+        let (service, pool) = service();
+        let account = account(&pool)?;
+        let mut recurring = recurring(&account.id, "0 0 9 25 * * *", time(2021, 8, 1));
+        service.insert(&recurring)?;
+        // September is moved into October, November into September
+        service.insert_or_replace_exception(&RecurringException {
+            time: Some(time(2021, 10, 1)),
+            ..exception(&recurring, 9, false, None)
+        })?;
+        service.insert_or_replace_exception(&RecurringException {
+            time: Some(time(2021, 9, 1)),
+            ..exception(&recurring, 11, false, None)
+        })?;
+
+        let occurrences = service.occurrences(&recurring, &date(2021, 9, 1), &date(2021, 9, 30))?;
+        assert_eq!(date(2021, 9, 25), occurrences[0].date);
+        assert_eq!(time(2021, 10, 1), occurrences[0].time);
+
+        assert_eq!(2, service.materialize(&mut recurring, &date(2021, 9, 30))?);
+        assert_eq!(2, service.materialize(&mut recurring, &date(2021, 11, 30))?);
+
+        let transactions = TransactionRepository::new(&pool).select_by_account_id(&account.id)?;
+        assert_eq!(
+            vec![
+                time(2021, 8, 25),
+                time(2021, 9, 1),
+                time(2021, 10, 1),
+                time(2021, 10, 25),
+            ],
+            transactions.iter().map(|it| it.time).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn validate_exception() -> Result<()> {
+        let (service, pool) = service();
+        let account = account(&pool)?;
+        let mut recurring = recurring(&account.id, "0 0 9 25 * * *", time(2021, 8, 1));
+        service.insert(&recurring)?;
+        service.materialize(&mut recurring, &date(2021, 8, 31))?;
+
+        let mut exception = exception(&recurring, 9, false, Some(2100.0));
+        assert!(service.validate_exception(&recurring, &exception).is_ok());
+
+        exception.quantity = Some(-1.0);
+        assert!(service.validate_exception(&recurring, &exception).is_err());
+
+        exception.quantity = None;
+        exception.date = date(2021, 9, 24);
+        assert!(service.validate_exception(&recurring, &exception).is_err());
+
+        exception.date = date(2021, 8, 25);
+        assert!(service.validate_exception(&recurring, &exception).is_err());
+
+        exception.date = date(2021, 9, 25);
+        exception.time = Some(time(2021, 8, 30));
+        assert!(service.validate_exception(&recurring, &exception).is_err());
+        exception.time = Some(time(2021, 9, 1));
+        assert!(service.validate_exception(&recurring, &exception).is_ok());
+        Ok(())
+    }
+
+    fn service() -> (RecurringService, Pool<SqliteConnectionManager>) {
+        let pool = pool();
+        let transaction_repo = TransactionRepository::new(&pool);
+        let transaction_service = TransactionService::new(
+            &transaction_repo,
+            &PortfolioSnapshotRepository::new(&pool),
+            &AssetService::new(&AssetRepository::new(&pool), &AssetConf { admins: vec![] }),
+            &LotService::new(
+                &LotRepository::new(&pool),
+                &LotMatchRepository::new(&pool),
+                &AccountRepository::new(&pool),
+                &transaction_repo,
+            ),
+        );
+        let service = RecurringService::new(
+            &RecurringTransactionRepository::new(&pool),
+            &RecurringExceptionRepository::new(&pool),
+            &transaction_service,
+            &RecurringConf {
+                schedule: "0 0 1 * * * *".into(),
+            },
+        );
+        (service, pool)
+    }
+
+    fn times(
+        service: &RecurringService,
+        recurring: &RecurringTransaction,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        Ok(service
+            .occurrences(recurring, &from, &to)?
+            .into_iter()
+            .map(|it| it.time)
+            .collect())
+    }
+
+    fn account(pool: &Pool<SqliteConnectionManager>) -> Result<Account> {
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Bank".into(),
+            account_type: AccountType::Bank,
+            currency: "EUR".into(),
+            opened_at: date(2021, 1, 1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        AccountRepository::new(pool).insert(&account)?;
+        Ok(account)
+    }
+
+    fn recurring(account_id: &Id, schedule: &str, start: DateTime<Utc>) -> RecurringTransaction {
+        RecurringTransaction {
+            id: Id::new(),
+            username: "test".into(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity: 2000.0,
+            price: None,
+            currency: "EUR".into(),
+            fee: 0.0,
+            description: Some("Salary".into()),
+            category_id: None,
+            tags: vec![],
+            schedule: schedule.into(),
+            start,
+            end: None,
+            materialized_until: None,
+        }
+    }
+
+    fn exception(
+        recurring: &RecurringTransaction,
+        month: u32,
+        skip: bool,
+        quantity: Option<f64>,
+    ) -> RecurringException {
+        RecurringException {
+            recurring_id: recurring.id.clone(),
+            date: date(2021, month, 25),
+            skip,
+            quantity,
+            price: None,
+            time: None,
+        }
+    }
+
+    fn time(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.ymd(year, month, day).and_hms(9, 0, 0)
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    model::PortfolioSnapshot,
+    provider::run_on_schedule,
+    repository::{AccountRepository, PortfolioSnapshotRepository, TransactionRepository},
+    service::{HoldingService, ValuationService},
+};
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+#[derive(Clone, Deserialize)]
+pub struct SnapshotConf {
+    pub schedule: String,
+    /// Snapshots are taken in each of these currencies
+    pub currencies: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct SnapshotService {
+    repo: PortfolioSnapshotRepository,
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    valuation_service: ValuationService,
+    conf: SnapshotConf,
+}
+
+impl SnapshotService {
+    pub fn new(
+        repo: &PortfolioSnapshotRepository,
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        valuation_service: &ValuationService,
+        conf: &SnapshotConf,
+    ) -> SnapshotService {
+        SnapshotService {
+            repo: repo.clone(),
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            valuation_service: valuation_service.clone(),
+            conf: conf.clone(),
+        }
+    }
+
+    pub fn is_tracked(&self, currency: &str) -> bool {
+        self.conf.currencies.iter().any(|it| it == currency)
+    }
+
+    pub fn select_history(
+        &self,
+        username: &str,
+        currency: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<PortfolioSnapshot>> {
+        self.repo
+            .select_by_username_and_currency(username, currency, from, to)
+    }
+
+    /// Fills the gaps in every user's history up to a given day. The last day is always
+    /// recomputed since its rates might have changed since the previous run.
+    pub fn take_all(&self, today: &NaiveDate) -> Result<()> {
+        for username in self.account_repo.select_usernames()? {
+            self.take(&username, today)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn take(&self, username: &str, today: &NaiveDate) -> Result<()> {
+        let transactions = self.transaction_repo.select_by_username(username)?;
+
+        let first_date = match transactions.first() {
+            Some(transaction) => transaction.time.date().naive_utc(),
+            None => return Ok(()),
+        };
+
+        for currency in &self.conf.currencies {
+            let mut date = match self.repo.select_last_date(username, currency)? {
+                Some(last_date) => last_date.succ().min(*today).max(first_date),
+                None => first_date,
+            };
+
+            while &date <= today {
+                let holdings = HoldingService::replay(&transactions, Some(date));
+                let value =
+                    self.valuation_service
+                        .value(&holdings, &transactions, currency, Some(date))?;
+
+                self.repo.insert_or_replace(&PortfolioSnapshot {
+                    username: username.into(),
+                    currency: currency.clone(),
+                    date,
+                    value: value.total,
+                })?;
+
+                date = date.succ();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn schedule(&self) -> Result<()> {
+        info!(schedule = %self.conf.schedule, "Scheduling portfolio snapshots...");
+
+        run_on_schedule("portfolio snapshots", &self.conf.schedule, || async {
+            if let Err(e) = self.take_all(&Utc::today().naive_utc()) {
+                warn!(?e, "Failed to take portfolio snapshots");
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{
+            Account, AccountType, CostBasisMethod, Id, PortfolioSnapshot, Transaction,
+            TransactionType,
+        },
+        repository::{
+            AccountRepository, ExchangeRateRepository, PortfolioSnapshotRepository,
+            TransactionRepository,
+        },
+        service::{
+            exchange_rate::RateCacheConf, snapshot::SnapshotConf, ExchangeRateService,
+            SnapshotService, ValuationService,
+        },
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn take() -> Result<()> {
+        let pool = pool();
+        let account_repo = AccountRepository::new(&pool);
+        let transaction_repo = TransactionRepository::new(&pool);
+        let service = SnapshotService::new(
+            &PortfolioSnapshotRepository::new(&pool),
+            &account_repo,
+            &transaction_repo,
+            &ValuationService::new(
+                &transaction_repo,
+                &ExchangeRateService::new(
+                    &ExchangeRateRepository::new(&pool),
+                    &RateCacheConf { ttl_secs: 60 },
+                ),
+            ),
+            &SnapshotConf {
+                schedule: "0 0 0 * * * *".into(),
+                currencies: vec!["USD".into()],
+            },
+        );
+
+        let account = Account {
+            id: Id::new(),
+            username: "test".into(),
+            name: "Bank".into(),
+            account_type: AccountType::Bank,
+            currency: "USD".into(),
+            opened_at: date(1),
+            closed_at: None,
+            cost_basis_method: CostBasisMethod::Fifo,
+        };
+        account_repo.insert(&account)?;
+        transaction_repo.insert(&deposit(&account.id, 100.0, 2))?;
+
+        service.take_all(&date(3))?;
+        let history = service.select_history("test", "USD", None, None)?;
+        assert_eq!(vec![date(2), date(3)], dates(&history));
+        assert_eq!(100.0, history[1].value);
+
+        transaction_repo.insert(&deposit(&account.id, 50.0, 4))?;
+        service.take_all(&date(5))?;
+        let history = service.select_history("test", "USD", None, None)?;
+        assert_eq!(vec![date(2), date(3), date(4), date(5)], dates(&history));
+        assert_eq!(150.0, history[3].value);
+        Ok(())
+    }
+
+    fn deposit(account_id: &Id, quantity: f64, day: u32) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: account_id.clone(),
+            transaction_type: TransactionType::Deposit,
+            asset: None,
+            quantity,
+            price: None,
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, day).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+
+    fn dates(history: &[PortfolioSnapshot]) -> Vec<NaiveDate> {
+        history.iter().map(|it| it.date).collect()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2021, 8, day)
+    }
+}
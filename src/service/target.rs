@@ -0,0 +1,243 @@
+use crate::{
+    model::{AssetType, Rebalance, RebalancePosition, RebalanceTrade, Target},
+    repository::{TargetRepository, TransactionRepository},
+    service::{AssetService, HoldingService, ValuationService},
+};
+use anyhow::{ensure, Error, Result};
+
+/// Weights may be off by this much in total due to rounding by the client
+const WEIGHT_TOLERANCE: f64 = 1e-6;
+
+#[derive(Clone)]
+pub struct TargetService {
+    repo: TargetRepository,
+    transaction_repo: TransactionRepository,
+    asset_service: AssetService,
+    valuation_service: ValuationService,
+}
+
+impl TargetService {
+    pub fn new(
+        repo: &TargetRepository,
+        transaction_repo: &TransactionRepository,
+        asset_service: &AssetService,
+        valuation_service: &ValuationService,
+    ) -> TargetService {
+        TargetService {
+            repo: repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            asset_service: asset_service.clone(),
+            valuation_service: valuation_service.clone(),
+        }
+    }
+
+    pub fn replace_by_username(&self, username: &str, targets: &[Target]) -> Result<()> {
+        self.repo.replace_by_username(username, targets)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Target>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Checks user input, returned errors are safe to show to the user. An empty list
+    /// clears the targets.
+    pub fn validate(&self, targets: &[Target]) -> Result<()> {
+        for (index, target) in targets.iter().enumerate() {
+            match (&target.asset, &target.asset_type) {
+                (Some(asset), None) => ensure!(
+                    self.asset_service.exists(asset)?,
+                    "Unknown asset: {}",
+                    asset
+                ),
+                (None, Some(_)) => {}
+                _ => return Err(Error::msg("Target needs either an asset or a type")),
+            }
+
+            ensure!(
+                target.weight > 0.0 && target.weight <= 1.0,
+                "Weight must be between 0 and 1"
+            );
+
+            ensure!(
+                !targets[..index]
+                    .iter()
+                    .any(|it| it.asset == target.asset && it.asset_type == target.asset_type),
+                "Duplicate target: {}",
+                Self::name(target.asset.as_deref(), target.asset_type)
+            );
+        }
+
+        if !targets.is_empty() {
+            let total: f64 = targets.iter().map(|it| it.weight).sum();
+            ensure!(
+                (total - 1.0).abs() <= WEIGHT_TOLERANCE,
+                "Weights must add up to 1"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Compares the current values with the targets after adding some cash, or taking it
+    /// out when it's negative. Positions which are off by more than the tolerance get
+    /// trades which bring them back to their targets. Currencies which aren't targeted
+    /// count as cash, so they fund the trades instead of getting sold.
+    pub fn rebalance(
+        &self,
+        username: &str,
+        currency: &str,
+        cash: f64,
+        tolerance: f64,
+    ) -> Result<Rebalance> {
+        let targets = self.repo.select_by_username(username)?;
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        let holdings = HoldingService::replay(&transactions, None);
+        let value = self
+            .valuation_service
+            .value(&holdings, &transactions, currency, None)?;
+        let total = value.total + cash;
+        let mut cash = cash;
+
+        let mut positions: Vec<RebalancePosition> = targets
+            .iter()
+            .map(|it| RebalancePosition {
+                asset: it.asset.clone(),
+                asset_type: it.asset_type,
+                value: 0.0,
+                weight: 0.0,
+                target_value: it.weight * total,
+                target_weight: it.weight,
+                amount: 0.0,
+                trades: vec![],
+            })
+            .collect();
+        let mut held: Vec<Vec<(String, f64, f64)>> = vec![vec![]; positions.len()];
+        let mut unpriced = vec![];
+
+        for holding in value.holdings {
+            let (price, value) = match (holding.price, holding.value) {
+                (Some(price), Some(value)) => (price, value),
+                _ => {
+                    unpriced.push(holding.asset);
+                    continue;
+                }
+            };
+
+            let asset_type = self
+                .asset_service
+                .select_by_code(&holding.asset)?
+                .map(|it| it.asset_type);
+
+            // Asset targets take precedence over type targets
+            let index = positions
+                .iter()
+                .position(|it| it.asset.as_ref() == Some(&holding.asset))
+                .or_else(|| {
+                    positions
+                        .iter()
+                        .position(|it| it.asset.is_none() && it.asset_type == asset_type)
+                });
+
+            let index = match index {
+                Some(index) => index,
+                None if asset_type == Some(AssetType::Fiat) => {
+                    cash += value;
+                    continue;
+                }
+                None => {
+                    positions.push(RebalancePosition {
+                        asset: Some(holding.asset.clone()),
+                        asset_type: None,
+                        value: 0.0,
+                        weight: 0.0,
+                        target_value: 0.0,
+                        target_weight: 0.0,
+                        amount: 0.0,
+                        trades: vec![],
+                    });
+                    held.push(vec![]);
+                    positions.len() - 1
+                }
+            };
+
+            positions[index].value += value;
+            held[index].push((holding.asset, price, value));
+        }
+
+        let mut rates = vec![];
+
+        for (position, held) in positions.iter_mut().zip(held) {
+            if total > 0.0 {
+                position.weight = position.value / total;
+            }
+
+            if (position.weight - position.target_weight).abs() <= tolerance {
+                continue;
+            }
+
+            position.amount = position.target_value - position.value;
+
+            let shares = match &position.asset {
+                // Targeted assets which aren't held yet need a price of their own
+                Some(asset) if held.is_empty() => self
+                    .valuation_service
+                    .price(asset, &transactions, currency, None, &mut rates)?
+                    .map(|price| vec![(asset.clone(), price, 1.0)])
+                    .unwrap_or_default(),
+                // Holdings which net to nothing share the trade evenly
+                _ if position.value == 0.0 => {
+                    let count = held.len() as f64;
+                    held.into_iter()
+                        .map(|(asset, price, _)| (asset, price, 1.0 / count))
+                        .collect()
+                }
+                _ => held
+                    .into_iter()
+                    .map(|(asset, price, value)| (asset, price, value / position.value))
+                    .collect(),
+            };
+
+            for (asset, price, share) in shares {
+                if price <= 0.0 {
+                    continue;
+                }
+
+                let amount = position.amount * share;
+                let precision = self
+                    .asset_service
+                    .select_by_code(&asset)?
+                    .map(|it| it.precision)
+                    .unwrap_or(8);
+
+                position.trades.push(RebalanceTrade {
+                    quantity: Self::round(amount / price, precision),
+                    asset,
+                    price,
+                    amount,
+                });
+            }
+        }
+
+        Ok(Rebalance {
+            currency: currency.into(),
+            total,
+            cash,
+            tolerance,
+            positions,
+            unpriced,
+        })
+    }
+
+    fn round(quantity: f64, precision: u8) -> f64 {
+        let factor = 10f64.powi(precision as i32);
+        (quantity * factor).round() / factor
+    }
+
+    fn name(asset: Option<&str>, asset_type: Option<AssetType>) -> String {
+        match (asset, asset_type) {
+            (Some(asset), _) => asset.into(),
+            (None, Some(asset_type)) => asset_type.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    model::{CostBasisMethod, Disposal, DividendIncome, HoldingPeriod, TaxReport, TransactionType},
+    repository::{AccountRepository, TransactionRepository},
+    service::{GainsService, LotService, ValuationService},
+};
+use anyhow::Result;
+use chrono::Datelike;
+use std::io::{Cursor, Write};
+use zip::{write::FileOptions, ZipWriter};
+
+#[derive(Clone)]
+pub struct TaxService {
+    account_repo: AccountRepository,
+    transaction_repo: TransactionRepository,
+    gains_service: GainsService,
+    valuation_service: ValuationService,
+}
+
+impl TaxService {
+    pub fn new(
+        account_repo: &AccountRepository,
+        transaction_repo: &TransactionRepository,
+        gains_service: &GainsService,
+        valuation_service: &ValuationService,
+    ) -> TaxService {
+        TaxService {
+            account_repo: account_repo.clone(),
+            transaction_repo: transaction_repo.clone(),
+            gains_service: gains_service.clone(),
+            valuation_service: valuation_service.clone(),
+        }
+    }
+
+    /// Sells are matched against lots with the given method, or with the method of each
+    /// account if there's none. Years follow UTC dates.
+    pub fn report(
+        &self,
+        username: &str,
+        currency: &str,
+        year: i32,
+        method: Option<CostBasisMethod>,
+    ) -> Result<TaxReport> {
+        let mut report = TaxReport {
+            currency: currency.into(),
+            year,
+            method,
+            disposals: vec![],
+            dividends: vec![],
+            total_proceeds: 0.0,
+            total_cost: 0.0,
+            short_term_gain: 0.0,
+            long_term_gain: 0.0,
+            total_gain: 0.0,
+            total_dividends: 0.0,
+            total_withheld: 0.0,
+            unattributed_taxes: 0.0,
+            unpriced: vec![],
+            rates: vec![],
+        };
+
+        let accounts = self.account_repo.select_by_username(username)?;
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        let (_, lot_matches) = match method {
+            Some(method) => LotService::match_lots(&transactions, method),
+            None => LotService::match_accounts(&accounts, &transactions),
+        };
+
+        for lot_match in lot_matches {
+            if lot_match.sold_at.year() != year {
+                continue;
+            }
+
+            let gain = match self
+                .gains_service
+                .realized(&lot_match, currency, &mut report.rates)?
+            {
+                Some(gain) => gain,
+                None => {
+                    if !report.unpriced.contains(&lot_match.sell_id) {
+                        report.unpriced.push(lot_match.sell_id);
+                    }
+                    continue;
+                }
+            };
+
+            report.disposals.push(Disposal {
+                account_id: gain.account_id,
+                sell_id: gain.sell_id,
+                lot_id: gain.lot_id,
+                asset: gain.asset,
+                quantity: gain.quantity,
+                acquired_at: gain.acquired_at,
+                sold_at: gain.sold_at,
+                proceeds: gain.proceeds,
+                cost: gain.cost,
+                gain: gain.gain,
+                holding_period: HoldingPeriod::of(&gain.acquired_at, &gain.sold_at),
+            });
+        }
+
+        for transaction in transactions.iter().filter(|it| it.time.year() == year) {
+            let (amount, withheld) = match transaction.transaction_type {
+                TransactionType::Dividend => (transaction.quantity, false),
+                TransactionType::DividendReinvestment => (
+                    transaction.quantity * transaction.price.unwrap_or(0.0),
+                    false,
+                ),
+                TransactionType::Tax => (transaction.quantity, true),
+                _ => continue,
+            };
+
+            let rate = self.valuation_service.rate(
+                &transaction.currency,
+                currency,
+                Some(transaction.time.date().naive_utc()),
+                &mut report.rates,
+            )?;
+
+            let amount = match rate {
+                Some(rate) => amount * rate,
+                None => {
+                    report.unpriced.push(transaction.id.clone());
+                    continue;
+                }
+            };
+
+            let asset = match &transaction.asset {
+                Some(asset) => asset,
+                None => {
+                    if withheld {
+                        report.unattributed_taxes += amount;
+                    }
+                    continue;
+                }
+            };
+
+            let index = match report.dividends.iter().position(|it| &it.asset == asset) {
+                Some(index) => index,
+                None => {
+                    report.dividends.push(DividendIncome {
+                        asset: asset.clone(),
+                        gross: 0.0,
+                        withheld: 0.0,
+                        net: 0.0,
+                    });
+                    report.dividends.len() - 1
+                }
+            };
+
+            let dividend = &mut report.dividends[index];
+
+            match withheld {
+                true => dividend.withheld += amount,
+                false => dividend.gross += amount,
+            }
+
+            dividend.net = dividend.gross - dividend.withheld;
+        }
+
+        report.disposals.sort_by_key(|it| it.sold_at);
+        report.dividends.sort_by(|a, b| a.asset.cmp(&b.asset));
+
+        for disposal in &report.disposals {
+            report.total_proceeds += disposal.proceeds;
+            report.total_cost += disposal.cost;
+
+            match disposal.holding_period {
+                HoldingPeriod::Short => report.short_term_gain += disposal.gain,
+                HoldingPeriod::Long => report.long_term_gain += disposal.gain,
+            }
+        }
+
+        report.total_gain = report.short_term_gain + report.long_term_gain;
+        report.total_dividends = report.dividends.iter().map(|it| it.gross).sum();
+        report.total_withheld = report.dividends.iter().map(|it| it.withheld).sum();
+        Ok(report)
+    }
+
+    /// Zip archive with disposals.csv and dividends.csv
+    pub fn to_zip(report: &TaxReport) -> Result<Vec<u8>> {
+        let mut disposals = csv::Writer::from_writer(vec![]);
+        disposals.write_record([
+            "asset",
+            "quantity",
+            "acquired_at",
+            "sold_at",
+            "proceeds",
+            "cost",
+            "gain",
+            "holding_period",
+            "account_id",
+            "sell_id",
+            "lot_id",
+        ])?;
+
+        for disposal in &report.disposals {
+            disposals.write_record([
+                disposal.asset.clone(),
+                disposal.quantity.to_string(),
+                disposal.acquired_at.to_rfc3339(),
+                disposal.sold_at.to_rfc3339(),
+                disposal.proceeds.to_string(),
+                disposal.cost.to_string(),
+                disposal.gain.to_string(),
+                disposal.holding_period.to_string(),
+                disposal.account_id.to_string(),
+                disposal.sell_id.to_string(),
+                disposal.lot_id.to_string(),
+            ])?;
+        }
+
+        let mut dividends = csv::Writer::from_writer(vec![]);
+        dividends.write_record(["asset", "gross", "withheld", "net"])?;
+
+        for dividend in &report.dividends {
+            dividends.write_record([
+                dividend.asset.clone(),
+                dividend.gross.to_string(),
+                dividend.withheld.to_string(),
+                dividend.net.to_string(),
+            ])?;
+        }
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let files = vec![
+            ("disposals.csv", disposals.into_inner()?),
+            ("dividends.csv", dividends.into_inner()?),
+        ];
+
+        for (name, contents) in files {
+            zip.start_file(name, FileOptions::default())?;
+            zip.write_all(&contents)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+}
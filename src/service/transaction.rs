@@ -0,0 +1,244 @@
+use crate::{
+    model::{Id, Transaction, TransactionType},
+    repository::{PortfolioSnapshotRepository, TransactionRepository},
+    service::{AssetService, LotService},
+};
+use anyhow::{ensure, Result};
+
+#[derive(Clone)]
+pub struct TransactionService {
+    repo: TransactionRepository,
+    snapshot_repo: PortfolioSnapshotRepository,
+    asset_service: AssetService,
+    lot_service: LotService,
+}
+
+impl TransactionService {
+    pub fn new(
+        repo: &TransactionRepository,
+        snapshot_repo: &PortfolioSnapshotRepository,
+        asset_service: &AssetService,
+        lot_service: &LotService,
+    ) -> TransactionService {
+        TransactionService {
+            repo: repo.clone(),
+            snapshot_repo: snapshot_repo.clone(),
+            asset_service: asset_service.clone(),
+            lot_service: lot_service.clone(),
+        }
+    }
+
+    /// Snapshots taken after the changed date are dropped so they can be retaken
+    pub fn insert(&self, transaction: &Transaction) -> Result<()> {
+        self.repo.insert(transaction)?;
+        self.invalidate_snapshots(transaction)?;
+        self.lot_service.rebuild(&transaction.account_id)
+    }
+
+    /// Same as `insert` but writes all the transactions at once and rebuilds the lots
+    /// only once
+    pub fn insert_all(&self, transactions: &[Transaction]) -> Result<()> {
+        self.repo.insert_all(transactions)?;
+
+        for transaction in transactions {
+            self.invalidate_snapshots(transaction)?;
+        }
+
+        self.rebuild_lots(transactions)
+    }
+
+    pub fn update(&self, transaction: &Transaction) -> Result<()> {
+        self.update_all(std::slice::from_ref(transaction))
+    }
+
+    /// Same as `update` but writes all the transactions at once and rebuilds the lots
+    /// only once
+    pub fn update_all(&self, transactions: &[Transaction]) -> Result<()> {
+        for transaction in transactions {
+            if let Some(previous) = self.repo.select_by_id(&transaction.id)? {
+                self.invalidate_snapshots(&previous)?;
+            }
+        }
+
+        self.repo.update_all(transactions)?;
+
+        for transaction in transactions {
+            self.invalidate_snapshots(transaction)?;
+        }
+
+        self.rebuild_lots(transactions)
+    }
+
+    /// Both legs of a transfer are deleted together
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        let transactions = match self.repo.select_by_id(id)? {
+            Some(Transaction {
+                transfer_id: Some(transfer_id),
+                ..
+            }) => self.repo.select_by_transfer_id(&transfer_id)?,
+            Some(transaction) => vec![transaction],
+            None => vec![],
+        };
+
+        let ids: Vec<Id> = transactions.iter().map(|it| it.id.clone()).collect();
+        self.repo.delete_all(&ids)?;
+
+        for transaction in &transactions {
+            self.invalidate_snapshots(transaction)?;
+        }
+
+        self.rebuild_lots(&transactions)
+    }
+
+    pub fn select_by_account_id(&self, account_id: &Id) -> Result<Vec<Transaction>> {
+        self.repo.select_by_account_id(account_id)
+    }
+
+    /// Transactions of other accounts are treated as non-existent
+    pub fn select_by_account_id_and_id(
+        &self,
+        account_id: &Id,
+        id: &Id,
+    ) -> Result<Option<Transaction>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| &it.account_id == account_id))
+    }
+
+    fn rebuild_lots(&self, transactions: &[Transaction]) -> Result<()> {
+        let account_ids: Vec<Id> = transactions
+            .iter()
+            .map(|it| it.account_id.clone())
+            .collect();
+        self.lot_service.rebuild_all(&account_ids)
+    }
+
+    fn invalidate_snapshots(&self, transaction: &Transaction) -> Result<()> {
+        self.snapshot_repo.delete_by_account_id_since(
+            &transaction.account_id,
+            &transaction.time.date().naive_utc(),
+        )
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, transaction: &Transaction) -> Result<()> {
+        ensure!(
+            self.asset_service.exists(&transaction.currency)?,
+            "Unknown asset: {}",
+            transaction.currency
+        );
+
+        if let Some(asset) = &transaction.asset {
+            ensure!(
+                self.asset_service.exists(asset)?,
+                "Unknown asset: {}",
+                asset
+            );
+        }
+
+        match transaction.transaction_type {
+            TransactionType::Buy | TransactionType::Sell => {
+                ensure!(transaction.asset.is_some(), "Trades require an asset");
+                ensure!(
+                    transaction.price.map(|it| it >= 0.0).unwrap_or(false),
+                    "Trades require a non-negative price"
+                );
+            }
+            TransactionType::Dividend => {
+                ensure!(transaction.asset.is_some(), "Dividends require an asset");
+            }
+            TransactionType::Deposit | TransactionType::Withdrawal | TransactionType::Interest => {
+                ensure!(
+                    transaction.asset.is_none(),
+                    "Cash transactions can't have an asset, use currency instead"
+                );
+            }
+            TransactionType::DividendReinvestment => {
+                ensure!(transaction.asset.is_some(), "Dividends require an asset");
+                ensure!(
+                    transaction.price.map(|it| it >= 0.0).unwrap_or(false),
+                    "Reinvested dividends require a non-negative price"
+                );
+            }
+            TransactionType::Split => {
+                ensure!(transaction.asset.is_some(), "Splits require an asset");
+            }
+            TransactionType::SpinOff | TransactionType::TickerChange => {
+                ensure!(
+                    transaction.asset.is_some() && transaction.new_asset.is_some(),
+                    "Spin-offs and ticker changes require an asset and a new asset"
+                );
+                ensure!(
+                    transaction.asset != transaction.new_asset,
+                    "New asset must be different"
+                );
+            }
+            TransactionType::Transfer => {
+                ensure!(
+                    transaction.transfer_id.is_some(),
+                    "Transfers are added through /transfers"
+                );
+            }
+            TransactionType::Fee | TransactionType::Tax => {}
+        }
+
+        if let Some(new_asset) = &transaction.new_asset {
+            ensure!(
+                matches!(
+                    transaction.transaction_type,
+                    TransactionType::SpinOff | TransactionType::TickerChange
+                ),
+                "Only spin-offs and ticker changes can have a new asset"
+            );
+            ensure!(
+                self.asset_service.exists(new_asset)?,
+                "Unknown asset: {}",
+                new_asset
+            );
+        }
+
+        if transaction.transaction_type == TransactionType::SpinOff {
+            ensure!(
+                transaction
+                    .price
+                    .map(|it| (0.0..=1.0).contains(&it))
+                    .unwrap_or(false),
+                "Spin-offs require the share of the cost basis between 0 and 1 as price"
+            );
+        }
+
+        match transaction.transaction_type {
+            TransactionType::Transfer => {
+                ensure!(
+                    transaction.quantity != 0.0,
+                    "Transfer quantity can't be zero"
+                )
+            }
+            _ => ensure!(transaction.quantity > 0.0, "Quantity must be positive"),
+        }
+
+        ensure!(transaction.fee >= 0.0, "Fee can't be negative");
+
+        if let Some(lot_id) = &transaction.lot_id {
+            ensure!(
+                transaction.transaction_type == TransactionType::Sell,
+                "Only sells can point to a lot"
+            );
+
+            // Lots moved in by transfers don't share the ID of any transaction
+            let lots = self
+                .lot_service
+                .select_by_account_id(&transaction.account_id)?;
+
+            ensure!(
+                lots.iter()
+                    .any(|it| &it.id == lot_id && Some(&it.asset) == transaction.asset.as_ref()),
+                "Unknown lot: {}",
+                lot_id
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    model::{Id, Transfer},
+    repository::{AccountRepository, TransactionRepository},
+    service::{ExchangeRateService, TransactionService},
+};
+use anyhow::{ensure, Result};
+
+#[derive(Clone)]
+pub struct TransferService {
+    transaction_repo: TransactionRepository,
+    account_repo: AccountRepository,
+    transaction_service: TransactionService,
+    rate_service: ExchangeRateService,
+}
+
+impl TransferService {
+    pub fn new(
+        transaction_repo: &TransactionRepository,
+        account_repo: &AccountRepository,
+        transaction_service: &TransactionService,
+        rate_service: &ExchangeRateService,
+    ) -> TransferService {
+        TransferService {
+            transaction_repo: transaction_repo.clone(),
+            account_repo: account_repo.clone(),
+            transaction_service: transaction_service.clone(),
+            rate_service: rate_service.clone(),
+        }
+    }
+
+    pub fn insert(&self, transfer: &Transfer) -> Result<()> {
+        let (from, to) = transfer.legs();
+        self.transaction_service.insert_all(&[from, to])
+    }
+
+    /// Accounts of a transfer can't be changed
+    pub fn update(&self, transfer: &Transfer) -> Result<()> {
+        let (from, to) = transfer.legs();
+        self.transaction_service.update_all(&[from, to])
+    }
+
+    /// Deleting either leg deletes the other one too
+    pub fn delete(&self, transfer: &Transfer) -> Result<()> {
+        self.transaction_service
+            .delete(&transfer.from_transaction_id)
+    }
+
+    /// Legs which lost their counterpart are left out
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Transfer>> {
+        let legs: Vec<_> = self
+            .transaction_repo
+            .select_by_username(username)?
+            .into_iter()
+            .filter(|it| it.transfer_id.is_some())
+            .collect();
+
+        Ok(legs
+            .iter()
+            .filter(|from| from.quantity < 0.0)
+            .filter_map(|from| {
+                legs.iter()
+                    .find(|to| to.id != from.id && to.transfer_id == from.transfer_id)
+                    .and_then(|to| Transfer::from_legs(from, to))
+            })
+            .collect())
+    }
+
+    /// Transfers between other users' accounts are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Transfer>> {
+        let legs = self.transaction_repo.select_by_transfer_id(id)?;
+
+        let transfer = match legs.as_slice() {
+            [from, to] => Transfer::from_legs(from, to),
+            _ => None,
+        };
+
+        let transfer = match transfer {
+            Some(transfer) => transfer,
+            None => return Ok(None),
+        };
+
+        Ok(self
+            .account_repo
+            .select_by_id(&transfer.from_account_id)?
+            .filter(|it| it.username == username)
+            .map(|_| transfer))
+    }
+
+    /// Units of `to` per unit of `from` at the latest known rates
+    pub fn rate(&self, from: &str, to: &str) -> Result<Option<f64>> {
+        if from == to {
+            return Ok(Some(1.0));
+        }
+
+        Ok(self
+            .rate_service
+            .get_by_quote_and_base(from, to)?
+            .map(|it| it.rate))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, transfer: &Transfer) -> Result<()> {
+        ensure!(
+            transfer.from_account_id != transfer.to_account_id,
+            "Transfer accounts must be different"
+        );
+        ensure!(transfer.quantity > 0.0, "Quantity must be positive");
+        ensure!(transfer.rate > 0.0, "Rate must be positive");
+
+        if transfer.asset.is_some() {
+            ensure!(
+                transfer.from_currency == transfer.to_currency,
+                "Asset transfers can't be converted"
+            );
+        }
+
+        if transfer.from_currency == transfer.to_currency {
+            ensure!(
+                transfer.rate == 1.0,
+                "Rate must be 1 when both currencies are the same"
+            );
+        }
+
+        let (from, to) = transfer.legs();
+        self.transaction_service.validate(&from)?;
+        self.transaction_service.validate(&to)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{Id, Transfer},
+        repository::{
+            AccountRepository, AssetRepository, ExchangeRateRepository, LotMatchRepository,
+            LotRepository, PortfolioSnapshotRepository, TransactionRepository,
+        },
+        service::{
+            asset::AssetConf, exchange_rate::RateCacheConf, AssetService, ExchangeRateService,
+            LotService, TransactionService, TransferService,
+        },
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn legs() {
+        let transfer = transfer(None, 100.0, "USD", "EUR", 0.8);
+        let (from, to) = transfer.legs();
+        assert_eq!(-100.0, from.quantity);
+        assert_eq!(1.0, from.fee);
+        assert_eq!(Some(transfer.id.clone()), from.transfer_id);
+        assert_eq!(80.0, to.quantity);
+        assert_eq!("EUR", to.currency);
+        assert_eq!(0.0, to.fee);
+        assert_eq!(Some(transfer.clone()), Transfer::from_legs(&from, &to));
+    }
+
+    #[test]
+    fn validate() -> Result<()> {
+        let service = service();
+        assert!(service
+            .validate(&transfer(None, 100.0, "USD", "EUR", 0.8))
+            .is_ok());
+        assert!(service
+            .validate(&transfer(None, 0.0, "USD", "EUR", 0.8))
+            .is_err());
+        assert!(service
+            .validate(&transfer(None, 100.0, "USD", "EUR", 0.0))
+            .is_err());
+        assert!(service
+            .validate(&transfer(Some("BTC"), 1.0, "USD", "EUR", 0.8))
+            .is_err());
+        assert!(service
+            .validate(&transfer(Some("BTC"), 1.0, "USD", "USD", 1.0))
+            .is_ok());
+
+        let same_account = transfer(None, 100.0, "USD", "USD", 1.0);
+        let same_account = Transfer {
+            to_account_id: same_account.from_account_id.clone(),
+            ..same_account
+        };
+        assert!(service.validate(&same_account).is_err());
+        Ok(())
+    }
+
+    fn service() -> TransferService {
+        let pool = pool();
+        let transaction_repo = TransactionRepository::new(&pool);
+        let account_repo = AccountRepository::new(&pool);
+        let asset_service =
+            AssetService::new(&AssetRepository::new(&pool), &AssetConf { admins: vec![] });
+        let lot_service = LotService::new(
+            &LotRepository::new(&pool),
+            &LotMatchRepository::new(&pool),
+            &account_repo,
+            &transaction_repo,
+        );
+        TransferService::new(
+            &transaction_repo,
+            &account_repo,
+            &TransactionService::new(
+                &transaction_repo,
+                &PortfolioSnapshotRepository::new(&pool),
+                &asset_service,
+                &lot_service,
+            ),
+            &ExchangeRateService::new(
+                &ExchangeRateRepository::new(&pool),
+                &RateCacheConf { ttl_secs: 60 },
+            ),
+        )
+    }
+
+    fn transfer(asset: Option<&str>, quantity: f64, from: &str, to: &str, rate: f64) -> Transfer {
+        Transfer {
+            id: Id::new(),
+            from_account_id: Id::new(),
+            to_account_id: Id::new(),
+            asset: asset.map(|it| it.into()),
+            quantity,
+            from_currency: from.into(),
+            to_currency: to.into(),
+            rate,
+            fee: 1.0,
+            time: Utc.ymd(2021, 8, 1).and_hms(12, 0, 0),
+            from_transaction_id: Id::new(),
+            to_transaction_id: Id::new(),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{
+    model::{ExchangeRate, Holding, HoldingValue, PortfolioValue, Transaction, TransactionType},
+    repository::TransactionRepository,
+    service::{ExchangeRateService, HoldingService},
+};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[derive(Clone)]
+pub struct ValuationService {
+    transaction_repo: TransactionRepository,
+    rate_service: ExchangeRateService,
+}
+
+impl ValuationService {
+    pub fn new(
+        transaction_repo: &TransactionRepository,
+        rate_service: &ExchangeRateService,
+    ) -> ValuationService {
+        ValuationService {
+            transaction_repo: transaction_repo.clone(),
+            rate_service: rate_service.clone(),
+        }
+    }
+
+    /// Values all the user's accounts
+    pub fn value_by_username(
+        &self,
+        username: &str,
+        currency: &str,
+        date: Option<NaiveDate>,
+    ) -> Result<PortfolioValue> {
+        let transactions = self.transaction_repo.select_by_username(username)?;
+        let holdings = HoldingService::replay(&transactions, date);
+        self.value(&holdings, &transactions, currency, date)
+    }
+
+    /// Prices come from the exchange rates of the assets. Assets which don't have any rates,
+    /// such as securities, fall back to the last trade price found in the ledger.
+    pub fn value(
+        &self,
+        holdings: &[Holding],
+        transactions: &[Transaction],
+        currency: &str,
+        date: Option<NaiveDate>,
+    ) -> Result<PortfolioValue> {
+        let mut rates = vec![];
+        let mut values = vec![];
+
+        for holding in holdings {
+            let price = self.price(&holding.asset, transactions, currency, date, &mut rates)?;
+
+            values.push(HoldingValue {
+                asset: holding.asset.clone(),
+                quantity: holding.quantity,
+                price,
+                value: price.map(|it| it * holding.quantity),
+            });
+        }
+
+        Ok(PortfolioValue {
+            currency: currency.into(),
+            date,
+            total: values.iter().filter_map(|it| it.value).sum(),
+            holdings: values,
+            rates,
+        })
+    }
+
+    /// Unit price of an asset in a given currency, see `value`
+    pub fn price(
+        &self,
+        asset: &str,
+        transactions: &[Transaction],
+        currency: &str,
+        date: Option<NaiveDate>,
+        rates: &mut Vec<ExchangeRate>,
+    ) -> Result<Option<f64>> {
+        if let Some(rate) = self.rate(asset, currency, date, rates)? {
+            return Ok(Some(rate));
+        }
+
+        let end = date.map(|it| HoldingService::end_of_day(&it));
+
+        let trade = Self::last_trade(asset, transactions, end);
+
+        let (trade_price, trade_currency) = match trade {
+            Some(trade) => trade,
+            None => return Ok(None),
+        };
+
+        match self.rate(trade_currency, currency, date, rates)? {
+            Some(rate) => {
+                let trade_rate = ExchangeRate {
+                    quote: asset.into(),
+                    base: trade_currency.clone(),
+                    rate: trade_price,
+                };
+
+                if !rates.contains(&trade_rate) {
+                    rates.push(trade_rate);
+                }
+
+                Ok(Some(trade_price * rate))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Last unit price paid for an asset, adjusted for the splits and ticker changes which
+    /// came after it. The same action recorded in several accounts is applied only once.
+    fn last_trade<'a>(
+        asset: &str,
+        transactions: &'a [Transaction],
+        end: Option<DateTime<Utc>>,
+    ) -> Option<(f64, &'a String)> {
+        let mut asset = asset;
+        let mut factor = 1.0;
+        let mut applied = vec![];
+
+        for transaction in transactions
+            .iter()
+            .rev()
+            .filter(|it| end.map(|end| it.time < end).unwrap_or(true))
+        {
+            let transaction_asset = match transaction.asset.as_deref() {
+                Some(transaction_asset) => transaction_asset,
+                None => continue,
+            };
+
+            let is_applicable = match transaction.transaction_type {
+                TransactionType::Split => transaction_asset == asset,
+                TransactionType::TickerChange => transaction.new_asset.as_deref() == Some(asset),
+                TransactionType::SpinOff => false,
+                _ if transaction_asset == asset => match transaction.price {
+                    Some(price) => return Some((price * factor, &transaction.currency)),
+                    None => false,
+                },
+                _ => false,
+            };
+
+            if !is_applicable {
+                continue;
+            }
+
+            let action = (
+                transaction.transaction_type,
+                transaction_asset,
+                transaction.time.date(),
+            );
+
+            if !applied.contains(&action) {
+                applied.push(action);
+                factor /= transaction.quantity;
+            }
+
+            asset = transaction_asset;
+        }
+
+        None
+    }
+
+    /// Looks up a rate and remembers it unless it's an identity
+    pub fn rate(
+        &self,
+        quote: &str,
+        base: &str,
+        date: Option<NaiveDate>,
+        rates: &mut Vec<ExchangeRate>,
+    ) -> Result<Option<f64>> {
+        if quote == base {
+            return Ok(Some(1.0));
+        }
+
+        let rate = match date {
+            Some(date) => self
+                .rate_service
+                .get_by_quote_and_base_and_date(quote, base, &date)?,
+            None => self.rate_service.get_by_quote_and_base(quote, base)?,
+        };
+
+        Ok(rate.map(|rate| {
+            let value = rate.rate;
+
+            if !rates.contains(&rate) {
+                rates.push(rate);
+            }
+
+            value
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{ExchangeRate, Holding, HoldingValue, Id, Transaction, TransactionType},
+        repository::{ExchangeRateRepository, TransactionRepository},
+        service::{exchange_rate::RateCacheConf, ExchangeRateService, ValuationService},
+        test::pool,
+    };
+    use anyhow::Result;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn value() -> Result<()> {
+        let pool = pool();
+        let rate_repo = ExchangeRateRepository::new(&pool);
+        let service = ValuationService::new(
+            &TransactionRepository::new(&pool),
+            &ExchangeRateService::new(&rate_repo, &RateCacheConf { ttl_secs: 60 }),
+        );
+        let btc_eur = rate("BTC", "EUR", 40000.0);
+        let usd_eur = rate("USD", "EUR", 0.8);
+        rate_repo.insert_or_replace(&btc_eur)?;
+        rate_repo.insert_or_replace(&usd_eur)?;
+
+        let holdings = vec![
+            holding("BTC", 0.5),
+            holding("EUR", 100.0),
+            holding("USD", 1000.0),
+            holding("VWCE", 10.0),
+            holding("XAU", 1.0),
+        ];
+        let transactions = vec![trade("VWCE", 95.0, 1), trade("VWCE", 100.0, 2)];
+
+        let res = service.value(&holdings, &transactions, "EUR", None)?;
+
+        assert_eq!(
+            vec![
+                holding_value("BTC", 0.5, Some(40000.0)),
+                holding_value("EUR", 100.0, Some(1.0)),
+                holding_value("USD", 1000.0, Some(0.8)),
+                holding_value("VWCE", 10.0, Some(100.0 * 0.8)),
+                holding_value("XAU", 1.0, None),
+            ],
+            res.holdings
+        );
+        assert_eq!(20000.0 + 100.0 + 800.0 + 800.0, res.total);
+        assert_eq!(
+            vec![btc_eur, usd_eur, rate("VWCE", "USD", 100.0)],
+            res.rates
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn value_historical() -> Result<()> {
+        let pool = pool();
+        let rate_repo = ExchangeRateRepository::new(&pool);
+        let service = ValuationService::new(
+            &TransactionRepository::new(&pool),
+            &ExchangeRateService::new(&rate_repo, &RateCacheConf { ttl_secs: 60 }),
+        );
+        let date = NaiveDate::from_ymd(2021, 8, 1);
+        rate_repo.insert_or_replace_history(&rate("BTC", "EUR", 30000.0), &date)?;
+        rate_repo.insert_or_replace(&rate("BTC", "EUR", 40000.0))?;
+        let transactions = vec![trade("VWCE", 95.0, 1), trade("VWCE", 100.0, 2)];
+        let holdings = vec![holding("BTC", 1.0), holding("VWCE", 1.0)];
+
+        let res = service.value(&holdings, &transactions, "USD", Some(date))?;
+        assert_eq!(holding_value("VWCE", 1.0, Some(95.0)), res.holdings[1]);
+
+        let res = service.value(&holdings, &transactions, "EUR", Some(date))?;
+        assert_eq!(holding_value("BTC", 1.0, Some(30000.0)), res.holdings[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn value_after_corporate_actions() -> Result<()> {
+        let pool = pool();
+        let service = ValuationService::new(
+            &TransactionRepository::new(&pool),
+            &ExchangeRateService::new(
+                &ExchangeRateRepository::new(&pool),
+                &RateCacheConf { ttl_secs: 60 },
+            ),
+        );
+        let action =
+            |transaction_type, asset: &str, new_asset: Option<&str>, ratio, day| Transaction {
+                transaction_type,
+                asset: Some(asset.into()),
+                new_asset: new_asset.map(|it| it.into()),
+                quantity: ratio,
+                price: None,
+                ..trade(asset, 0.0, day)
+            };
+        let split = action(TransactionType::Split, "OLD", None, 4.0, 2);
+        let transactions = vec![
+            trade("OLD", 100.0, 1),
+            split.clone(),
+            // Same split recorded in another account
+            Transaction {
+                account_id: Id::new(),
+                ..split
+            },
+            action(TransactionType::TickerChange, "OLD", Some("NEW"), 1.0, 3),
+        ];
+
+        let res = service.value(&[holding("NEW", 4.0)], &transactions, "USD", None)?;
+        assert_eq!(holding_value("NEW", 4.0, Some(25.0)), res.holdings[0]);
+        Ok(())
+    }
+
+    fn rate(quote: &str, base: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            quote: quote.into(),
+            base: base.into(),
+            rate,
+        }
+    }
+
+    fn holding(asset: &str, quantity: f64) -> Holding {
+        Holding {
+            asset: asset.into(),
+            quantity,
+        }
+    }
+
+    fn holding_value(asset: &str, quantity: f64, price: Option<f64>) -> HoldingValue {
+        HoldingValue {
+            asset: asset.into(),
+            quantity,
+            price,
+            value: price.map(|it| it * quantity),
+        }
+    }
+
+    fn trade(asset: &str, price: f64, day: u32) -> Transaction {
+        Transaction {
+            id: Id::new(),
+            account_id: Id::new(),
+            transaction_type: TransactionType::Buy,
+            asset: Some(asset.into()),
+            quantity: 1.0,
+            price: Some(price),
+            currency: "USD".into(),
+            fee: 0.0,
+            time: Utc.ymd(2021, 8, day).and_hms(12, 0, 0),
+            lot_id: None,
+            external_id: None,
+            new_asset: None,
+            transfer_id: None,
+            description: None,
+            category_id: None,
+            tags: vec![],
+        }
+    }
+}
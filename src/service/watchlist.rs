@@ -0,0 +1,123 @@
+use crate::{
+    model::{Id, Quote, Watchlist, WatchlistQuotes},
+    repository::WatchlistRepository,
+    service::{AssetService, ExchangeRateService},
+};
+use anyhow::{ensure, Result};
+use chrono::NaiveDate;
+
+#[derive(Clone)]
+pub struct WatchlistService {
+    repo: WatchlistRepository,
+    asset_service: AssetService,
+    rate_service: ExchangeRateService,
+}
+
+impl WatchlistService {
+    pub fn new(
+        repo: &WatchlistRepository,
+        asset_service: &AssetService,
+        rate_service: &ExchangeRateService,
+    ) -> WatchlistService {
+        WatchlistService {
+            repo: repo.clone(),
+            asset_service: asset_service.clone(),
+            rate_service: rate_service.clone(),
+        }
+    }
+
+    pub fn insert(&self, watchlist: &Watchlist) -> Result<()> {
+        self.repo.insert(watchlist)
+    }
+
+    pub fn update(&self, watchlist: &Watchlist) -> Result<()> {
+        self.repo.update(watchlist)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.repo.delete(id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Watchlist>> {
+        self.repo.select_by_username(username)
+    }
+
+    /// Other users' watchlists are treated as non-existent
+    pub fn select_owned(&self, id: &Id, username: &str) -> Result<Option<Watchlist>> {
+        Ok(self
+            .repo
+            .select_by_id(id)?
+            .filter(|it| it.username == username))
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, watchlist: &Watchlist) -> Result<()> {
+        ensure!(
+            !watchlist.name.trim().is_empty(),
+            "Watchlist name can't be empty"
+        );
+
+        for (i, asset) in watchlist.assets.iter().enumerate() {
+            ensure!(
+                !watchlist.assets[..i].contains(asset),
+                "Asset {} is listed twice",
+                asset
+            );
+            ensure!(
+                self.asset_service.exists(asset)?,
+                "Unknown asset: {}",
+                asset
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Latest prices in a given currency and their change since the end of the previous
+    /// day. Rates are converted the same way as everywhere else, through the inverse or
+    /// EUR if there's no direct rate.
+    pub fn quotes(
+        &self,
+        watchlist: &Watchlist,
+        currency: &str,
+        today: &NaiveDate,
+    ) -> Result<WatchlistQuotes> {
+        let previous_date = today.pred();
+        let mut quotes = vec![];
+
+        for asset in &watchlist.assets {
+            let price = self
+                .rate_service
+                .get_by_quote_and_base(asset, currency)?
+                .map(|it| it.rate);
+            let previous_price = self
+                .rate_service
+                .get_by_quote_and_base_and_date(asset, currency, &previous_date)?
+                .map(|it| it.rate);
+            let change = match (price, previous_price) {
+                (Some(price), Some(previous_price)) => Some(price - previous_price),
+                _ => None,
+            };
+
+            quotes.push(Quote {
+                asset: asset.clone(),
+                name: self.asset_service.select_by_code(asset)?.map(|it| it.name),
+                price,
+                previous_price,
+                change,
+                change_percent: change
+                    .zip(previous_price)
+                    .filter(|(_, previous_price)| *previous_price != 0.0)
+                    .map(|(change, previous_price)| change / previous_price),
+            });
+        }
+
+        Ok(WatchlistQuotes {
+            watchlist_id: watchlist.id.clone(),
+            name: watchlist.name.clone(),
+            currency: currency.into(),
+            previous_date,
+            quotes,
+        })
+    }
+}
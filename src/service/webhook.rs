@@ -0,0 +1,477 @@
+use crate::{
+    model::{DeliveryStatus, ExchangeRate, Id, Webhook, WebhookDelivery, WebhookEvent},
+    provider::SyncListener,
+    repository::{WebhookDeliveryRepository, WebhookRepository},
+};
+use anyhow::{ensure, Error, Result};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::{
+    net::{IpAddr, ToSocketAddrs},
+    sync::Arc,
+    time,
+};
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{info, warn};
+
+#[derive(Clone, Deserialize)]
+pub struct WebhookConf {
+    pub max_attempts: i64,
+    pub retry_delay_secs: i64,
+    pub dispatch_interval_secs: u64,
+    /// Allows webhooks to private, loopback and link-local addresses. Only enable it when
+    /// every user is trusted to reach the server's network.
+    #[serde(default)]
+    pub allow_private_urls: bool,
+}
+
+#[derive(Clone)]
+pub struct WebhookService {
+    repo: WebhookRepository,
+    delivery_repo: WebhookDeliveryRepository,
+    conf: WebhookConf,
+    client: reqwest::Client,
+    /// Serializes dispatching, which runs from the schedule and from the sync listeners
+    dispatching: Arc<Mutex<()>>,
+}
+
+/// Retries back off exponentially up to a day
+const MAX_RETRY_DELAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Serialize)]
+struct Envelope<'a, T: Serialize> {
+    id: &'a Id,
+    event: WebhookEvent,
+    created_at: &'a chrono::DateTime<Utc>,
+    data: T,
+}
+
+impl WebhookService {
+    pub fn new(
+        repo: &WebhookRepository,
+        delivery_repo: &WebhookDeliveryRepository,
+        conf: &WebhookConf,
+    ) -> WebhookService {
+        // A redirect could point to an address which was never checked
+        let redirect = if conf.allow_private_urls {
+            reqwest::redirect::Policy::default()
+        } else {
+            reqwest::redirect::Policy::none()
+        };
+
+        WebhookService {
+            repo: repo.clone(),
+            delivery_repo: delivery_repo.clone(),
+            conf: conf.clone(),
+            client: reqwest::Client::builder()
+                .timeout(time::Duration::from_secs(10))
+                .redirect(redirect)
+                .build()
+                .unwrap(),
+            dispatching: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn insert(&self, webhook: &Webhook) -> Result<()> {
+        self.repo.insert(webhook)
+    }
+
+    pub fn update(&self, webhook: &Webhook) -> Result<()> {
+        self.repo.update(webhook)
+    }
+
+    pub fn delete(&self, id: &Id) -> Result<()> {
+        self.delivery_repo.delete_by_webhook_id(id)?;
+        self.repo.delete(id)
+    }
+
+    pub fn select_by_username(&self, username: &str) -> Result<Vec<Webhook>> {
+        self.repo.select_by_username(username)
+    }
+
+    pub fn select_by_id(&self, id: &Id) -> Result<Option<Webhook>> {
+        self.repo.select_by_id(id)
+    }
+
+    pub fn select_deliveries(&self, webhook_id: &Id) -> Result<Vec<WebhookDelivery>> {
+        self.delivery_repo.select_by_webhook_id(webhook_id)
+    }
+
+    /// Checks user input, returned errors are safe to show to the user
+    pub fn validate(&self, webhook: &Webhook) -> Result<()> {
+        ensure!(
+            webhook.url.starts_with("http://") || webhook.url.starts_with("https://"),
+            "Webhook URL must be an HTTP(S) URL"
+        );
+        ensure!(
+            !webhook.events.is_empty(),
+            "Webhook should subscribe to at least one event"
+        );
+
+        if !self.conf.allow_private_urls {
+            let addrs = Self::resolve(&webhook.url)
+                .map_err(|_| Error::msg("Webhook URL host can't be resolved"))?;
+            ensure!(
+                addrs.iter().all(Self::is_public),
+                "Webhook URL must point to a public address"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Addresses of the URL's host. Blocking, since it may query DNS.
+    fn resolve(url: &str) -> Result<Vec<IpAddr>> {
+        let url = reqwest::Url::parse(url)?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::msg("URL has no host"))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        // IPv6 hosts are bracketed in URLs
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addrs: Vec<IpAddr> = (host, port).to_socket_addrs()?.map(|it| it.ip()).collect();
+        ensure!(!addrs.is_empty(), "URL host has no addresses");
+        Ok(addrs)
+    }
+
+    fn is_public(ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                !(ip.is_private()
+                    || ip.is_loopback()
+                    || ip.is_link_local()
+                    || ip.is_unspecified()
+                    || ip.is_broadcast()
+                    || ip.is_multicast()
+                    || ip.is_documentation()
+                    // Shared address space (100.64.0.0/10)
+                    || (a == 100 && (b & 0b1100_0000) == 64)
+                    || a == 0)
+            }
+            IpAddr::V6(ip) => {
+                if let Some(ip) = ip.to_ipv4() {
+                    return Self::is_public(&IpAddr::V4(ip));
+                }
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10)
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        }
+    }
+
+    pub fn generate_secret() -> String {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        hex::encode(secret)
+    }
+
+    /// Hex encoded HMAC-SHA256 of a payload
+    pub fn sign(secret: &str, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Queues an event for every matching webhook. Events without a username are
+    /// delivered to all the users who subscribed to them.
+    pub fn enqueue<T: Serialize>(
+        &self,
+        username: Option<&str>,
+        event: WebhookEvent,
+        data: &T,
+    ) -> Result<()> {
+        let webhooks = match username {
+            Some(username) => self.repo.select_by_username(username)?,
+            None => self.repo.select_all()?,
+        };
+
+        for webhook in webhooks.iter().filter(|it| it.events.contains(&event)) {
+            self.enqueue_to(webhook, event, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues an event for a single webhook, whether it subscribed to it or not
+    pub fn enqueue_to<T: Serialize>(
+        &self,
+        webhook: &Webhook,
+        event: WebhookEvent,
+        data: &T,
+    ) -> Result<()> {
+        let id = Id::new();
+        let created_at = Utc::now();
+        let payload = serde_json::to_string(&Envelope {
+            id: &id,
+            event,
+            created_at: &created_at,
+            data,
+        })?;
+
+        self.delivery_repo.insert(&WebhookDelivery {
+            id,
+            webhook_id: webhook.id.clone(),
+            event,
+            payload,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: created_at,
+            last_error: None,
+            created_at,
+        })
+    }
+
+    /// Attempts all the pending deliveries which are due
+    pub async fn dispatch(&self) -> Result<()> {
+        let _guard = self.dispatching.lock().await;
+
+        for delivery in self.delivery_repo.select_due(&Utc::now())? {
+            let webhook = self.repo.select_by_id(&delivery.webhook_id)?;
+            let res = match &webhook {
+                Some(webhook) => self.send(webhook, &delivery).await,
+                None => Err(Error::msg("Webhook no longer exists")),
+            };
+            let attempts = delivery.attempts + 1;
+
+            let delivery = match res {
+                Ok(_) => WebhookDelivery {
+                    status: DeliveryStatus::Delivered,
+                    attempts,
+                    last_error: None,
+                    ..delivery
+                },
+                Err(e) => {
+                    warn!(delivery = %delivery.id, attempts, ?e, "Webhook delivery failed");
+                    let status = if webhook.is_none() || attempts >= self.conf.max_attempts {
+                        DeliveryStatus::Failed
+                    } else {
+                        DeliveryStatus::Pending
+                    };
+                    let delay = Self::retry_delay(self.conf.retry_delay_secs, attempts);
+                    WebhookDelivery {
+                        status,
+                        attempts,
+                        next_attempt_at: Utc::now() + Duration::seconds(delay),
+                        last_error: Some(e.to_string()),
+                        ..delivery
+                    }
+                }
+            };
+
+            self.delivery_repo.update(&delivery)?;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps dispatching the delivery queue, so failed deliveries are retried
+    pub async fn schedule(&self) -> Result<()> {
+        info!(
+            interval_secs = self.conf.dispatch_interval_secs,
+            "Scheduling webhook dispatch..."
+        );
+
+        loop {
+            if let Err(e) = self.dispatch().await {
+                warn!(?e, "Failed to dispatch webhooks");
+            }
+
+            sleep(time::Duration::from_secs(self.conf.dispatch_interval_secs)).await;
+        }
+    }
+
+    /// Delay before the next attempt, after the given number of attempts failed
+    fn retry_delay(retry_delay_secs: i64, attempts: i64) -> i64 {
+        2i64.checked_pow((attempts - 1).max(0) as u32)
+            .map_or(MAX_RETRY_DELAY_SECS, |factor| {
+                retry_delay_secs.saturating_mul(factor)
+            })
+            .min(MAX_RETRY_DELAY_SECS)
+    }
+
+    async fn send(&self, webhook: &Webhook, delivery: &WebhookDelivery) -> Result<()> {
+        // The host may resolve differently than when the webhook was validated
+        if !self.conf.allow_private_urls {
+            let url = webhook.url.clone();
+            let addrs = tokio::task::spawn_blocking(move || Self::resolve(&url)).await??;
+            ensure!(
+                addrs.iter().all(Self::is_public),
+                "Webhook URL no longer points to a public address"
+            );
+        }
+
+        self.client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Pfd-Event", delivery.event.to_string())
+            .header("X-Pfd-Delivery", delivery.id.to_string())
+            .header(
+                "X-Pfd-Signature",
+                format!("sha256={}", Self::sign(&webhook.secret, &delivery.payload)),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl SyncListener for WebhookService {
+    async fn on_sync(&self, provider: &str, rates: &[ExchangeRate]) -> Result<()> {
+        let data = json!({ "provider": provider, "rates": rates });
+        self.enqueue(None, WebhookEvent::RateUpdated, &data)?;
+        self.dispatch().await
+    }
+
+    async fn on_sync_failed(&self, provider: &str, error: &Error) -> Result<()> {
+        let data = json!({ "provider": provider, "error": error.to_string() });
+        self.enqueue(None, WebhookEvent::SyncFailed, &data)?;
+        self.dispatch().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model::{DeliveryStatus, Id, Webhook, WebhookEvent},
+        repository::{WebhookDeliveryRepository, WebhookRepository},
+        service::{webhook::WebhookConf, WebhookService},
+        test::pool,
+    };
+    use anyhow::Result;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn sign() {
+        // echo -n '{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            "aa9e2e3575f5d7098b6caccd790888c36d5fdb63342a73bada2d6a51747a8494",
+            WebhookService::sign("secret", r#"{"a":1}"#)
+        );
+    }
+
+    #[test]
+    fn retry_delay() {
+        assert_eq!(60, WebhookService::retry_delay(60, 1));
+        assert_eq!(240, WebhookService::retry_delay(60, 3));
+        assert_eq!(24 * 60 * 60, WebhookService::retry_delay(60, 20));
+        assert_eq!(24 * 60 * 60, WebhookService::retry_delay(60, 100));
+    }
+
+    #[test]
+    fn validate_private_url() {
+        let pool = pool();
+        let service = WebhookService::new(
+            &WebhookRepository::new(&pool),
+            &WebhookDeliveryRepository::new(&pool),
+            &conf(),
+        );
+        let with_url = |url: &str| Webhook {
+            url: url.into(),
+            ..webhook("test", vec![WebhookEvent::AlertFired])
+        };
+        for url in &[
+            "http://localhost/hook",
+            "http://127.0.0.1:8000/hook",
+            "http://10.0.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(service.validate(&with_url(url)).is_err(), "{}", url);
+        }
+        assert!(service
+            .validate(&with_url("https://93.184.216.34/hook"))
+            .is_ok());
+
+        let service = WebhookService::new(
+            &WebhookRepository::new(&pool),
+            &WebhookDeliveryRepository::new(&pool),
+            &WebhookConf {
+                allow_private_urls: true,
+                ..conf()
+            },
+        );
+        assert!(service.validate(&with_url("http://localhost/hook")).is_ok());
+    }
+
+    #[test]
+    fn enqueue() -> Result<()> {
+        let pool = pool();
+        let repo = WebhookRepository::new(&pool);
+        let service = WebhookService::new(&repo, &WebhookDeliveryRepository::new(&pool), &conf());
+        let subscribed = webhook("test", vec![WebhookEvent::AlertFired]);
+        let not_subscribed = webhook("test", vec![WebhookEvent::SyncFailed]);
+        let foreign = webhook("test2", vec![WebhookEvent::AlertFired]);
+        repo.insert(&subscribed)?;
+        repo.insert(&not_subscribed)?;
+        repo.insert(&foreign)?;
+
+        service.enqueue(Some("test"), WebhookEvent::AlertFired, &json!({ "a": 1 }))?;
+
+        let deliveries = service.select_deliveries(&subscribed.id)?;
+        assert_eq!(1, deliveries.len());
+        assert_eq!(DeliveryStatus::Pending, deliveries[0].status);
+        let payload: Value = serde_json::from_str(&deliveries[0].payload)?;
+        assert_eq!("alert_fired", payload["event"]);
+        assert_eq!(1, payload["data"]["a"]);
+        assert!(service.select_deliveries(&not_subscribed.id)?.is_empty());
+        assert!(service.select_deliveries(&foreign.id)?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dispatch_retry() -> Result<()> {
+        let pool = pool();
+        let repo = WebhookRepository::new(&pool);
+        let service = WebhookService::new(&repo, &WebhookDeliveryRepository::new(&pool), &conf());
+        let webhook = Webhook {
+            url: "http://127.0.0.1:1/hook".into(),
+            ..webhook("test", vec![WebhookEvent::RateUpdated])
+        };
+        repo.insert(&webhook)?;
+
+        service.enqueue(None, WebhookEvent::RateUpdated, &json!({}))?;
+        service.dispatch().await?;
+
+        let delivery = &service.select_deliveries(&webhook.id)?[0];
+        assert_eq!(DeliveryStatus::Pending, delivery.status);
+        assert_eq!(1, delivery.attempts);
+        assert!(delivery.last_error.is_some());
+        assert!(delivery.next_attempt_at > delivery.created_at);
+        Ok(())
+    }
+
+    fn conf() -> WebhookConf {
+        WebhookConf {
+            max_attempts: 3,
+            retry_delay_secs: 60,
+            dispatch_interval_secs: 30,
+            allow_private_urls: false,
+        }
+    }
+
+    fn webhook(username: &str, events: Vec<WebhookEvent>) -> Webhook {
+        Webhook {
+            id: Id::new(),
+            username: username.into(),
+            url: "https://example.com/hook".into(),
+            secret: "secret".into(),
+            events,
+        }
+    }
+}
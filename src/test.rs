@@ -4,6 +4,7 @@ use crate::{
     db::migrate_to_latest,
     model::{AuthToken, User},
     repository::{AuthTokenRepository, UserRepository},
+    service::{asset::AssetConf, webhook::WebhookConf},
 };
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
@@ -25,7 +26,20 @@ pub fn client() -> Client {
     let db_url = format!("file::testdb_{}:?mode=memory&cache=shared", db_name);
 
     let conf = Conf::new().unwrap();
-    let conf = Conf { db_url, ..conf };
+    // Tests can't resolve hosts, so webhook addresses aren't checked
+    let webhooks = WebhookConf {
+        allow_private_urls: true,
+        ..conf.webhooks
+    };
+    let assets = AssetConf {
+        admins: vec!["test".into()],
+    };
+    let conf = Conf {
+        db_url,
+        assets,
+        webhooks,
+        ..conf
+    };
 
     let rocket =
         attach_payload(rocket::build(), conf).attach(AdHoc::on_request("Authorize", |req, _| {